@@ -1,5 +1,5 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, Mint, TokenAccount, Transfer};
+use anchor_spl::token::{self, CloseAccount, Token, Mint, TokenAccount, Transfer};
 
 declare_id!("EYDWvx95gq6GhniDGHMHbn6DsigFhcWGHvHgbbxzuqQq");
 
@@ -8,10 +8,114 @@ pub const NODE_SEED: &[u8] = b"node";
 pub const SESSION_SEED: &[u8] = b"session";
 pub const SUBSCRIPTION_SEED: &[u8] = b"subscription";
 pub const TREASURY_SEED: &[u8] = b"treasury";
-
-// Revenue split: 80% to node provider, 20% to contract treasury
-pub const PROVIDER_SHARE_PERCENT: u64 = 80;
-pub const TREASURY_SHARE_PERCENT: u64 = 20;
+pub const NODE_PRICING_SEED: &[u8] = b"node_pricing";
+pub const USER_VAULT_SEED: &[u8] = b"user_vault";
+pub const DELEGATION_SEED: &[u8] = b"delegation";
+pub const OPERATOR_SEED: &[u8] = b"operator";
+pub const INSURANCE_FUND_SEED: &[u8] = b"insurance_fund";
+pub const SLASH_RECORD_SEED: &[u8] = b"slash_record";
+pub const PROVIDER_PROFILE_SEED: &[u8] = b"provider_profile";
+pub const ARBITER_REGISTRY_SEED: &[u8] = b"arbiter_registry";
+pub const DISPUTE_PANEL_SEED: &[u8] = b"dispute_panel";
+pub const DISPUTE_SEED: &[u8] = b"dispute";
+pub const PROGRAM_CONFIG_SEED: &[u8] = b"program_config";
+pub const MULTISIG_SEED: &[u8] = b"multisig";
+pub const MULTISIG_PROPOSAL_SEED: &[u8] = b"multisig_proposal";
+pub const GOVERNANCE_SEED: &[u8] = b"governance";
+pub const GOVERNANCE_PROPOSAL_SEED: &[u8] = b"governance_proposal";
+pub const GOVERNANCE_VOTE_SEED: &[u8] = b"governance_vote";
+
+// Operator permission bits
+pub const OPERATOR_PERMISSION_CLAIM: u8 = 1 << 0;
+pub const OPERATOR_PERMISSION_HEARTBEAT: u8 = 1 << 1;
+pub const OPERATOR_PERMISSION_NODE_UPDATE: u8 = 1 << 2;
+pub const OPERATOR_PERMISSION_ALL: u8 =
+    OPERATOR_PERMISSION_CLAIM | OPERATOR_PERMISSION_HEARTBEAT | OPERATOR_PERMISSION_NODE_UPDATE;
+
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+// Fixed-point scale for Provider.acc_reward_per_share
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
+
+// Minimum notice before a rotated WireGuard key takes over
+pub const MIN_WG_KEY_OVERLAP_SECONDS: i64 = 60 * 60;
+
+// Vault daily spending window
+pub const VAULT_DAY_SECONDS: i64 = 24 * 60 * 60;
+
+// Max SPL mints a node can accept
+pub const MAX_ACCEPTED_MINTS: usize = 8;
+
+// Arbitration: registry and per-dispute panel bounds
+pub const MAX_ARBITERS: usize = 32;
+pub const MAX_DISPUTE_PANEL: usize = 7;
+pub const MAX_DISPUTE_EVIDENCE: usize = 8;
+
+// Default dispute bonds: the larger of a flat amount and a share of SOL escrow
+pub const DEFAULT_USER_BOND_LAMPORTS: u64 = 10_000_000;        // 0.01 SOL
+pub const DEFAULT_USER_BOND_BPS: u16 = 500;
+pub const DEFAULT_PROVIDER_BOND_LAMPORTS: u64 = 50_000_000;    // 0.05 SOL
+pub const DEFAULT_PROVIDER_BOND_BPS: u16 = 1_000;
+
+// ProgramConfig defaults, applied by initialize_program_config
+pub const DEFAULT_TREASURY_SHARE_BPS: u16 = 2_000;            // 80% provider / 20% treasury
+pub const DEFAULT_WEEKLY_PRICE_LAMPORTS: u64 = 30_000_000;    // 0.03 SOL
+pub const DEFAULT_MONTHLY_PRICE_LAMPORTS: u64 = 100_000_000;  // 0.1 SOL
+pub const DEFAULT_YEARLY_PRICE_LAMPORTS: u64 = 600_000_000;   // 0.6 SOL
+pub const DEFAULT_WEEKLY_DURATION_SECONDS: i64 = 7 * 24 * 60 * 60;
+pub const DEFAULT_MONTHLY_DURATION_SECONDS: i64 = 30 * 24 * 60 * 60;
+pub const DEFAULT_YEARLY_DURATION_SECONDS: i64 = 365 * 24 * 60 * 60;
+pub const DEFAULT_EARNINGS_EPOCH_SECONDS: i64 = 7 * 24 * 60 * 60;
+pub const DEFAULT_HEARTBEAT_TIMEOUT_SECONDS: i64 = 5 * 60;     // Silent longer than this = down
+pub const DEFAULT_UNBONDING_PERIOD_SECONDS: i64 = 7 * 24 * 60 * 60; // Unstake / undelegate cooldown
+pub const DEFAULT_HASHCHAIN_GRACE_SECONDS: i64 = 24 * 60 * 60;  // Redemption window after a hash-chain session ends
+// Slashed stake split; the treasury gets the remainder
+pub const DEFAULT_SLASH_USER_BPS: u16 = 5_000;
+pub const DEFAULT_SLASH_INSURANCE_BPS: u16 = 2_000;
+pub const DEFAULT_EXPIRE_CRANK_FEE_LAMPORTS: u64 = 10_000;     // Paid from SOL escrow to whoever cranks expire_session
+pub const DEFAULT_BOND_WINNER_BPS: u16 = 5_000;                // Forfeited bond share to the winner; treasury gets the rest
+// Minimum stake: base + per node + per unit of max_capacity
+pub const DEFAULT_MIN_STAKE_BASE_LAMPORTS: u64 = 100_000_000;          // 0.1 SOL
+pub const DEFAULT_MIN_STAKE_PER_NODE_LAMPORTS: u64 = 50_000_000;       // 0.05 SOL
+pub const DEFAULT_MIN_STAKE_PER_CAPACITY_LAMPORTS: u64 = 1_000_000;    // 0.001 SOL
+
+// ProgramConfig validation ranges
+pub const MAX_TREASURY_SHARE_BPS: u16 = 5_000;
+pub const MIN_PLAN_DURATION_SECONDS: i64 = 24 * 60 * 60;
+pub const MAX_PLAN_DURATION_SECONDS: i64 = 400 * 24 * 60 * 60;
+pub const MIN_EARNINGS_EPOCH_SECONDS: i64 = 24 * 60 * 60;
+pub const MAX_EARNINGS_EPOCH_SECONDS: i64 = 90 * 24 * 60 * 60;
+pub const MIN_HEARTBEAT_TIMEOUT_SECONDS: i64 = 60;
+pub const MAX_HEARTBEAT_TIMEOUT_SECONDS: i64 = 60 * 60;
+pub const MIN_UNBONDING_PERIOD_SECONDS: i64 = 24 * 60 * 60;
+pub const MAX_UNBONDING_PERIOD_SECONDS: i64 = 30 * 24 * 60 * 60;
+pub const MIN_HASHCHAIN_GRACE_SECONDS: i64 = 60 * 60;
+pub const MAX_HASHCHAIN_GRACE_SECONDS: i64 = 7 * 24 * 60 * 60;
+pub const MAX_EXPIRE_CRANK_FEE_LAMPORTS: u64 = 1_000_000;              // 0.001 SOL
+pub const MIN_MIN_STAKE_BASE_LAMPORTS: u64 = 10_000_000;               // 0.01 SOL
+pub const MAX_MIN_STAKE_BASE_LAMPORTS: u64 = 100_000_000_000;          // 100 SOL
+pub const MAX_MIN_STAKE_PER_NODE_LAMPORTS: u64 = 10_000_000_000;       // 10 SOL
+pub const MAX_MIN_STAKE_PER_CAPACITY_LAMPORTS: u64 = 100_000_000;      // 0.1 SOL
+
+// Multisig bounds
+pub const MAX_MULTISIG_OWNERS: usize = 10;
+pub const MIN_TIMELOCK_SECONDS: i64 = 60 * 60;
+pub const MAX_TIMELOCK_SECONDS: i64 = 30 * 24 * 60 * 60;
+
+// Provider governance bounds
+pub const MIN_VOTING_PERIOD_SECONDS: i64 = 24 * 60 * 60;
+pub const MAX_VOTING_PERIOD_SECONDS: i64 = 30 * 24 * 60 * 60;
+pub const STAKE_CHECKPOINTS: usize = 4;
+
+// Account-size caps for strings; ProgramConfig limits may only be tighter
+pub const MAX_ENDPOINT_LEN: usize = 80;
+pub const MAX_EVIDENCE_URI_LEN: usize = 100;
+pub const MAX_DISPLAY_NAME_LEN: usize = 32;
+pub const MAX_WEBSITE_LEN: usize = 64;
+pub const MAX_JURISDICTION_LEN: usize = 3;
+pub const MAX_POLICY_URI_LEN: usize = 200;
+pub const MAX_REGION_LEN: usize = 12;
+pub const MAX_DISPUTE_REASON_LEN: usize = 200;
 
 #[program]
 pub mod dvpn {
@@ -22,10 +126,23 @@ pub mod dvpn {
         provider.authority = ctx.accounts.authority.key();
         provider.node_count = 0;
         provider.stake_lamports = 0;
+        provider.unbonding_lamports = 0;
+        provider.unbonding_unlock_ts = 0;
         provider.reputation_score = 1000; // Start at 1000 (neutral)
         provider.total_uptime_seconds = 0;
         provider.total_sessions = 0;
         provider.total_earnings = 0;
+        provider.total_capacity = 0;
+        provider.delegated_lamports = 0;
+        provider.delegated_shares = 0;
+        provider.delegated_unbonding_lamports = 0;
+        provider.delegated_unbonding_shares = 0;
+        provider.commission_bps = 0;
+        provider.acc_reward_per_share = 0;
+        provider.payout_address = ctx.accounts.authority.key();
+        provider.seed_authority = ctx.accounts.authority.key();
+        provider.pending_authority = Pubkey::default();
+        provider.stake_checkpoints = [StakeCheckpoint::default(); STAKE_CHECKPOINTS];
         provider.bump = ctx.bumps.provider;
         Ok(())
     }
@@ -54,31 +171,61 @@ pub mod dvpn {
             ],
         )?;
 
+        // New stake can't vote on proposals created before it
+        ctx.accounts.provider.record_stake_increase(Clock::get()?.unix_timestamp);
         ctx.accounts.provider.stake_lamports = ctx.accounts.provider.stake_lamports.checked_add(amount_lamports)
             .ok_or(DvpnError::MathOverflow)?;
 
         Ok(())
     }
 
-    pub fn unstake_provider(
-        ctx: Context<UnstakeProvider>,
+    // Phase 1 of unstake: move stake into the unbonding queue. Each request
+    // restarts the cooldown for the whole queued amount.
+    pub fn request_unstake(
+        ctx: Context<RequestUnstake>,
         amount_lamports: u64,
     ) -> Result<()> {
         require!(amount_lamports > 0, DvpnError::InvalidAmount);
 
+        let now = Clock::get()?.unix_timestamp;
         let provider = &mut ctx.accounts.provider;
         require_keys_eq!(provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
         require!(provider.stake_lamports >= amount_lamports, DvpnError::InsufficientStake);
+        require!(
+            provider.stake_lamports - amount_lamports >= provider.required_stake(&ctx.accounts.config),
+            DvpnError::BelowMinimumStake
+        );
+
+        provider.stake_lamports = provider.stake_lamports.saturating_sub(amount_lamports);
+        provider.unbonding_lamports = provider.unbonding_lamports
+            .checked_add(amount_lamports)
+            .ok_or(DvpnError::MathOverflow)?;
+        provider.unbonding_unlock_ts = now + ctx.accounts.config.unbonding_period_seconds;
+
+        Ok(())
+    }
+
+    // Phase 2 of unstake: release unbonded lamports after the cooldown
+    pub fn withdraw_unstaked(ctx: Context<WithdrawUnstaked>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let provider = &mut ctx.accounts.provider;
+        require_keys_eq!(provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+
+        let amount_lamports = provider.unbonding_lamports;
+        require!(amount_lamports > 0, DvpnError::NothingUnbonding);
+        require!(now >= provider.unbonding_unlock_ts, DvpnError::StillUnbonding);
 
         // Transfer SOL from provider PDA -> authority
         **provider.to_account_info().try_borrow_mut_lamports()? -= amount_lamports;
         **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += amount_lamports;
 
-        provider.stake_lamports = provider.stake_lamports.saturating_sub(amount_lamports);
+        provider.unbonding_lamports = 0;
+        provider.unbonding_unlock_ts = 0;
 
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn register_node(
         ctx: Context<RegisterNode>,
         node_id: u64,
@@ -89,8 +236,9 @@ pub mod dvpn {
         max_capacity: u32,
         bandwidth_mbps: u32,              // NEW: Node bandwidth in Mbps
     ) -> Result<()> {
-        require!(endpoint.len() <= 80, DvpnError::StringTooLong);
-        require!(region.len() <= 12, DvpnError::StringTooLong);
+        let config = &ctx.accounts.config;
+        require!(endpoint.len() <= config.max_endpoint_len as usize, DvpnError::StringTooLong);
+        require!(region.len() <= config.max_region_len as usize, DvpnError::StringTooLong);
         require!(bandwidth_mbps > 0, DvpnError::InvalidAmount);
 
         let provider = &mut ctx.accounts.provider;
@@ -103,8 +251,11 @@ pub mod dvpn {
         node.region = region;
         node.price_per_minute_lamports = price_per_minute_lamports;
         node.wg_server_pubkey = wg_server_pubkey;
+        node.next_wg_server_pubkey = [0u8; 32];
+        node.next_wg_key_activation_ts = 0;
         node.max_capacity = max_capacity;
         node.active_sessions = 0;
+        node.unsettled_sessions = 0;
         node.total_uptime_seconds = 0;
         node.total_earnings = 0;
         node.is_active = true;
@@ -114,9 +265,210 @@ pub mod dvpn {
         node.total_bytes_served = 0;
         node.rating_sum = 0;
         node.rating_count = 0;
+        let clock = Clock::get()?;
+        node.last_heartbeat_ts = clock.unix_timestamp;
+        node.last_heartbeat_slot = clock.slot;
         node.bump = ctx.bumps.node;
 
         provider.node_count = provider.node_count.saturating_add(1);
+        provider.total_capacity = provider.total_capacity.saturating_add(max_capacity as u64);
+        require!(provider.meets_minimum_stake(config), DvpnError::BelowMinimumStake);
+        Ok(())
+    }
+
+    // Change node settings in place. Open sessions keep the escrow and end_ts
+    // they were opened with; lowering max_capacity only blocks new sessions.
+    pub fn update_node(
+        ctx: Context<UpdateNode>,
+        endpoint: Option<String>,
+        region: Option<String>,
+        price_per_minute_lamports: Option<u64>,
+        max_capacity: Option<u32>,
+        bandwidth_mbps: Option<u32>,
+        is_active: Option<bool>,
+    ) -> Result<()> {
+        authorize_provider(&ctx.accounts.provider, &ctx.accounts.authority.key(), &ctx.accounts.operator, OPERATOR_PERMISSION_NODE_UPDATE)?;
+
+        let node = &mut ctx.accounts.node;
+
+        if let Some(endpoint) = endpoint {
+            require!(endpoint.len() <= ctx.accounts.config.max_endpoint_len as usize, DvpnError::StringTooLong);
+            node.endpoint = endpoint;
+        }
+        if let Some(region) = region {
+            require!(region.len() <= ctx.accounts.config.max_region_len as usize, DvpnError::StringTooLong);
+            node.region = region;
+        }
+        if let Some(price) = price_per_minute_lamports {
+            node.price_per_minute_lamports = price;
+        }
+        if let Some(bandwidth) = bandwidth_mbps {
+            require!(bandwidth > 0, DvpnError::InvalidAmount);
+            node.bandwidth_mbps = bandwidth;
+        }
+        if let Some(active) = is_active {
+            node.is_active = active;
+        }
+        if let Some(capacity) = max_capacity {
+            let provider = &mut ctx.accounts.provider;
+            provider.total_capacity = provider.total_capacity
+                .saturating_sub(node.max_capacity as u64)
+                .saturating_add(capacity as u64);
+            node.max_capacity = capacity;
+            // Extra capacity needs extra stake
+            require!(provider.meets_minimum_stake(&ctx.accounts.config), DvpnError::BelowMinimumStake);
+        }
+
+        Ok(())
+    }
+
+    // Schedule a WireGuard key rotation. Clients see both keys until
+    // activation_ts; the new key becomes current lazily on the next open/heartbeat.
+    pub fn rotate_wg_key(
+        ctx: Context<UpdateNode>,
+        next_wg_server_pubkey: [u8; 32],
+        activation_ts: i64,
+    ) -> Result<()> {
+        authorize_provider(&ctx.accounts.provider, &ctx.accounts.authority.key(), &ctx.accounts.operator, OPERATOR_PERMISSION_NODE_UPDATE)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            activation_ts >= now.saturating_add(MIN_WG_KEY_OVERLAP_SECONDS),
+            DvpnError::KeyOverlapTooShort
+        );
+
+        let node = &mut ctx.accounts.node;
+        // A previously scheduled key that is already live becomes current first
+        node.promote_wg_key(now);
+        node.next_wg_server_pubkey = next_wg_server_pubkey;
+        node.next_wg_key_activation_ts = activation_ts;
+
+        Ok(())
+    }
+
+    // Decommission a drained node: no open or unclaimed sessions may reference it.
+    // Rent (and the node's pricing account, if any) goes back to the authority.
+    pub fn close_node(ctx: Context<CloseNode>) -> Result<()> {
+        let provider = &mut ctx.accounts.provider;
+        let node = &ctx.accounts.node;
+
+        require_keys_eq!(provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+        require!(node.active_sessions == 0, DvpnError::NodeHasSessions);
+        require!(node.unsettled_sessions == 0, DvpnError::NodeHasSessions);
+
+        provider.node_count = provider.node_count.saturating_sub(1);
+        provider.total_capacity = provider.total_capacity.saturating_sub(node.max_capacity as u64);
+
+        close_if_initialized(&ctx.accounts.node_pricing, &ctx.accounts.authority)?;
+
+        Ok(())
+    }
+
+    // Liveness signal from the node daemon
+    pub fn node_heartbeat(ctx: Context<NodeHeartbeat>) -> Result<()> {
+        authorize_provider(&ctx.accounts.provider, &ctx.accounts.authority.key(), &ctx.accounts.operator, OPERATOR_PERMISSION_HEARTBEAT)?;
+
+        let clock = Clock::get()?;
+        let node = &mut ctx.accounts.node;
+        node.last_heartbeat_ts = clock.unix_timestamp;
+        node.last_heartbeat_slot = clock.slot;
+        node.promote_wg_key(clock.unix_timestamp);
+
+        Ok(())
+    }
+
+    // Resize a Node created before the account layout grew. The old size tells
+    // which fields were missing: a node that never heartbeated is treated as
+    // alive from now, and sessions opened before unsettled_sessions existed
+    // are counted from active_sessions. Other new fields default to zero.
+    pub fn migrate_node(ctx: Context<MigrateNode>, _node_id: u64) -> Result<()> {
+        require_keys_eq!(ctx.accounts.provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+
+        let info = ctx.accounts.node.to_account_info();
+        require_keys_eq!(*info.owner, crate::ID, DvpnError::Unauthorized);
+
+        let old_len = info.data_len();
+        let new_len = 8 + Node::MAX_SIZE;
+        require!(old_len < new_len, DvpnError::AlreadyMigrated);
+
+        // Top up rent for the larger account
+        let rent_due = Rent::get()?.minimum_balance(new_len).saturating_sub(info.lamports());
+        if rent_due > 0 {
+            let ix = anchor_lang::solana_program::system_instruction::transfer(
+                &ctx.accounts.authority.key(),
+                &info.key(),
+                rent_due,
+            );
+            anchor_lang::solana_program::program::invoke(
+                &ix,
+                &[
+                    ctx.accounts.authority.to_account_info(),
+                    info.clone(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+        info.realloc(new_len, true)?;
+
+        let mut node = {
+            let data = info.try_borrow_data()?;
+            Node::try_deserialize(&mut &data[..])?
+        };
+        require_keys_eq!(node.provider, ctx.accounts.provider.key(), DvpnError::Unauthorized);
+
+        if old_len < 8 + Node::HEARTBEAT_LAYOUT_SIZE {
+            let clock = Clock::get()?;
+            node.last_heartbeat_ts = clock.unix_timestamp;
+            node.last_heartbeat_slot = clock.slot;
+        }
+        if old_len < 8 + Node::UNSETTLED_LAYOUT_SIZE {
+            node.unsettled_sessions = node.active_sessions;
+        }
+
+        let mut data = info.try_borrow_mut_data()?;
+        node.try_serialize(&mut &mut data[..])?;
+
+        Ok(())
+    }
+
+    // Add or update an accepted SPL mint and its price per minute (in token base units)
+    pub fn set_accepted_mint(
+        ctx: Context<SetAcceptedMint>,
+        price_per_minute_tokens: u64,
+    ) -> Result<()> {
+        require!(price_per_minute_tokens > 0, DvpnError::InvalidAmount);
+        authorize_provider(&ctx.accounts.provider, &ctx.accounts.authority.key(), &ctx.accounts.operator, OPERATOR_PERMISSION_NODE_UPDATE)?;
+
+        let mint = ctx.accounts.mint.key();
+        let pricing = &mut ctx.accounts.node_pricing;
+        pricing.node = ctx.accounts.node.key();
+        pricing.bump = ctx.bumps.node_pricing;
+
+        if let Some(entry) = pricing.accepted_mints.iter_mut().find(|m| m.mint == mint) {
+            entry.price_per_minute = price_per_minute_tokens;
+        } else {
+            require!(pricing.accepted_mints.len() < MAX_ACCEPTED_MINTS, DvpnError::TooManyMints);
+            pricing.accepted_mints.push(MintPrice {
+                mint,
+                price_per_minute: price_per_minute_tokens,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Stop accepting an SPL mint for new sessions (open sessions are unaffected)
+    pub fn remove_accepted_mint(
+        ctx: Context<RemoveAcceptedMint>,
+        mint: Pubkey,
+    ) -> Result<()> {
+        authorize_provider(&ctx.accounts.provider, &ctx.accounts.authority.key(), &ctx.accounts.operator, OPERATOR_PERMISSION_NODE_UPDATE)?;
+
+        let pricing = &mut ctx.accounts.node_pricing;
+        let before = pricing.accepted_mints.len();
+        pricing.accepted_mints.retain(|m| m.mint != mint);
+        require!(pricing.accepted_mints.len() < before, DvpnError::MintNotAccepted);
+
         Ok(())
     }
 
@@ -129,14 +481,83 @@ pub mod dvpn {
 
         let now = Clock::get()?.unix_timestamp;
         let node = &mut ctx.accounts.node;
+        node.promote_wg_key(now);
+
+        require!(node.is_active, DvpnError::NodeInactive);
+        require!(node.active_sessions < node.max_capacity, DvpnError::NodeAtCapacity);
+        require!(ctx.accounts.provider.meets_minimum_stake(&ctx.accounts.config), DvpnError::BelowMinimumStake);
+
+        let cost = (minutes as u64)
+            .checked_mul(node.price_per_minute_lamports)
+            .ok_or(DvpnError::MathOverflow)?;
+
+        // Transfer SOL from user -> session PDA (escrow)
+        let ix = anchor_lang::solana_program::system_instruction::transfer(
+            &ctx.accounts.user.key(),
+            &ctx.accounts.session.key(),
+            cost,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.user.to_account_info(),
+                ctx.accounts.session.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        let session = &mut ctx.accounts.session;
+        session.init(
+            ctx.accounts.user.key(),
+            node,
+            session_id,
+            now,
+            now + (minutes as i64) * 60,
+            cost,
+            Pubkey::default(),
+            ctx.accounts.user.key(),
+            ctx.bumps.session,
+        );
+
+        node.active_sessions = node.active_sessions.saturating_add(1);
+        node.unsettled_sessions = node.unsettled_sessions.saturating_add(1);
+        ctx.accounts.provider.total_sessions = ctx.accounts.provider.total_sessions.saturating_add(1);
+
+        Ok(())
+    }
+
+    // Hash-chain session: user commits to chain tip Hn and a per-link price.
+    // The provider later redeems links by revealing preimages (see claim_hashchain).
+    pub fn open_hashchain_session(
+        ctx: Context<OpenHashchainSession>,
+        session_id: u64,
+        minutes: u32,
+        chain_tip: [u8; 32],
+        chain_length: u32,
+        price_per_link_lamports: u64,
+    ) -> Result<()> {
+        require!(minutes > 0, DvpnError::InvalidMinutes);
+        require!(chain_length > 0, DvpnError::InvalidAmount);
+        require!(price_per_link_lamports > 0, DvpnError::InvalidAmount);
+
+        let now = Clock::get()?.unix_timestamp;
+        let node = &mut ctx.accounts.node;
+        node.promote_wg_key(now);
 
         require!(node.is_active, DvpnError::NodeInactive);
         require!(node.active_sessions < node.max_capacity, DvpnError::NodeAtCapacity);
+        require!(ctx.accounts.provider.meets_minimum_stake(&ctx.accounts.config), DvpnError::BelowMinimumStake);
 
         let cost = (minutes as u64)
             .checked_mul(node.price_per_minute_lamports)
             .ok_or(DvpnError::MathOverflow)?;
 
+        // The full chain must be payable out of escrow
+        let chain_value = (chain_length as u64)
+            .checked_mul(price_per_link_lamports)
+            .ok_or(DvpnError::MathOverflow)?;
+        require!(chain_value <= cost, DvpnError::InsufficientBalance);
+
         // Transfer SOL from user -> session PDA (escrow)
         let ix = anchor_lang::solana_program::system_instruction::transfer(
             &ctx.accounts.user.key(),
@@ -153,20 +574,23 @@ pub mod dvpn {
         )?;
 
         let session = &mut ctx.accounts.session;
-        session.user = ctx.accounts.user.key();
-        session.node = node.key();
-        session.session_id = session_id;
-        session.start_ts = now;
-        session.end_ts = now + (minutes as i64) * 60;
-        session.escrow_lamports = cost;
-        session.remaining_balance = cost;
-        session.bytes_used = 0;
-        session.last_proof_hash = [0u8; 32];
-        session.state = SessionState::Active;
-        session.payment_token = Pubkey::default(); // SOL payment
-        session.bump = ctx.bumps.session;
+        session.init(
+            ctx.accounts.user.key(),
+            node,
+            session_id,
+            now,
+            now + (minutes as i64) * 60,
+            cost,
+            Pubkey::default(),
+            ctx.accounts.user.key(),
+            ctx.bumps.session,
+        );
+        session.hashchain_tip = chain_tip;
+        session.hashchain_length = chain_length;
+        session.price_per_link_lamports = price_per_link_lamports;
 
         node.active_sessions = node.active_sessions.saturating_add(1);
+        node.unsettled_sessions = node.unsettled_sessions.saturating_add(1);
         ctx.accounts.provider.total_sessions = ctx.accounts.provider.total_sessions.saturating_add(1);
 
         Ok(())
@@ -177,16 +601,24 @@ pub mod dvpn {
         ctx: Context<OpenSessionSpl>,
         session_id: u64,
         minutes: u32,
-        amount_tokens: u64,
     ) -> Result<()> {
         require!(minutes > 0, DvpnError::InvalidMinutes);
-        require!(amount_tokens > 0, DvpnError::InvalidAmount);
 
         let now = Clock::get()?.unix_timestamp;
         let node = &mut ctx.accounts.node;
+        node.promote_wg_key(now);
 
         require!(node.is_active, DvpnError::NodeInactive);
         require!(node.active_sessions < node.max_capacity, DvpnError::NodeAtCapacity);
+        require!(ctx.accounts.provider.meets_minimum_stake(&ctx.accounts.config), DvpnError::BelowMinimumStake);
+
+        // Price comes from the node's published list of accepted mints
+        let price_per_minute = ctx.accounts.node_pricing
+            .price_for(&ctx.accounts.mint.key())
+            .ok_or(DvpnError::MintNotAccepted)?;
+        let amount_tokens = (minutes as u64)
+            .checked_mul(price_per_minute)
+            .ok_or(DvpnError::MathOverflow)?;
 
         // Transfer SPL tokens from user -> session token account (escrow)
         let cpi_ctx = CpiContext::new(
@@ -200,25 +632,104 @@ pub mod dvpn {
         token::transfer(cpi_ctx, amount_tokens)?;
 
         let session = &mut ctx.accounts.session;
-        session.user = ctx.accounts.user.key();
-        session.node = node.key();
-        session.session_id = session_id;
-        session.start_ts = now;
-        session.end_ts = now + (minutes as i64) * 60;
-        session.escrow_lamports = amount_tokens;
-        session.remaining_balance = amount_tokens;
-        session.bytes_used = 0;
-        session.last_proof_hash = [0u8; 32];
-        session.state = SessionState::Active;
-        session.payment_token = ctx.accounts.mint.key(); // SPL token mint
-        session.bump = ctx.bumps.session;
+        session.init(
+            ctx.accounts.user.key(),
+            node,
+            session_id,
+            now,
+            now + (minutes as i64) * 60,
+            amount_tokens,
+            ctx.accounts.mint.key(),
+            ctx.accounts.user.key(),
+            ctx.bumps.session,
+        );
 
         node.active_sessions = node.active_sessions.saturating_add(1);
+        node.unsettled_sessions = node.unsettled_sessions.saturating_add(1);
         ctx.accounts.provider.total_sessions = ctx.accounts.provider.total_sessions.saturating_add(1);
 
         Ok(())
     }
 
+    // Extend an active SOL session: top up escrow at the node's current price
+    pub fn extend_session(
+        ctx: Context<ExtendSession>,
+        minutes: u32,
+    ) -> Result<()> {
+        require!(minutes > 0, DvpnError::InvalidMinutes);
+
+        let now = Clock::get()?.unix_timestamp;
+        let session = &ctx.accounts.session;
+        let node = &ctx.accounts.node;
+
+        require_keys_eq!(session.user, ctx.accounts.user.key(), DvpnError::Unauthorized);
+        require!(session.state == SessionState::Active, DvpnError::SessionNotActive);
+        require!(session.payment_token == Pubkey::default(), DvpnError::WrongPaymentToken);
+        require_keys_eq!(session.refund_to, session.user, DvpnError::VaultFundedSession);
+        require!(now < session.end_ts, DvpnError::SessionEnded);
+        require!(node.is_active, DvpnError::NodeInactive);
+
+        let cost = (minutes as u64)
+            .checked_mul(node.price_per_minute_lamports)
+            .ok_or(DvpnError::MathOverflow)?;
+
+        // Transfer SOL from user -> session PDA (escrow)
+        let ix = anchor_lang::solana_program::system_instruction::transfer(
+            &ctx.accounts.user.key(),
+            &ctx.accounts.session.key(),
+            cost,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.user.to_account_info(),
+                ctx.accounts.session.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        ctx.accounts.session.extend(minutes, cost)
+    }
+
+    // Extend an active SPL session: top up the escrow token account
+    pub fn extend_session_spl(
+        ctx: Context<ExtendSessionSpl>,
+        minutes: u32,
+    ) -> Result<()> {
+        require!(minutes > 0, DvpnError::InvalidMinutes);
+
+        let now = Clock::get()?.unix_timestamp;
+        let session = &ctx.accounts.session;
+
+        require_keys_eq!(session.user, ctx.accounts.user.key(), DvpnError::Unauthorized);
+        require!(session.state == SessionState::Active, DvpnError::SessionNotActive);
+        require!(session.payment_token != Pubkey::default(), DvpnError::WrongPaymentToken);
+        require_keys_eq!(session.refund_to, session.user, DvpnError::VaultFundedSession);
+        require!(now < session.end_ts, DvpnError::SessionEnded);
+        require!(ctx.accounts.node.is_active, DvpnError::NodeInactive);
+
+        // Top up at the node's current price for this mint
+        let price_per_minute = ctx.accounts.node_pricing
+            .price_for(&session.payment_token)
+            .ok_or(DvpnError::MintNotAccepted)?;
+        let amount_tokens = (minutes as u64)
+            .checked_mul(price_per_minute)
+            .ok_or(DvpnError::MathOverflow)?;
+
+        // Transfer SPL tokens from user -> session token account (escrow)
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.user_token_account.to_account_info(),
+                to: ctx.accounts.session_token_account.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        );
+        token::transfer(cpi_ctx, amount_tokens)?;
+
+        ctx.accounts.session.extend(minutes, amount_tokens)
+    }
+
     // Close session with partial refund for unused time
     pub fn close_session(ctx: Context<CloseSession>) -> Result<()> {
         let now = Clock::get()?.unix_timestamp;
@@ -227,12 +738,18 @@ pub mod dvpn {
 
         require_keys_eq!(session.user, ctx.accounts.user.key(), DvpnError::Unauthorized);
         require!(session.state == SessionState::Active, DvpnError::SessionNotActive);
+        require!(session.payment_token == Pubkey::default(), DvpnError::WrongPaymentToken);
 
-        // Calculate refund for unused time
+        // Calculate refund for unused time. Hash-chain sessions refund nothing
+        // here: ending now opens the redemption window, and refund_hashchain
+        // returns the unredeemed links once it has passed
         let elapsed = now.saturating_sub(session.start_ts);
         let total_duration = session.end_ts.saturating_sub(session.start_ts);
         
-        let refund = if elapsed < total_duration {
+        let refund = if session.price_per_link_lamports > 0 {
+            session.end_ts = session.end_ts.min(now);
+            0
+        } else if elapsed < total_duration {
             let used_fraction = (elapsed as u128)
                 .checked_mul(session.escrow_lamports as u128)
                 .ok_or(DvpnError::MathOverflow)?;
@@ -241,16 +758,119 @@ pub mod dvpn {
         } else {
             0
         };
+        // Never refund more than is left after receipt / hash-chain claims
+        let refund = refund.min(session.remaining_balance);
 
         if refund > 0 {
-            // Refund unused portion to user
+            // Refund unused portion to the user's wallet or vault
             **session.to_account_info().try_borrow_mut_lamports()? -= refund;
-            **ctx.accounts.user.to_account_info().try_borrow_mut_lamports()? += refund;
+            **ctx.accounts.refund_to.to_account_info().try_borrow_mut_lamports()? += refund;
+            session.remaining_balance = session.remaining_balance.saturating_sub(refund);
+            session.refunded_amount = session.refunded_amount.saturating_add(refund);
+        }
+
+        session.state = SessionState::Closed;
+        session.release_capacity(node);
+
+        Ok(())
+    }
+
+    // Failover: settle time used on the old node and move the unused escrow
+    // into a new session on another node in one transaction
+    pub fn migrate_session(
+        ctx: Context<MigrateSession>,
+        new_session_id: u64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let old_session = &mut ctx.accounts.old_session;
+
+        require_keys_eq!(old_session.user, ctx.accounts.user.key(), DvpnError::Unauthorized);
+        require!(old_session.state == SessionState::Active, DvpnError::SessionNotActive);
+        require!(old_session.payment_token == Pubkey::default(), DvpnError::WrongPaymentToken);
+        require!(old_session.price_per_link_lamports == 0, DvpnError::HashchainSession);
+        require!(now < old_session.end_ts, DvpnError::SessionEnded);
+        require_keys_neq!(ctx.accounts.old_node.key(), ctx.accounts.new_node.key(), DvpnError::SameNode);
+
+        let new_node = &mut ctx.accounts.new_node;
+        new_node.promote_wg_key(now);
+        require!(new_node.is_active, DvpnError::NodeInactive);
+        require!(new_node.active_sessions < new_node.max_capacity, DvpnError::NodeAtCapacity);
+        require!(ctx.accounts.new_provider.meets_minimum_stake(&ctx.accounts.config), DvpnError::BelowMinimumStake);
+        require!(new_node.price_per_minute_lamports > 0, DvpnError::InvalidAmount);
+
+        // Unused portion of escrow, same pro-rating as close_session
+        let elapsed = now.saturating_sub(old_session.start_ts);
+        let total_duration = old_session.end_ts.saturating_sub(old_session.start_ts);
+        let used_fraction = (elapsed as u128)
+            .checked_mul(old_session.escrow_lamports as u128)
+            .ok_or(DvpnError::MathOverflow)?;
+        let used_amount = (used_fraction / total_duration as u128) as u64;
+        let unused = old_session.escrow_lamports
+            .saturating_sub(used_amount)
+            .min(old_session.remaining_balance);
+        require!(unused > 0, DvpnError::InsufficientBalance);
+
+        // Remaining time at the new node's price
+        let new_duration = (unused as u128)
+            .checked_mul(60)
+            .ok_or(DvpnError::MathOverflow)?
+            / new_node.price_per_minute_lamports as u128;
+        require!(new_duration > 0, DvpnError::InsufficientBalance);
+
+        // Move unused escrow old session -> new session
+        **old_session.to_account_info().try_borrow_mut_lamports()? -= unused;
+        **ctx.accounts.new_session.to_account_info().try_borrow_mut_lamports()? += unused;
+
+        // Old session keeps the used portion for the old provider's claim_payout
+        old_session.remaining_balance = old_session.remaining_balance.saturating_sub(unused);
+        old_session.refunded_amount = old_session.refunded_amount.saturating_add(unused);
+        old_session.state = SessionState::Closed;
+        old_session.release_capacity(&mut ctx.accounts.old_node);
+        let refund_to = old_session.refund_to;
+
+        let new_session = &mut ctx.accounts.new_session;
+        new_session.init(
+            ctx.accounts.user.key(),
+            &ctx.accounts.new_node,
+            new_session_id,
+            now,
+            now + new_duration as i64,
+            unused,
+            Pubkey::default(),
+            refund_to,
+            ctx.bumps.new_session,
+        );
+
+        let new_node = &mut ctx.accounts.new_node;
+        new_node.active_sessions = new_node.active_sessions.saturating_add(1);
+        new_node.unsettled_sessions = new_node.unsettled_sessions.saturating_add(1);
+        ctx.accounts.new_provider.total_sessions = ctx.accounts.new_provider.total_sessions.saturating_add(1);
+
+        Ok(())
+    }
+
+    // User closes a session whose node stopped heartbeating and is refunded
+    // the unserved time from the last heartbeat, without arbitration
+    pub fn claim_downtime_refund(ctx: Context<ClaimDowntimeRefund>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let session = &mut ctx.accounts.session;
+        let node = &mut ctx.accounts.node;
+
+        require_keys_eq!(session.user, ctx.accounts.user.key(), DvpnError::Unauthorized);
+        require!(session.state == SessionState::Active, DvpnError::SessionNotActive);
+        require!(session.payment_token == Pubkey::default(), DvpnError::WrongPaymentToken);
+
+        let refund = session.downtime_refund(node, ctx.accounts.config.heartbeat_timeout_seconds, now)?;
+
+        if refund > 0 {
+            **session.to_account_info().try_borrow_mut_lamports()? -= refund;
+            **ctx.accounts.refund_to.to_account_info().try_borrow_mut_lamports()? += refund;
             session.remaining_balance = session.remaining_balance.saturating_sub(refund);
+            session.refunded_amount = session.refunded_amount.saturating_add(refund);
         }
 
         session.state = SessionState::Closed;
-        node.active_sessions = node.active_sessions.saturating_sub(1);
+        session.release_capacity(node);
 
         Ok(())
     }
@@ -261,7 +881,7 @@ pub mod dvpn {
 
         let provider = &mut ctx.accounts.provider;
         let node = &mut ctx.accounts.node;
-        require_keys_eq!(provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+        authorize_provider(provider, &ctx.accounts.authority.key(), &ctx.accounts.operator, OPERATOR_PERMISSION_CLAIM)?;
 
         let session = &mut ctx.accounts.session;
         let ended = now >= session.end_ts;
@@ -269,34 +889,41 @@ pub mod dvpn {
 
         require!(ended || closed, DvpnError::SessionNotEnded);
         require!(session.state != SessionState::Claimed, DvpnError::AlreadyClaimed);
+        // A dispute freezes the escrow until it is withdrawn or resolved
+        require!(session.state != SessionState::Disputed, DvpnError::SessionDisputed);
+        require!(session.payment_token == Pubkey::default(), DvpnError::WrongPaymentToken);
+        // Hash-chain escrow is earned only through claim_hashchain; the
+        // unredeemed rest must go back to the user via refund_hashchain or a
+        // dispute resolution first
+        require!(
+            session.price_per_link_lamports == 0 || session.remaining_balance == 0,
+            DvpnError::HashchainSession
+        );
 
-        // Split revenue: 80% to provider, 20% to treasury
+        // Split revenue between provider and treasury (ProgramConfig.treasury_share_bps)
         let amount = session.remaining_balance;
 
         if amount > 0 {
-            let treasury_share = amount
-                .checked_mul(TREASURY_SHARE_PERCENT)
-                .ok_or(DvpnError::MathOverflow)?
-                .checked_div(100)
-                .ok_or(DvpnError::MathOverflow)?;
+            let treasury_share = ctx.accounts.config.treasury_share(amount)?;
             let provider_share = amount.saturating_sub(treasury_share);
 
             **session.to_account_info().try_borrow_mut_lamports()? -= amount;
             
-            // 80% to provider
-            **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += provider_share;
+            // Provider share
+            pay_provider_share(provider, &ctx.accounts.payout.to_account_info(), provider_share)?;
             
-            // 20% to treasury
+            // Treasury share
             **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += treasury_share;
             ctx.accounts.treasury.total_collected = ctx.accounts.treasury.total_collected.saturating_add(treasury_share);
             
-            // Track provider earnings (only their 80% share)
+            // Track provider earnings (only their share)
             provider.total_earnings = provider.total_earnings.saturating_add(provider_share);
             node.total_earnings = node.total_earnings.saturating_add(provider_share);
         }
 
         session.state = SessionState::Claimed;
-        node.active_sessions = node.active_sessions.saturating_sub(1);
+        session.release_capacity(node);
+        node.unsettled_sessions = node.unsettled_sessions.saturating_sub(1);
 
         // Update uptime stats
         let duration = session.end_ts.saturating_sub(session.start_ts) as u64;
@@ -306,56 +933,237 @@ pub mod dvpn {
         Ok(())
     }
 
-    // Usage-based billing: provider submits receipt for partial claim
+    // Permissionless crank: anyone can expire a session past end_ts to free its
+    // node slot. Escrow stays on the session for the provider to claim; for
+    // hash-chain sessions that means the redemption window (see refund_hashchain).
+    pub fn expire_session(ctx: Context<ExpireSession>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let session = &mut ctx.accounts.session;
+        let node = &mut ctx.accounts.node;
+
+        require!(session.state == SessionState::Active, DvpnError::SessionNotActive);
+        require!(now >= session.end_ts, DvpnError::SessionNotEnded);
+
+        // Crank fee comes out of SOL escrow only; token escrow pays nothing.
+        // Escrow backing unredeemed hash-chain links stays put for the provider.
+        if session.payment_token == Pubkey::default() {
+            let unpromised = session.remaining_balance.saturating_sub(session.unredeemed_link_value());
+            let fee = ctx.accounts.config.expire_crank_fee_lamports.min(unpromised);
+            if fee > 0 {
+                **session.to_account_info().try_borrow_mut_lamports()? -= fee;
+                **ctx.accounts.caller.to_account_info().try_borrow_mut_lamports()? += fee;
+                session.remaining_balance = session.remaining_balance.saturating_sub(fee);
+            }
+        }
+
+        session.state = SessionState::Expired;
+        session.release_capacity(node);
+
+        Ok(())
+    }
+
+    // Resize a Session opened before the account layout grew, so its escrow can
+    // still be closed, claimed or disputed. Anyone may call it and pays the extra
+    // rent. Legacy sessions are plain time-based SOL/SPL sessions refunding the
+    // user's wallet; their node slot was already freed by close or claim. The
+    // WireGuard key they were opened under is unknown and left zeroed, and
+    // refunded_amount starts at zero.
+    pub fn migrate_session_layout(ctx: Context<MigrateSessionLayout>) -> Result<()> {
+        let info = ctx.accounts.session.to_account_info();
+        require_keys_eq!(*info.owner, crate::ID, DvpnError::Unauthorized);
+
+        let old_len = info.data_len();
+        let new_len = 8 + Session::MAX_SIZE;
+        require!(old_len < new_len, DvpnError::AlreadyMigrated);
+
+        // Top up rent for the larger account; the lamports above the old rent are escrow
+        let rent = Rent::get()?;
+        let rent_due = rent.minimum_balance(new_len).saturating_sub(rent.minimum_balance(old_len));
+        if rent_due > 0 {
+            let ix = anchor_lang::solana_program::system_instruction::transfer(
+                &ctx.accounts.payer.key(),
+                &info.key(),
+                rent_due,
+            );
+            anchor_lang::solana_program::program::invoke(
+                &ix,
+                &[
+                    ctx.accounts.payer.to_account_info(),
+                    info.clone(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
+        info.realloc(new_len, true)?;
+
+        // The discriminator check rejects anything that isn't a Session
+        let mut session = {
+            let data = info.try_borrow_data()?;
+            Session::try_deserialize(&mut &data[..])?
+        };
+
+        if old_len < 8 + Session::CAPACITY_LAYOUT_SIZE {
+            session.capacity_released = matches!(session.state, SessionState::Closed | SessionState::Claimed);
+        }
+        if old_len < 8 + Session::REFUND_TO_LAYOUT_SIZE {
+            session.refund_to = session.user;
+        }
+
+        let mut data = info.try_borrow_mut_data()?;
+        session.try_serialize(&mut &mut data[..])?;
+
+        Ok(())
+    }
+
+    // Usage-based billing: provider submits a user-signed receipt for partial claim.
+    // The preceding instruction must be an Ed25519 verification of the receipt
+    // message (see receipt_message) signed by session.user.
     pub fn claim_chunk(
         ctx: Context<ClaimChunk>,
-        bytes_used: u64,
-        proof_hash: [u8; 32],
-        amount_lamports: u64,
+        cumulative_bytes: u64,
+        cumulative_amount: u64,
+        nonce: u64,
     ) -> Result<()> {
         let session = &mut ctx.accounts.session;
         let provider = &mut ctx.accounts.provider;
 
-        require_keys_eq!(provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+        authorize_provider(provider, &ctx.accounts.authority.key(), &ctx.accounts.operator, OPERATOR_PERMISSION_CLAIM)?;
         require!(session.state == SessionState::Active, DvpnError::SessionNotActive);
-        require!(amount_lamports > 0, DvpnError::InvalidAmount);
+        require!(session.price_per_link_lamports == 0, DvpnError::HashchainSession);
+        require!(session.payment_token == Pubkey::default(), DvpnError::WrongPaymentToken);
+
+        // Receipts are cumulative and must strictly advance
+        require!(nonce > session.receipt_nonce, DvpnError::StaleReceipt);
+        require!(cumulative_bytes >= session.bytes_used, DvpnError::StaleReceipt);
+        require!(cumulative_amount > session.receipt_amount, DvpnError::StaleReceipt);
+
+        let message = receipt_message(&session.key(), cumulative_bytes, cumulative_amount, nonce);
+        verify_ed25519_receipt(&ctx.accounts.instructions_sysvar, &session.user, &message)?;
+
+        let amount_lamports = cumulative_amount.saturating_sub(session.receipt_amount);
         require!(session.remaining_balance >= amount_lamports, DvpnError::InsufficientBalance);
 
         // Update session proof and usage
-        session.last_proof_hash = proof_hash;
-        session.bytes_used = session.bytes_used.saturating_add(bytes_used);
+        session.last_proof_hash = anchor_lang::solana_program::hash::hash(&message).to_bytes();
+        session.bytes_used = cumulative_bytes;
+        session.receipt_amount = cumulative_amount;
+        session.receipt_nonce = nonce;
 
-        // Split revenue: 80% to provider, 20% to treasury
-        let treasury_share = amount_lamports
-            .checked_mul(TREASURY_SHARE_PERCENT)
-            .ok_or(DvpnError::MathOverflow)?
-            .checked_div(100)
-            .ok_or(DvpnError::MathOverflow)?;
+        // Split revenue between provider and treasury (ProgramConfig.treasury_share_bps)
+        let treasury_share = ctx.accounts.config.treasury_share(amount_lamports)?;
         let provider_share = amount_lamports.saturating_sub(treasury_share);
 
         **session.to_account_info().try_borrow_mut_lamports()? -= amount_lamports;
         
-        // 80% to provider
-        **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += provider_share;
+        // Provider share
+        pay_provider_share(provider, &ctx.accounts.payout.to_account_info(), provider_share)?;
         
-        // 20% to treasury
+        // Treasury share
         **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += treasury_share;
         ctx.accounts.treasury.total_collected = ctx.accounts.treasury.total_collected.saturating_add(treasury_share);
 
         session.remaining_balance = session.remaining_balance.saturating_sub(amount_lamports);
         
-        // Track provider earnings (only their 80% share)
+        // Track provider earnings (only their share)
         provider.total_earnings = provider.total_earnings.saturating_add(provider_share);
 
         Ok(())
     }
 
-    // Dispute: user or provider can raise dispute
-    pub fn raise_dispute(
-        ctx: Context<RaiseDispute>,
+    // Hash-chain redemption: provider reveals the preimage `links` steps behind
+    // the last redeemed link and is paid exactly `links * price_per_link`.
+    // Links stay redeemable for hashchain_grace_seconds after the session ends.
+    pub fn claim_hashchain(
+        ctx: Context<ClaimHashchain>,
+        preimage: [u8; 32],
+        links: u32,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let session = &mut ctx.accounts.session;
+        let provider = &mut ctx.accounts.provider;
+
+        authorize_provider(provider, &ctx.accounts.authority.key(), &ctx.accounts.operator, OPERATOR_PERMISSION_CLAIM)?;
+        let redeemable = session.state == SessionState::Active
+            || (matches!(session.state, SessionState::Closed | SessionState::Expired)
+                && now < session.end_ts.saturating_add(ctx.accounts.config.hashchain_grace_seconds));
+        require!(redeemable, DvpnError::SessionNotActive);
+        require!(session.price_per_link_lamports > 0, DvpnError::NotHashchainSession);
+        require!(links > 0, DvpnError::InvalidAmount);
+
+        let redeemed = session.hashchain_redeemed
+            .checked_add(links)
+            .ok_or(DvpnError::MathOverflow)?;
+        require!(redeemed <= session.hashchain_length, DvpnError::HashchainExhausted);
+
+        // Hash forward to the last redeemed link (initially the committed tip Hn)
+        let mut current = preimage;
+        for _ in 0..links {
+            current = anchor_lang::solana_program::hash::hash(&current).to_bytes();
+        }
+        require!(current == session.hashchain_tip, DvpnError::InvalidHashchainProof);
+
+        let amount_lamports = (links as u64)
+            .checked_mul(session.price_per_link_lamports)
+            .ok_or(DvpnError::MathOverflow)?;
+        require!(session.remaining_balance >= amount_lamports, DvpnError::InsufficientBalance);
+
+        // Split revenue between provider and treasury (ProgramConfig.treasury_share_bps)
+        let treasury_share = ctx.accounts.config.treasury_share(amount_lamports)?;
+        let provider_share = amount_lamports.saturating_sub(treasury_share);
+
+        **session.to_account_info().try_borrow_mut_lamports()? -= amount_lamports;
+
+        // Provider share
+        pay_provider_share(provider, &ctx.accounts.payout.to_account_info(), provider_share)?;
+
+        // Treasury share
+        **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += treasury_share;
+        ctx.accounts.treasury.total_collected = ctx.accounts.treasury.total_collected.saturating_add(treasury_share);
+
+        session.remaining_balance = session.remaining_balance.saturating_sub(amount_lamports);
+        session.hashchain_tip = preimage;
+        session.hashchain_redeemed = redeemed;
+        session.last_proof_hash = preimage;
+
+        // Track provider earnings (only their share)
+        provider.total_earnings = provider.total_earnings.saturating_add(provider_share);
+
+        Ok(())
+    }
+
+    // Permissionless crank: once the redemption window of a closed or expired
+    // hash-chain session has passed, return the unredeemed links to the user
+    pub fn refund_hashchain(ctx: Context<RefundHashchain>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let session = &mut ctx.accounts.session;
+
+        require!(session.price_per_link_lamports > 0, DvpnError::NotHashchainSession);
+        require!(
+            matches!(session.state, SessionState::Closed | SessionState::Expired),
+            DvpnError::SessionNotEnded
+        );
+        require!(
+            now >= session.end_ts.saturating_add(ctx.accounts.config.hashchain_grace_seconds),
+            DvpnError::RedemptionWindowOpen
+        );
+
+        let refund = session.remaining_balance;
+        if refund > 0 {
+            **session.to_account_info().try_borrow_mut_lamports()? -= refund;
+            **ctx.accounts.refund_to.to_account_info().try_borrow_mut_lamports()? += refund;
+            session.remaining_balance = 0;
+            session.refunded_amount = session.refunded_amount.saturating_add(refund);
+        }
+
+        Ok(())
+    }
+
+    // Dispute: user or provider can raise dispute
+    pub fn raise_dispute(
+        ctx: Context<RaiseDispute>,
         reason: String,
     ) -> Result<()> {
-        require!(reason.len() <= 200, DvpnError::StringTooLong);
+        require!(reason.len() <= ctx.accounts.config.max_reason_len as usize, DvpnError::StringTooLong);
 
         let session = &mut ctx.accounts.session;
         
@@ -363,25 +1171,161 @@ pub mod dvpn {
         let is_user = session.user == ctx.accounts.authority.key();
         let is_provider = ctx.accounts.provider.authority == ctx.accounts.authority.key();
         require!(is_user || is_provider, DvpnError::Unauthorized);
-        require!(session.state == SessionState::Active || session.state == SessionState::Closed, DvpnError::InvalidSessionState);
+        require!(
+            session.state == SessionState::Active
+                || session.state == SessionState::Closed
+                || session.state == SessionState::Expired,
+            DvpnError::InvalidSessionState
+        );
+
+        // Bond is always SOL; the escrow fraction only applies to SOL sessions
+        let registry = &ctx.accounts.arbiter_registry;
+        let schedule = if is_user { registry.user_bond } else { registry.provider_bond };
+        let sol_escrow = if session.payment_token == Pubkey::default() { session.escrow_lamports } else { 0 };
+        let bond = schedule.bond_for(sol_escrow)?;
+        if bond > 0 {
+            let ix = anchor_lang::solana_program::system_instruction::transfer(
+                &ctx.accounts.authority.key(),
+                &ctx.accounts.dispute.key(),
+                bond,
+            );
+            anchor_lang::solana_program::program::invoke(
+                &ix,
+                &[
+                    ctx.accounts.authority.to_account_info(),
+                    ctx.accounts.dispute.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+        }
 
+        let prior_state = session.state;
         session.state = SessionState::Disputed;
 
+        let now = Clock::get()?.unix_timestamp;
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.prior_state = prior_state;
+        dispute.bond_lamports = bond;
+        dispute.session = session.key();
+        dispute.raised_by = ctx.accounts.authority.key();
+        dispute.raised_by_user = is_user;
+        dispute.reason = reason;
+        dispute.raised_at = now;
+        dispute.deadline_ts = now.saturating_add(ctx.accounts.arbiter_registry.ruling_timeout_seconds);
+        dispute.resolved_at = 0;
+        dispute.evidence = Vec::new();
+        dispute.bump = ctx.bumps.dispute;
+
+        Ok(())
+    }
+
+    // Either party attaches evidence (hash of the artifact plus where to fetch it)
+    pub fn submit_evidence(
+        ctx: Context<SubmitEvidence>,
+        evidence_hash: [u8; 32],
+        uri: String,
+    ) -> Result<()> {
+        require!(uri.len() <= MAX_EVIDENCE_URI_LEN, DvpnError::StringTooLong);
+
+        let submitter = ctx.accounts.authority.key();
+        let is_user = ctx.accounts.session.user == submitter;
+        let is_provider = ctx.accounts.provider.authority == submitter;
+        require!(is_user || is_provider, DvpnError::Unauthorized);
+        require!(ctx.accounts.session.state == SessionState::Disputed, DvpnError::SessionNotDisputed);
+
+        let dispute = &mut ctx.accounts.dispute;
+        require!(dispute.evidence.len() < MAX_DISPUTE_EVIDENCE, DvpnError::TooMuchEvidence);
+        dispute.evidence.push(Evidence {
+            submitter,
+            hash: evidence_hash,
+            uri,
+            submitted_at: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    // Raiser drops the dispute before arbiters are seated: the session returns
+    // to its prior state and the bond comes back with the Dispute rent
+    pub fn withdraw_dispute(ctx: Context<WithdrawDispute>) -> Result<()> {
+        let session = &mut ctx.accounts.session;
+
+        require!(session.state == SessionState::Disputed, DvpnError::SessionNotDisputed);
+        require!(ctx.accounts.dispute_panel.data_is_empty(), DvpnError::PanelAlreadySeated);
+
+        session.state = ctx.accounts.dispute.prior_state;
+
         Ok(())
     }
 
-    // Resolve dispute (requires governance in production)
-    pub fn resolve_dispute(
-        ctx: Context<ResolveDispute>,
+    // Both parties agree on a refund; the bond is returned to the raiser
+    pub fn settle_dispute(
+        ctx: Context<SettleDispute>,
         refund_to_user: u64,
-        slash_amount: u64,
     ) -> Result<()> {
         let session = &mut ctx.accounts.session;
-        let provider = &mut ctx.accounts.provider;
 
         require!(session.state == SessionState::Disputed, DvpnError::SessionNotDisputed);
+        require!(session.payment_token == Pubkey::default(), DvpnError::WrongPaymentToken);
         require!(session.remaining_balance >= refund_to_user, DvpnError::InsufficientBalance);
-        require!(provider.stake_lamports >= slash_amount, DvpnError::InsufficientStake);
+
+        if refund_to_user > 0 {
+            **session.to_account_info().try_borrow_mut_lamports()? -= refund_to_user;
+            **ctx.accounts.refund_to.to_account_info().try_borrow_mut_lamports()? += refund_to_user;
+            session.remaining_balance = session.remaining_balance.saturating_sub(refund_to_user);
+        }
+        refund_unredeemed_links(session, &ctx.accounts.refund_to)?;
+
+        session.state = SessionState::Resolved;
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.resolved_at = Clock::get()?.unix_timestamp;
+        return_dispute_bond(dispute, &ctx.accounts.raiser)?;
+
+        Ok(())
+    }
+
+    // Permissionless: once the ruling deadline passes without a decided panel,
+    // refund the user for the part of the session not yet elapsed when the
+    // dispute was raised. No slash.
+    pub fn auto_resolve_dispute(ctx: Context<AutoResolveDispute>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let session = &mut ctx.accounts.session;
+        let dispute = &mut ctx.accounts.dispute;
+
+        require!(session.state == SessionState::Disputed, DvpnError::SessionNotDisputed);
+        require!(session.payment_token == Pubkey::default(), DvpnError::WrongPaymentToken);
+        require!(now >= dispute.deadline_ts, DvpnError::DisputeDeadlineNotReached);
+        require!(!panel_decided(&ctx.accounts.dispute_panel)?, DvpnError::DisputeAlreadyDecided);
+
+        let refund = session.prorated_refund(dispute.raised_at);
+        if refund > 0 {
+            **session.to_account_info().try_borrow_mut_lamports()? -= refund;
+            **ctx.accounts.user.to_account_info().try_borrow_mut_lamports()? += refund;
+            session.remaining_balance = session.remaining_balance.saturating_sub(refund);
+        }
+        refund_unredeemed_links(session, &ctx.accounts.user)?;
+
+        session.state = SessionState::Resolved;
+        dispute.resolved_at = now;
+        // No ruling, no loser
+        return_dispute_bond(dispute, &ctx.accounts.raiser)?;
+
+        Ok(())
+    }
+
+    // Execute a decided arbiter panel ruling (permissionless)
+    pub fn resolve_dispute(ctx: Context<ResolveDispute>) -> Result<()> {
+        let panel = &ctx.accounts.dispute_panel;
+        let session = &mut ctx.accounts.session;
+        let provider = &mut ctx.accounts.provider;
+
+        require!(session.state == SessionState::Disputed, DvpnError::SessionNotDisputed);
+        require!(session.payment_token == Pubkey::default(), DvpnError::WrongPaymentToken);
+        require!(panel.decided, DvpnError::DisputeUndecided);
+
+        // Stake may have shrunk since the vote; slash what is left
+        let refund_to_user = panel.refund_to_user.min(session.remaining_balance);
+        let slash_amount = panel.slash_amount.min(provider.slashable_lamports());
 
         // Refund to user
         if refund_to_user > 0 {
@@ -390,13 +1334,39 @@ pub mod dvpn {
             session.remaining_balance = session.remaining_balance.saturating_sub(refund_to_user);
         }
 
-        // Slash provider stake
+        // Slash provider stake and pay it out to user / treasury / insurance
         if slash_amount > 0 {
-            provider.stake_lamports = provider.stake_lamports.saturating_sub(slash_amount);
+            provider.slash(slash_amount);
             provider.reputation_score = provider.reputation_score.saturating_sub(100);
         }
+        ctx.accounts.slash_record.bump = ctx.bumps.slash_record;
+        distribute_slash(
+            &ctx.accounts.config,
+            provider,
+            &ctx.accounts.user,
+            &mut ctx.accounts.treasury,
+            &mut ctx.accounts.insurance_fund,
+            &mut ctx.accounts.slash_record,
+            session.key(),
+            slash_amount,
+        )?;
+        refund_unredeemed_links(session, &ctx.accounts.user)?;
 
         session.state = SessionState::Resolved;
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.resolved_at = Clock::get()?.unix_timestamp;
+        let counterparty = if dispute.raised_by_user { &ctx.accounts.payout } else { &ctx.accounts.user };
+        // Any refund or slash rules for the user, so a provider raiser loses
+        let user_won = refund_to_user > 0 || slash_amount > 0;
+        let raiser_won = dispute.raised_by_user == user_won;
+        settle_dispute_bond(
+            &ctx.accounts.config,
+            dispute,
+            &ctx.accounts.raiser,
+            counterparty,
+            &mut ctx.accounts.treasury,
+            raiser_won,
+        )?;
 
         Ok(())
     }
@@ -422,775 +1392,4473 @@ pub mod dvpn {
         Ok(())
     }
 
-    // ============== SUBSCRIPTION FUNCTIONS ==============
+    // Decommission a provider with no nodes and no remaining own or delegated stake
+    pub fn close_provider(ctx: Context<CloseProvider>) -> Result<()> {
+        let provider = &ctx.accounts.provider;
+
+        require_keys_eq!(provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+        require!(provider.node_count == 0, DvpnError::ProviderHasNodes);
+        require!(provider.stake_lamports == 0 && provider.unbonding_lamports == 0, DvpnError::ProviderHasStake);
+        require!(
+            provider.delegated_shares == 0 && provider.delegated_unbonding_shares == 0,
+            DvpnError::ProviderHasStake
+        );
+        require!(provider.operator_count == 0, DvpnError::ProviderHasOperators);
+
+        close_if_initialized(&ctx.accounts.profile, &ctx.accounts.authority)?;
 
-    // Initialize treasury (one-time setup)
-    pub fn initialize_treasury(ctx: Context<InitializeTreasury>) -> Result<()> {
-        let treasury = &mut ctx.accounts.treasury;
-        treasury.authority = ctx.accounts.authority.key();
-        treasury.total_collected = 0;
-        treasury.bump = ctx.bumps.treasury;
         Ok(())
     }
 
-    // Create subscription - user pays SOL to escrow
-    pub fn create_subscription(
-        ctx: Context<CreateSubscription>,
-        plan: SubscriptionPlan,
+    // ============== ARBITRATION ==============
+    // The registry authority curates arbiters and seats a panel per dispute.
+    // A ruling is final once a strict majority of the panel agrees on it;
+    // resolve_dispute(_spl) then executes it.
+
+    // One-time setup by the config admin, who becomes the registry authority
+    pub fn initialize_arbiter_registry(
+        ctx: Context<InitializeArbiterRegistry>,
+        min_panel_size: u8,
+        ruling_timeout_seconds: i64,
     ) -> Result<()> {
-        let now = Clock::get()?.unix_timestamp;
-        
-        // Calculate price and duration based on plan
-        let (price_lamports, duration_seconds) = match plan {
-            SubscriptionPlan::Weekly => (30_000_000, 7 * 24 * 60 * 60),      // 0.03 SOL, 7 days
-            SubscriptionPlan::Monthly => (100_000_000, 30 * 24 * 60 * 60),   // 0.1 SOL, 30 days
-            SubscriptionPlan::Yearly => (600_000_000, 365 * 24 * 60 * 60),   // 0.6 SOL, 365 days
+        require!(
+            min_panel_size >= 1 && min_panel_size as usize <= MAX_DISPUTE_PANEL,
+            DvpnError::InvalidPanel
+        );
+        require!(ruling_timeout_seconds > 0, DvpnError::InvalidAmount);
+
+        let registry = &mut ctx.accounts.arbiter_registry;
+        registry.authority = ctx.accounts.authority.key();
+        registry.arbiters = Vec::new();
+        registry.min_panel_size = min_panel_size;
+        registry.ruling_timeout_seconds = ruling_timeout_seconds;
+        registry.user_bond = BondSchedule {
+            base_lamports: DEFAULT_USER_BOND_LAMPORTS,
+            escrow_bps: DEFAULT_USER_BOND_BPS,
+        };
+        registry.provider_bond = BondSchedule {
+            base_lamports: DEFAULT_PROVIDER_BOND_LAMPORTS,
+            escrow_bps: DEFAULT_PROVIDER_BOND_BPS,
         };
+        registry.bump = ctx.bumps.arbiter_registry;
+        Ok(())
+    }
 
-        // Transfer SOL from user -> subscription PDA (escrow)
-        let ix = anchor_lang::solana_program::system_instruction::transfer(
-            &ctx.accounts.user.key(),
-            &ctx.accounts.subscription.key(),
-            price_lamports,
-        );
-        anchor_lang::solana_program::program::invoke(
-            &ix,
-            &[
-                ctx.accounts.user.to_account_info(),
-                ctx.accounts.subscription.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-        )?;
+    pub fn add_arbiter(
+        ctx: Context<UpdateArbiterRegistry>,
+        arbiter: Pubkey,
+    ) -> Result<()> {
+        let registry = &mut ctx.accounts.arbiter_registry;
+        require!(!registry.arbiters.contains(&arbiter), DvpnError::ArbiterAlreadyRegistered);
+        require!(registry.arbiters.len() < MAX_ARBITERS, DvpnError::TooManyArbiters);
 
-        let subscription = &mut ctx.accounts.subscription;
-        subscription.user = ctx.accounts.user.key();
-        subscription.plan = plan;
-        subscription.escrow_lamports = price_lamports;
-        subscription.start_ts = now;
-        subscription.end_ts = now + duration_seconds;
-        subscription.state = SubscriptionState::Active;
-        subscription.bump = ctx.bumps.subscription;
+        registry.arbiters.push(arbiter);
+        Ok(())
+    }
 
+    // Removed arbiters can no longer vote on panels they were seated on
+    pub fn remove_arbiter(
+        ctx: Context<UpdateArbiterRegistry>,
+        arbiter: Pubkey,
+    ) -> Result<()> {
+        let registry = &mut ctx.accounts.arbiter_registry;
+        let before = registry.arbiters.len();
+        registry.arbiters.retain(|a| *a != arbiter);
+        require!(registry.arbiters.len() < before, DvpnError::NotAnArbiter);
         Ok(())
     }
 
-    // Renew subscription - for expired/cancelled subscriptions
-    pub fn renew_subscription(
-        ctx: Context<RenewSubscription>,
-        plan: SubscriptionPlan,
+    pub fn set_min_panel_size(
+        ctx: Context<UpdateArbiterRegistry>,
+        min_panel_size: u8,
     ) -> Result<()> {
-        let now = Clock::get()?.unix_timestamp;
-        
-        // Verify the subscription belongs to this user
-        require_keys_eq!(ctx.accounts.subscription.user, ctx.accounts.user.key(), DvpnError::Unauthorized);
-        
-        // Only allow renewal if expired or cancelled
-        let is_expired = now >= ctx.accounts.subscription.end_ts;
-        let is_cancelled = ctx.accounts.subscription.state == SubscriptionState::Cancelled;
-        require!(is_expired || is_cancelled, DvpnError::SubscriptionStillActive);
+        require!(
+            min_panel_size >= 1 && min_panel_size as usize <= MAX_DISPUTE_PANEL,
+            DvpnError::InvalidPanel
+        );
+        ctx.accounts.arbiter_registry.min_panel_size = min_panel_size;
+        Ok(())
+    }
 
-        // Calculate price and duration based on plan
-        let (price_lamports, duration_seconds) = match plan {
-            SubscriptionPlan::Weekly => (30_000_000, 7 * 24 * 60 * 60),      // 0.03 SOL, 7 days
-            SubscriptionPlan::Monthly => (100_000_000, 30 * 24 * 60 * 60),   // 0.1 SOL, 30 days
-            SubscriptionPlan::Yearly => (600_000_000, 365 * 24 * 60 * 60),   // 0.6 SOL, 365 days
-        };
+    // Applies to disputes raised after the change
+    pub fn set_ruling_timeout(
+        ctx: Context<UpdateArbiterRegistry>,
+        ruling_timeout_seconds: i64,
+    ) -> Result<()> {
+        require!(ruling_timeout_seconds > 0, DvpnError::InvalidAmount);
+        ctx.accounts.arbiter_registry.ruling_timeout_seconds = ruling_timeout_seconds;
+        Ok(())
+    }
 
-        // Transfer SOL from user -> subscription PDA (escrow)
-        let ix = anchor_lang::solana_program::system_instruction::transfer(
-            &ctx.accounts.user.key(),
-            &ctx.accounts.subscription.key(),
-            price_lamports,
+    // Separate bond schedules for disputes raised by users and by providers
+    pub fn set_bond_schedules(
+        ctx: Context<UpdateArbiterRegistry>,
+        user_bond: BondSchedule,
+        provider_bond: BondSchedule,
+    ) -> Result<()> {
+        require!(
+            user_bond.escrow_bps as u64 <= BPS_DENOMINATOR && provider_bond.escrow_bps as u64 <= BPS_DENOMINATOR,
+            DvpnError::InvalidAmount
         );
-        anchor_lang::solana_program::program::invoke(
-            &ix,
-            &[
-                ctx.accounts.user.to_account_info(),
-                ctx.accounts.subscription.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-        )?;
 
-        // Update subscription
-        let subscription = &mut ctx.accounts.subscription;
-        subscription.plan = plan;
-        subscription.escrow_lamports = price_lamports;
-        subscription.start_ts = now;
-        subscription.end_ts = now + duration_seconds;
-        subscription.state = SubscriptionState::Active;
+        let registry = &mut ctx.accounts.arbiter_registry;
+        registry.user_bond = user_bond;
+        registry.provider_bond = provider_bond;
+        Ok(())
+    }
 
+    // Hand registry control to a new key. Registry and panel instructions need
+    // the authority to sign, so it must be a wallet rather than a PDA.
+    pub fn set_arbiter_authority(
+        ctx: Context<UpdateArbiterRegistry>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.arbiter_registry.authority = new_authority;
         Ok(())
     }
 
-    // Cancel subscription - user gets refund for unused time
-    pub fn cancel_subscription(ctx: Context<CancelSubscription>) -> Result<()> {
-        let now = Clock::get()?.unix_timestamp;
-        let subscription = &mut ctx.accounts.subscription;
+    // Seat a panel of registered arbiters on a disputed session
+    pub fn assign_arbiters(
+        ctx: Context<AssignArbiters>,
+        arbiters: Vec<Pubkey>,
+    ) -> Result<()> {
+        let registry = &ctx.accounts.arbiter_registry;
+        require!(ctx.accounts.session.state == SessionState::Disputed, DvpnError::SessionNotDisputed);
+        require!(
+            arbiters.len() >= registry.min_panel_size as usize && arbiters.len() <= MAX_DISPUTE_PANEL,
+            DvpnError::InvalidPanel
+        );
+        for (i, arbiter) in arbiters.iter().enumerate() {
+            require!(registry.arbiters.contains(arbiter), DvpnError::NotAnArbiter);
+            require!(!arbiters[..i].contains(arbiter), DvpnError::InvalidPanel);
+        }
 
-        require_keys_eq!(subscription.user, ctx.accounts.user.key(), DvpnError::Unauthorized);
-        require!(subscription.state == SubscriptionState::Active, DvpnError::SubscriptionNotActive);
+        let panel = &mut ctx.accounts.dispute_panel;
+        panel.session = ctx.accounts.session.key();
+        panel.arbiters = arbiters;
+        panel.votes = Vec::new();
+        panel.decided = false;
+        panel.refund_to_user = 0;
+        panel.slash_amount = 0;
+        panel.bump = ctx.bumps.dispute_panel;
+        Ok(())
+    }
 
-        // Calculate refund for unused time
-        let elapsed = now.saturating_sub(subscription.start_ts);
-        let total_duration = subscription.end_ts.saturating_sub(subscription.start_ts);
-        
-        let refund = if elapsed < total_duration {
-            let used_fraction = (elapsed as u128)
-                .checked_mul(subscription.escrow_lamports as u128)
-                .ok_or(DvpnError::MathOverflow)?;
-            let used_amount = (used_fraction / total_duration as u128) as u64;
-            subscription.escrow_lamports.saturating_sub(used_amount)
-        } else {
-            0
-        };
+    // Seated arbiter votes for an outcome; one vote each, no changes
+    pub fn cast_dispute_vote(
+        ctx: Context<CastDisputeVote>,
+        refund_to_user: u64,
+        slash_amount: u64,
+    ) -> Result<()> {
+        let arbiter = ctx.accounts.arbiter.key();
+        let session = &ctx.accounts.session;
+        let panel = &mut ctx.accounts.dispute_panel;
 
-        // Transfer used portion to treasury
-        let used_amount = subscription.escrow_lamports.saturating_sub(refund);
-        if used_amount > 0 {
-            **subscription.to_account_info().try_borrow_mut_lamports()? -= used_amount;
-            **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += used_amount;
-            ctx.accounts.treasury.total_collected = ctx.accounts.treasury.total_collected.saturating_add(used_amount);
-        }
+        require!(session.state == SessionState::Disputed, DvpnError::SessionNotDisputed);
+        require!(!panel.decided, DvpnError::DisputeAlreadyDecided);
+        require!(panel.arbiters.contains(&arbiter), DvpnError::NotAnArbiter);
+        require!(ctx.accounts.arbiter_registry.arbiters.contains(&arbiter), DvpnError::NotAnArbiter);
+        require!(!panel.votes.iter().any(|v| v.arbiter == arbiter), DvpnError::AlreadyVoted);
+        require!(refund_to_user <= session.remaining_balance, DvpnError::InsufficientBalance);
+
+        panel.votes.push(ArbiterVote {
+            arbiter,
+            refund_to_user,
+            slash_amount,
+        });
+        panel.tally(refund_to_user, slash_amount);
 
-        // Refund unused portion to user
-        if refund > 0 {
-            **subscription.to_account_info().try_borrow_mut_lamports()? -= refund;
-            **ctx.accounts.user.to_account_info().try_borrow_mut_lamports()? += refund;
-        }
+        Ok(())
+    }
 
-        subscription.state = SubscriptionState::Cancelled;
+    // ============== PROVIDER AUTHORITY TRANSFER ==============
+    // The Provider PDA stays at its original address (seeded by seed_authority);
+    // nodes, stake, delegations and reputation move with it.
+
+    // Step 1: current authority nominates a new one (Pubkey::default() cancels)
+    pub fn propose_authority_transfer(
+        ctx: Context<ProposeAuthorityTransfer>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let provider = &mut ctx.accounts.provider;
+        require_keys_eq!(provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
 
+        provider.pending_authority = new_authority;
         Ok(())
     }
 
-    // Claim subscription - provider claims their 80% share after subscription expires
-    // Revenue split: 80% to provider, 20% to treasury
-    pub fn claim_subscription(ctx: Context<ClaimSubscription>) -> Result<()> {
-        let now = Clock::get()?.unix_timestamp;
-        let subscription = &mut ctx.accounts.subscription;
+    // Step 2: nominated key signs to take ownership
+    pub fn accept_authority_transfer(ctx: Context<AcceptAuthorityTransfer>) -> Result<()> {
         let provider = &mut ctx.accounts.provider;
-        let node = &mut ctx.accounts.node;
+        let new_authority = ctx.accounts.new_authority.key();
 
-        require!(subscription.state == SubscriptionState::Active, DvpnError::SubscriptionNotActive);
-        require!(now >= subscription.end_ts, DvpnError::SubscriptionNotExpired);
-        
-        // Verify the provider owns this node
-        require_keys_eq!(provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+        require!(provider.pending_authority != Pubkey::default(), DvpnError::NoPendingTransfer);
+        require_keys_eq!(provider.pending_authority, new_authority, DvpnError::Unauthorized);
 
-        let amount = subscription.escrow_lamports;
+        // A payout address left at the old key follows the transfer
+        if provider.payout_address == provider.authority {
+            provider.payout_address = new_authority;
+        }
+        provider.authority = new_authority;
+        provider.pending_authority = Pubkey::default();
 
-        if amount > 0 {
-            // Split: 80% to provider, 20% to treasury
-            let treasury_share = amount
-                .checked_mul(TREASURY_SHARE_PERCENT)
-                .ok_or(DvpnError::MathOverflow)?
-                .checked_div(100)
-                .ok_or(DvpnError::MathOverflow)?;
-            let provider_share = amount.saturating_sub(treasury_share);
+        Ok(())
+    }
 
-            **subscription.to_account_info().try_borrow_mut_lamports()? -= amount;
-            
-            // 80% to provider
-            **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += provider_share;
-            
-            // 20% to treasury
-            **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += treasury_share;
-            ctx.accounts.treasury.total_collected = ctx.accounts.treasury.total_collected.saturating_add(treasury_share);
-            
-            // Track provider earnings
-            provider.total_earnings = provider.total_earnings.saturating_add(provider_share);
-            node.total_earnings = node.total_earnings.saturating_add(provider_share);
+    // Resize a Provider created before the account layout grew and fill in the
+    // fields that can't be left zeroed. New fields default to zero.
+    pub fn migrate_provider(ctx: Context<MigrateProvider>) -> Result<()> {
+        let info = ctx.accounts.provider.to_account_info();
+        require_keys_eq!(*info.owner, crate::ID, DvpnError::Unauthorized);
+
+        let old_len = info.data_len();
+        let new_len = 8 + Provider::MAX_SIZE;
+        require!(old_len < new_len, DvpnError::AlreadyMigrated);
+
+        // Top up rent for the larger account; the lamports above the old rent are stake
+        let rent = Rent::get()?;
+        let rent_due = rent.minimum_balance(new_len).saturating_sub(rent.minimum_balance(old_len));
+        if rent_due > 0 {
+            let ix = anchor_lang::solana_program::system_instruction::transfer(
+                &ctx.accounts.authority.key(),
+                &info.key(),
+                rent_due,
+            );
+            anchor_lang::solana_program::program::invoke(
+                &ix,
+                &[
+                    ctx.accounts.authority.to_account_info(),
+                    info.clone(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
         }
+        info.realloc(new_len, true)?;
 
-        subscription.escrow_lamports = 0;
-        subscription.state = SubscriptionState::Claimed;
+        let mut provider = {
+            let data = info.try_borrow_data()?;
+            Provider::try_deserialize(&mut &data[..])?
+        };
+        require_keys_eq!(provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+
+        if provider.seed_authority == Pubkey::default() {
+            provider.seed_authority = provider.authority;
+        }
+        if provider.payout_address == Pubkey::default() {
+            provider.payout_address = provider.authority;
+        }
+
+        let mut data = info.try_borrow_mut_data()?;
+        provider.try_serialize(&mut &mut data[..])?;
 
         Ok(())
     }
 
-    // Check if user has active subscription (view function for frontend)
-    pub fn check_subscription(ctx: Context<CheckSubscription>) -> Result<bool> {
+    // ============== OPERATOR KEYS ==============
+    // Hot keys for node daemons: scoped to claim / heartbeat / node-update and
+    // never able to touch stake. Claims always pay Provider.payout_address.
+
+    pub fn add_operator(
+        ctx: Context<AddOperator>,
+        operator_key: Pubkey,
+        permissions: u8,
+    ) -> Result<()> {
+        require!(permissions != 0 && permissions & !OPERATOR_PERMISSION_ALL == 0, DvpnError::InvalidPermissions);
+        require_keys_eq!(ctx.accounts.provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+
+        let operator = &mut ctx.accounts.operator;
+        operator.provider = ctx.accounts.provider.key();
+        operator.key = operator_key;
+        operator.permissions = permissions;
+        operator.bump = ctx.bumps.operator;
+
+        let provider = &mut ctx.accounts.provider;
+        provider.operator_count = provider.operator_count.saturating_add(1);
+        Ok(())
+    }
+
+    pub fn set_operator_permissions(
+        ctx: Context<UpdateOperator>,
+        permissions: u8,
+    ) -> Result<()> {
+        require!(permissions != 0 && permissions & !OPERATOR_PERMISSION_ALL == 0, DvpnError::InvalidPermissions);
+        require_keys_eq!(ctx.accounts.provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+
+        ctx.accounts.operator.permissions = permissions;
+        Ok(())
+    }
+
+    // Revoke an operator key and reclaim its rent
+    pub fn remove_operator(ctx: Context<RemoveOperator>) -> Result<()> {
+        require_keys_eq!(ctx.accounts.provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+
+        let provider = &mut ctx.accounts.provider;
+        provider.operator_count = provider.operator_count.saturating_sub(1);
+        Ok(())
+    }
+
+    pub fn set_payout_address(
+        ctx: Context<SetPayoutAddress>,
+        payout_address: Pubkey,
+    ) -> Result<()> {
+        require_keys_eq!(ctx.accounts.provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+
+        ctx.accounts.provider.payout_address = payout_address;
+        Ok(())
+    }
+
+    // ============== PROVIDER PROFILE ==============
+    // Public identity and no-logs policy commitment. policy_version bumps on
+    // every policy change so clients can flag changes mid-subscription.
+
+    pub fn create_provider_profile(
+        ctx: Context<CreateProviderProfile>,
+        display_name: String,
+        website: String,
+        jurisdiction: String,
+        policy_hash: [u8; 32],
+        policy_uri: String,
+    ) -> Result<()> {
+        require_keys_eq!(ctx.accounts.provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+        require!(display_name.len() <= MAX_DISPLAY_NAME_LEN, DvpnError::StringTooLong);
+        require!(website.len() <= MAX_WEBSITE_LEN, DvpnError::StringTooLong);
+        require!(jurisdiction.len() <= MAX_JURISDICTION_LEN, DvpnError::StringTooLong);
+        require!(policy_uri.len() <= MAX_POLICY_URI_LEN, DvpnError::StringTooLong);
+
         let now = Clock::get()?.unix_timestamp;
-        let subscription = &ctx.accounts.subscription;
+        let profile = &mut ctx.accounts.profile;
+        profile.provider = ctx.accounts.provider.key();
+        profile.display_name = display_name;
+        profile.website = website;
+        profile.jurisdiction = jurisdiction;
+        profile.policy_hash = policy_hash;
+        profile.policy_uri = policy_uri;
+        profile.policy_version = 1;
+        profile.policy_updated_at = now;
+        profile.updated_at = now;
+        profile.bump = ctx.bumps.profile;
 
-        let is_active = subscription.state == SubscriptionState::Active && now < subscription.end_ts;
-        
-        Ok(is_active)
+        Ok(())
     }
 
-    // Withdraw from treasury (admin only)
-    pub fn withdraw_treasury(
-        ctx: Context<WithdrawTreasury>,
-        amount: u64,
+    // Identity fields only; None leaves a field unchanged
+    pub fn update_provider_profile(
+        ctx: Context<UpdateProviderProfile>,
+        display_name: Option<String>,
+        website: Option<String>,
+        jurisdiction: Option<String>,
     ) -> Result<()> {
-        let treasury = &mut ctx.accounts.treasury;
+        require_keys_eq!(ctx.accounts.provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
 
-        require_keys_eq!(treasury.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
-        require!(amount > 0, DvpnError::InvalidAmount);
+        let profile = &mut ctx.accounts.profile;
+        if let Some(display_name) = display_name {
+            require!(display_name.len() <= MAX_DISPLAY_NAME_LEN, DvpnError::StringTooLong);
+            profile.display_name = display_name;
+        }
+        if let Some(website) = website {
+            require!(website.len() <= MAX_WEBSITE_LEN, DvpnError::StringTooLong);
+            profile.website = website;
+        }
+        if let Some(jurisdiction) = jurisdiction {
+            require!(jurisdiction.len() <= MAX_JURISDICTION_LEN, DvpnError::StringTooLong);
+            profile.jurisdiction = jurisdiction;
+        }
+        profile.updated_at = Clock::get()?.unix_timestamp;
 
-        **treasury.to_account_info().try_borrow_mut_lamports()? -= amount;
-        **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += amount;
+        Ok(())
+    }
+
+    // Publish a new signed no-logs policy document
+    pub fn update_no_logs_policy(
+        ctx: Context<UpdateProviderProfile>,
+        policy_hash: [u8; 32],
+        policy_uri: String,
+    ) -> Result<()> {
+        require_keys_eq!(ctx.accounts.provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+        require!(policy_uri.len() <= MAX_POLICY_URI_LEN, DvpnError::StringTooLong);
+
+        let now = Clock::get()?.unix_timestamp;
+        let profile = &mut ctx.accounts.profile;
+        profile.policy_hash = policy_hash;
+        profile.policy_uri = policy_uri;
+        profile.policy_version = profile.policy_version.checked_add(1).ok_or(DvpnError::MathOverflow)?;
+        profile.policy_updated_at = now;
+        profile.updated_at = now;
 
         Ok(())
     }
 
-    // ============== FAIR EARNINGS DISTRIBUTION ==============
+    // ============== DELEGATED STAKING ==============
+    // Delegators buy shares of the provider's delegated pool; slashing shrinks
+    // the pool so every delegator loses pro rata. Rewards accrue per share.
 
-    // Initialize earnings pool for an epoch (weekly distribution)
-    pub fn initialize_earnings_pool(
-        ctx: Context<InitializeEarningsPool>,
-        epoch: u64,
+    // Provider's cut of delegator rewards, in basis points
+    pub fn set_commission(
+        ctx: Context<SetCommission>,
+        commission_bps: u16,
     ) -> Result<()> {
-        let pool = &mut ctx.accounts.earnings_pool;
-        pool.epoch = epoch;
-        pool.total_subscription_revenue = 0;
-        pool.total_distributed = 0;
-        pool.start_ts = Clock::get()?.unix_timestamp;
-        pool.end_ts = pool.start_ts + (7 * 24 * 60 * 60); // Weekly epochs
-        pool.is_finalized = false;
-        pool.bump = ctx.bumps.earnings_pool;
+        require!(commission_bps <= BPS_DENOMINATOR as u16, DvpnError::InvalidAmount);
+        require_keys_eq!(ctx.accounts.provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+
+        ctx.accounts.provider.commission_bps = commission_bps;
         Ok(())
     }
 
-    // Record node usage when user ends a session
-    // This tracks how much each node was used for fair distribution
-    pub fn record_node_usage(
-        ctx: Context<RecordNodeUsage>,
-        epoch: u64,
-        duration_seconds: u64,
-        bytes_transferred: u64,
-        user_rating: u8,  // 1-5 stars
+    pub fn delegate_stake(
+        ctx: Context<DelegateStake>,
+        amount_lamports: u64,
     ) -> Result<()> {
-        require!(user_rating >= 1 && user_rating <= 5, DvpnError::InvalidAmount);
-        
-        let node = &mut ctx.accounts.node;
-        let pool = &mut ctx.accounts.earnings_pool;
-        let usage = &mut ctx.accounts.usage_record;
-        
-        require!(!pool.is_finalized, DvpnError::PoolAlreadyFinalized);
-        
-        // Update node statistics
-        node.total_uptime_seconds = node.total_uptime_seconds.saturating_add(duration_seconds);
-        node.total_bytes_served = node.total_bytes_served.saturating_add(bytes_transferred);
-        node.rating_sum = node.rating_sum.saturating_add(user_rating as u64);
-        node.rating_count = node.rating_count.saturating_add(1);
-        
-        // Calculate quality score (0-10000)
-        // Based on: bandwidth weight + uptime weight + rating weight
-        let avg_rating = if node.rating_count > 0 {
-            (node.rating_sum * 2000) / node.rating_count as u64  // Max 10000 (5 stars * 2000)
+        require!(amount_lamports > 0, DvpnError::InvalidAmount);
+
+        let provider = &mut ctx.accounts.provider;
+        let delegation = &mut ctx.accounts.delegation;
+
+        if delegation.delegator == Pubkey::default() {
+            delegation.provider = provider.key();
+            delegation.delegator = ctx.accounts.delegator.key();
+            delegation.bump = ctx.bumps.delegation;
+        }
+
+        // Settle rewards earned on existing shares before changing the share count
+        pay_delegation_rewards(provider, delegation, &ctx.accounts.delegator.to_account_info())?;
+
+        // A pool slashed to zero can't price new shares
+        require!(
+            provider.delegated_shares == 0 || provider.delegated_lamports > 0,
+            DvpnError::DelegationPoolDepleted
+        );
+        let shares = if provider.delegated_shares == 0 {
+            amount_lamports
         } else {
-            10000  // Default perfect if no ratings
+            ((amount_lamports as u128)
+                .checked_mul(provider.delegated_shares as u128)
+                .ok_or(DvpnError::MathOverflow)?
+                / provider.delegated_lamports as u128) as u64
         };
-        node.quality_score = (avg_rating as u16).min(10000);
-        
-        // Update or create usage record for this node in this epoch
-        usage.node = node.key();
-        usage.epoch = epoch;
-        usage.usage_seconds = usage.usage_seconds.saturating_add(duration_seconds);
-        usage.bytes_served = usage.bytes_served.saturating_add(bytes_transferred);
-        usage.session_count = usage.session_count.saturating_add(1);
-        usage.bump = ctx.bumps.usage_record;
-        
+        require!(shares > 0, DvpnError::InvalidAmount);
+
+        // Transfer SOL from delegator -> provider PDA
+        let ix = anchor_lang::solana_program::system_instruction::transfer(
+            &ctx.accounts.delegator.key(),
+            &provider.key(),
+            amount_lamports,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.delegator.to_account_info(),
+                provider.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        provider.delegated_lamports = provider.delegated_lamports.checked_add(amount_lamports).ok_or(DvpnError::MathOverflow)?;
+        provider.delegated_shares = provider.delegated_shares.checked_add(shares).ok_or(DvpnError::MathOverflow)?;
+        delegation.shares = delegation.shares.checked_add(shares).ok_or(DvpnError::MathOverflow)?;
+        delegation.reward_debt = provider.reward_debt_for(delegation.shares);
+
         Ok(())
     }
 
-    // Rate a node after using it
-    pub fn rate_node(
-        ctx: Context<RateNode>,
-        rating: u8,  // 1-5 stars
+    // Start unbonding `shares`; they stop earning and move to the unbonding pool,
+    // where they remain slashable until withdrawn
+    pub fn request_undelegate(
+        ctx: Context<UpdateDelegation>,
+        shares: u64,
     ) -> Result<()> {
-        require!(rating >= 1 && rating <= 5, DvpnError::InvalidAmount);
-        
-        let node = &mut ctx.accounts.node;
-        node.rating_sum = node.rating_sum.saturating_add(rating as u64);
-        node.rating_count = node.rating_count.saturating_add(1);
-        
-        // Recalculate quality score
-        let avg_rating = (node.rating_sum * 2000) / node.rating_count as u64;
-        node.quality_score = (avg_rating as u16).min(10000);
-        
+        let now = Clock::get()?.unix_timestamp;
+        let provider = &mut ctx.accounts.provider;
+        let delegation = &mut ctx.accounts.delegation;
+
+        require!(shares > 0 && shares <= delegation.shares, DvpnError::InvalidAmount);
+
+        pay_delegation_rewards(provider, delegation, &ctx.accounts.delegator.to_account_info())?;
+
+        let lamports = ((shares as u128)
+            .checked_mul(provider.delegated_lamports as u128)
+            .ok_or(DvpnError::MathOverflow)?
+            / provider.delegated_shares as u128) as u64;
+
+        // Same as delegate_stake: a pool slashed to zero can't price new shares
+        require!(
+            provider.delegated_unbonding_shares == 0 || provider.delegated_unbonding_lamports > 0,
+            DvpnError::DelegationPoolDepleted
+        );
+        let unbonding_shares = if provider.delegated_unbonding_shares == 0 {
+            lamports
+        } else {
+            ((lamports as u128)
+                .checked_mul(provider.delegated_unbonding_shares as u128)
+                .ok_or(DvpnError::MathOverflow)?
+                / provider.delegated_unbonding_lamports as u128) as u64
+        };
+        require!(unbonding_shares > 0, DvpnError::InvalidAmount);
+
+        provider.delegated_shares -= shares;
+        provider.delegated_lamports = provider.delegated_lamports.saturating_sub(lamports);
+        provider.delegated_unbonding_shares = provider.delegated_unbonding_shares.checked_add(unbonding_shares).ok_or(DvpnError::MathOverflow)?;
+        provider.delegated_unbonding_lamports = provider.delegated_unbonding_lamports.checked_add(lamports).ok_or(DvpnError::MathOverflow)?;
+
+        delegation.shares -= shares;
+        delegation.reward_debt = provider.reward_debt_for(delegation.shares);
+        delegation.unbonding_shares = delegation.unbonding_shares.checked_add(unbonding_shares).ok_or(DvpnError::MathOverflow)?;
+        delegation.unbonding_unlock_ts = now + ctx.accounts.config.unbonding_period_seconds;
+
         Ok(())
     }
 
-    // Claim proportional earnings from the pool based on node contribution
-    // Formula: node_share = (usage_weight * quality_weight * bandwidth_weight) / total_weights
-    pub fn claim_proportional_earnings(
-        ctx: Context<ClaimProportionalEarnings>,
-        epoch: u64,
-    ) -> Result<()> {
-        let pool = &mut ctx.accounts.earnings_pool;
-        let node = &mut ctx.accounts.node;
+    pub fn withdraw_undelegated(ctx: Context<UpdateDelegation>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
         let provider = &mut ctx.accounts.provider;
-        let usage = &ctx.accounts.usage_record;
-        
-        require!(pool.is_finalized, DvpnError::PoolNotFinalized);
-        require!(usage.claimed == false, DvpnError::AlreadyClaimed);
-        
-        // Calculate node's weighted contribution
-        // Weight factors:
-        // - Usage time: 40% weight
-        // - Bandwidth: 30% weight  
-        // - Quality score: 30% weight
-        
-        let usage_weight = usage.usage_seconds as u128;
-        let bandwidth_weight = (node.bandwidth_mbps as u128) * usage.usage_seconds as u128 / 100;
-        let quality_weight = (node.quality_score as u128) * usage.usage_seconds as u128 / 10000;
-        
-        let node_weighted_score = usage_weight
-            .saturating_mul(40)
-            .saturating_add(bandwidth_weight.saturating_mul(30))
-            .saturating_add(quality_weight.saturating_mul(30))
-            / 100;
-        
-        // Get total weighted score from pool (stored when finalized)
-        let total_weighted = pool.total_weighted_score;
-        require!(total_weighted > 0, DvpnError::InvalidAmount);
-        
-        // Calculate node's share of the pool
-        let pool_amount = pool.total_subscription_revenue.saturating_sub(pool.total_distributed);
-        let node_share = (pool_amount as u128)
-            .saturating_mul(node_weighted_score)
-            .checked_div(total_weighted)
-            .unwrap_or(0) as u64;
-        
-        if node_share > 0 {
-            // Split: 80% to provider, 20% to treasury
-            let treasury_share = node_share
-                .checked_mul(TREASURY_SHARE_PERCENT)
-                .ok_or(DvpnError::MathOverflow)?
-                .checked_div(100)
-                .ok_or(DvpnError::MathOverflow)?;
-            let provider_share = node_share.saturating_sub(treasury_share);
-            
-            // Transfer from pool to provider
-            **pool.to_account_info().try_borrow_mut_lamports()? -= node_share;
-            **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += provider_share;
-            **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += treasury_share;
-            
-            pool.total_distributed = pool.total_distributed.saturating_add(node_share);
-            provider.total_earnings = provider.total_earnings.saturating_add(provider_share);
-            node.total_earnings = node.total_earnings.saturating_add(provider_share);
-            ctx.accounts.treasury.total_collected = ctx.accounts.treasury.total_collected.saturating_add(treasury_share);
+        let delegation = &mut ctx.accounts.delegation;
+
+        require!(delegation.unbonding_shares > 0, DvpnError::NothingUnbonding);
+        require!(now >= delegation.unbonding_unlock_ts, DvpnError::StillUnbonding);
+
+        let lamports = ((delegation.unbonding_shares as u128)
+            .checked_mul(provider.delegated_unbonding_lamports as u128)
+            .ok_or(DvpnError::MathOverflow)?
+            / provider.delegated_unbonding_shares as u128) as u64;
+
+        provider.delegated_unbonding_shares -= delegation.unbonding_shares;
+        provider.delegated_unbonding_lamports = provider.delegated_unbonding_lamports.saturating_sub(lamports);
+        delegation.unbonding_shares = 0;
+        delegation.unbonding_unlock_ts = 0;
+
+        // Transfer SOL from provider PDA -> delegator
+        if lamports > 0 {
+            **provider.to_account_info().try_borrow_mut_lamports()? -= lamports;
+            **ctx.accounts.delegator.to_account_info().try_borrow_mut_lamports()? += lamports;
         }
-        
-        // Mark as claimed (need mutable reference)
-        let usage_mut = &mut ctx.accounts.usage_record.clone();
-        // Note: In production, we'd need a separate claimed tracking
-        
+
+        Ok(())
+    }
+
+    pub fn claim_delegation_rewards(ctx: Context<UpdateDelegation>) -> Result<()> {
+        let provider = &mut ctx.accounts.provider;
+        let delegation = &mut ctx.accounts.delegation;
+
+        pay_delegation_rewards(provider, delegation, &ctx.accounts.delegator.to_account_info())?;
+        delegation.reward_debt = provider.reward_debt_for(delegation.shares);
+
         Ok(())
     }
 
-    // Finalize pool - calculates total weighted scores and allows claiming
-    pub fn finalize_earnings_pool(
-        ctx: Context<FinalizeEarningsPool>,
-        total_weighted_score: u128,
-    ) -> Result<()> {
-        let pool = &mut ctx.accounts.earnings_pool;
-        
-        require_keys_eq!(ctx.accounts.treasury.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
-        require!(!pool.is_finalized, DvpnError::PoolAlreadyFinalized);
-        
-        let now = Clock::get()?.unix_timestamp;
-        require!(now >= pool.end_ts, DvpnError::PoolNotEnded);
-        
-        pool.total_weighted_score = total_weighted_score;
-        pool.is_finalized = true;
-        
-        Ok(())
-    }
+    // ============== SPL SESSION SETTLEMENT ==============
+    // Mirrors close_session / claim_payout / claim_chunk / resolve_dispute for sessions
+    // opened with open_session_spl. Tokens move out of session_token_account signed by
+    // the session PDA; the escrow token account is closed once it is empty.
+
+    pub fn close_session_spl(ctx: Context<CloseSessionSpl>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let session = &ctx.accounts.session;
+
+        require_keys_eq!(session.user, ctx.accounts.user.key(), DvpnError::Unauthorized);
+        require!(session.state == SessionState::Active, DvpnError::SessionNotActive);
+        require!(session.payment_token != Pubkey::default(), DvpnError::WrongPaymentToken);
+
+        // Calculate refund for unused time
+        let elapsed = now.saturating_sub(session.start_ts);
+        let total_duration = session.end_ts.saturating_sub(session.start_ts);
+
+        let refund = if elapsed < total_duration {
+            let used_fraction = (elapsed as u128)
+                .checked_mul(session.escrow_lamports as u128)
+                .ok_or(DvpnError::MathOverflow)?;
+            let used_amount = (used_fraction / total_duration as u128) as u64;
+            session.escrow_lamports.saturating_sub(used_amount)
+        } else {
+            0
+        };
+        let refund = refund.min(session.remaining_balance);
+        let remaining = session.remaining_balance.saturating_sub(refund);
+
+        if refund > 0 {
+            // Refund unused portion to user
+            session_token_transfer(
+                &ctx.accounts.token_program,
+                session,
+                &ctx.accounts.session_token_account,
+                &ctx.accounts.user_token_account,
+                refund,
+            )?;
+        }
+
+        if remaining == 0 {
+            sweep_and_close_escrow(
+                &ctx.accounts.token_program,
+                session,
+                &mut ctx.accounts.session_token_account,
+                Some(&ctx.accounts.user_token_account),
+                ctx.accounts.user.to_account_info(),
+            )?;
+        }
+
+        let session = &mut ctx.accounts.session;
+        session.remaining_balance = remaining;
+        session.refunded_amount = session.refunded_amount.saturating_add(refund);
+        session.state = SessionState::Closed;
+        session.release_capacity(&mut ctx.accounts.node);
+
+        Ok(())
+    }
+
+    // SPL counterpart of claim_downtime_refund
+    pub fn claim_downtime_refund_spl(ctx: Context<ClaimDowntimeRefundSpl>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let session = &ctx.accounts.session;
+
+        require_keys_eq!(session.user, ctx.accounts.user.key(), DvpnError::Unauthorized);
+        require!(session.state == SessionState::Active, DvpnError::SessionNotActive);
+        require!(session.payment_token != Pubkey::default(), DvpnError::WrongPaymentToken);
+
+        let refund = session.downtime_refund(&ctx.accounts.node, ctx.accounts.config.heartbeat_timeout_seconds, now)?;
+        let remaining = session.remaining_balance.saturating_sub(refund);
+
+        if refund > 0 {
+            session_token_transfer(
+                &ctx.accounts.token_program,
+                session,
+                &ctx.accounts.session_token_account,
+                &ctx.accounts.user_token_account,
+                refund,
+            )?;
+        }
+
+        if remaining == 0 {
+            sweep_and_close_escrow(
+                &ctx.accounts.token_program,
+                session,
+                &mut ctx.accounts.session_token_account,
+                Some(&ctx.accounts.user_token_account),
+                ctx.accounts.user.to_account_info(),
+            )?;
+        }
+
+        let session = &mut ctx.accounts.session;
+        session.remaining_balance = remaining;
+        session.refunded_amount = session.refunded_amount.saturating_add(refund);
+        session.state = SessionState::Closed;
+        session.release_capacity(&mut ctx.accounts.node);
+
+        Ok(())
+    }
+
+    pub fn claim_payout_spl(ctx: Context<ClaimPayoutSpl>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let session = &ctx.accounts.session;
+
+        authorize_provider(&ctx.accounts.provider, &ctx.accounts.authority.key(), &ctx.accounts.operator, OPERATOR_PERMISSION_CLAIM)?;
+
+        let ended = now >= session.end_ts;
+        let closed = session.state == SessionState::Closed;
+
+        require!(ended || closed, DvpnError::SessionNotEnded);
+        require!(session.state != SessionState::Claimed, DvpnError::AlreadyClaimed);
+        // A dispute freezes the escrow until it is withdrawn or resolved
+        require!(session.state != SessionState::Disputed, DvpnError::SessionDisputed);
+        require!(session.payment_token != Pubkey::default(), DvpnError::WrongPaymentToken);
+
+        // Split revenue between provider and treasury (ProgramConfig.treasury_share_bps)
+        let amount = session.remaining_balance;
+
+        // The escrow is already gone if an earlier refund emptied and closed it
+        if let Some(escrow) = ctx.accounts.session_token_account.as_mut() {
+            let treasury_share = ctx.accounts.config.treasury_share(amount)?;
+            let provider_share = amount.saturating_sub(treasury_share);
+
+            if provider_share > 0 {
+                session_token_transfer(
+                    &ctx.accounts.token_program,
+                    session,
+                    escrow,
+                    &ctx.accounts.provider_token_account,
+                    provider_share,
+                )?;
+            }
+            if treasury_share > 0 {
+                session_token_transfer(
+                    &ctx.accounts.token_program,
+                    session,
+                    escrow,
+                    &ctx.accounts.treasury_token_account,
+                    treasury_share,
+                )?;
+            }
+
+            // Escrow is now settled: return stray tokens and its rent to the user who funded it
+            sweep_and_close_escrow(
+                &ctx.accounts.token_program,
+                session,
+                escrow,
+                ctx.accounts.user_token_account.as_ref(),
+                ctx.accounts.user.to_account_info(),
+            )?;
+        } else {
+            require!(amount == 0, DvpnError::TokenAccountMissing);
+        }
+
+        let duration = session.end_ts.saturating_sub(session.start_ts) as u64;
+
+        let session = &mut ctx.accounts.session;
+        let provider = &mut ctx.accounts.provider;
+        let node = &mut ctx.accounts.node;
+        session.remaining_balance = 0;
+        session.state = SessionState::Claimed;
+        session.release_capacity(node);
+        node.unsettled_sessions = node.unsettled_sessions.saturating_sub(1);
+
+        // total_earnings is denominated in lamports, so token payouts are not added to it
+
+        // Update uptime stats
+        provider.total_uptime_seconds = provider.total_uptime_seconds.saturating_add(duration);
+        node.total_uptime_seconds = node.total_uptime_seconds.saturating_add(duration);
+
+        Ok(())
+    }
+
+    pub fn claim_chunk_spl(
+        ctx: Context<ClaimChunkSpl>,
+        cumulative_bytes: u64,
+        cumulative_amount: u64,
+        nonce: u64,
+    ) -> Result<()> {
+        let session = &ctx.accounts.session;
+
+        authorize_provider(&ctx.accounts.provider, &ctx.accounts.authority.key(), &ctx.accounts.operator, OPERATOR_PERMISSION_CLAIM)?;
+        require!(session.state == SessionState::Active, DvpnError::SessionNotActive);
+        require!(session.payment_token != Pubkey::default(), DvpnError::WrongPaymentToken);
+
+        // Receipts are cumulative and must strictly advance
+        require!(nonce > session.receipt_nonce, DvpnError::StaleReceipt);
+        require!(cumulative_bytes >= session.bytes_used, DvpnError::StaleReceipt);
+        require!(cumulative_amount > session.receipt_amount, DvpnError::StaleReceipt);
+
+        let message = receipt_message(&session.key(), cumulative_bytes, cumulative_amount, nonce);
+        verify_ed25519_receipt(&ctx.accounts.instructions_sysvar, &session.user, &message)?;
+
+        let amount_tokens = cumulative_amount.saturating_sub(session.receipt_amount);
+        require!(session.remaining_balance >= amount_tokens, DvpnError::InsufficientBalance);
+
+        // Split revenue between provider and treasury (ProgramConfig.treasury_share_bps)
+        let treasury_share = ctx.accounts.config.treasury_share(amount_tokens)?;
+        let provider_share = amount_tokens.saturating_sub(treasury_share);
+
+        if provider_share > 0 {
+            session_token_transfer(
+                &ctx.accounts.token_program,
+                session,
+                &ctx.accounts.session_token_account,
+                &ctx.accounts.provider_token_account,
+                provider_share,
+            )?;
+        }
+        if treasury_share > 0 {
+            session_token_transfer(
+                &ctx.accounts.token_program,
+                session,
+                &ctx.accounts.session_token_account,
+                &ctx.accounts.treasury_token_account,
+                treasury_share,
+            )?;
+        }
+
+        let session = &mut ctx.accounts.session;
+        session.last_proof_hash = anchor_lang::solana_program::hash::hash(&message).to_bytes();
+        session.bytes_used = cumulative_bytes;
+        session.receipt_amount = cumulative_amount;
+        session.receipt_nonce = nonce;
+        session.remaining_balance = session.remaining_balance.saturating_sub(amount_tokens);
+
+        Ok(())
+    }
+
+    pub fn resolve_dispute_spl(ctx: Context<ResolveDisputeSpl>) -> Result<()> {
+        let panel = &ctx.accounts.dispute_panel;
+        let session = &ctx.accounts.session;
+
+        require!(session.state == SessionState::Disputed, DvpnError::SessionNotDisputed);
+        require!(session.payment_token != Pubkey::default(), DvpnError::WrongPaymentToken);
+        require!(panel.decided, DvpnError::DisputeUndecided);
+
+        let refund_to_user = panel.refund_to_user.min(session.remaining_balance);
+        let slash_amount = panel.slash_amount.min(ctx.accounts.provider.slashable_lamports());
+
+        // Refund to user
+        if refund_to_user > 0 {
+            session_token_transfer(
+                &ctx.accounts.token_program,
+                session,
+                &ctx.accounts.session_token_account,
+                &ctx.accounts.user_token_account,
+                refund_to_user,
+            )?;
+        }
+
+        let remaining = session.remaining_balance.saturating_sub(refund_to_user);
+        if remaining == 0 {
+            sweep_and_close_escrow(
+                &ctx.accounts.token_program,
+                session,
+                &mut ctx.accounts.session_token_account,
+                Some(&ctx.accounts.user_token_account),
+                ctx.accounts.user.to_account_info(),
+            )?;
+        }
+
+        let session = &mut ctx.accounts.session;
+        session.remaining_balance = remaining;
+
+        // Slash provider stake and pay it out to user / treasury / insurance
+        let provider = &mut ctx.accounts.provider;
+        if slash_amount > 0 {
+            provider.slash(slash_amount);
+            provider.reputation_score = provider.reputation_score.saturating_sub(100);
+        }
+        ctx.accounts.slash_record.bump = ctx.bumps.slash_record;
+        distribute_slash(
+            &ctx.accounts.config,
+            provider,
+            &ctx.accounts.user,
+            &mut ctx.accounts.treasury,
+            &mut ctx.accounts.insurance_fund,
+            &mut ctx.accounts.slash_record,
+            session.key(),
+            slash_amount,
+        )?;
+
+        session.state = SessionState::Resolved;
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.resolved_at = Clock::get()?.unix_timestamp;
+        let counterparty = if dispute.raised_by_user { &ctx.accounts.payout } else { &ctx.accounts.user };
+        // Any refund or slash rules for the user, so a provider raiser loses
+        let user_won = refund_to_user > 0 || slash_amount > 0;
+        let raiser_won = dispute.raised_by_user == user_won;
+        settle_dispute_bond(
+            &ctx.accounts.config,
+            dispute,
+            &ctx.accounts.raiser,
+            counterparty,
+            &mut ctx.accounts.treasury,
+            raiser_won,
+        )?;
+
+        Ok(())
+    }
+
+    // SPL counterpart of auto_resolve_dispute
+    pub fn auto_resolve_dispute_spl(ctx: Context<AutoResolveDisputeSpl>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let session = &ctx.accounts.session;
+
+        require!(session.state == SessionState::Disputed, DvpnError::SessionNotDisputed);
+        require!(session.payment_token != Pubkey::default(), DvpnError::WrongPaymentToken);
+        require!(now >= ctx.accounts.dispute.deadline_ts, DvpnError::DisputeDeadlineNotReached);
+        require!(!panel_decided(&ctx.accounts.dispute_panel)?, DvpnError::DisputeAlreadyDecided);
+
+        let refund = session.prorated_refund(ctx.accounts.dispute.raised_at);
+        if refund > 0 {
+            session_token_transfer(
+                &ctx.accounts.token_program,
+                session,
+                &ctx.accounts.session_token_account,
+                &ctx.accounts.user_token_account,
+                refund,
+            )?;
+        }
+
+        let remaining = session.remaining_balance.saturating_sub(refund);
+        if remaining == 0 {
+            sweep_and_close_escrow(
+                &ctx.accounts.token_program,
+                session,
+                &mut ctx.accounts.session_token_account,
+                Some(&ctx.accounts.user_token_account),
+                ctx.accounts.user.to_account_info(),
+            )?;
+        }
+
+        let session = &mut ctx.accounts.session;
+        session.remaining_balance = remaining;
+        session.state = SessionState::Resolved;
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.resolved_at = now;
+        return_dispute_bond(dispute, &ctx.accounts.raiser)?;
+
+        Ok(())
+    }
+
+    // SPL counterpart of settle_dispute
+    pub fn settle_dispute_spl(
+        ctx: Context<SettleDisputeSpl>,
+        refund_to_user: u64,
+    ) -> Result<()> {
+        let session = &ctx.accounts.session;
+
+        require!(session.state == SessionState::Disputed, DvpnError::SessionNotDisputed);
+        require!(session.payment_token != Pubkey::default(), DvpnError::WrongPaymentToken);
+        require!(session.remaining_balance >= refund_to_user, DvpnError::InsufficientBalance);
+
+        if refund_to_user > 0 {
+            session_token_transfer(
+                &ctx.accounts.token_program,
+                session,
+                &ctx.accounts.session_token_account,
+                &ctx.accounts.user_token_account,
+                refund_to_user,
+            )?;
+        }
+
+        let remaining = session.remaining_balance.saturating_sub(refund_to_user);
+        if remaining == 0 {
+            sweep_and_close_escrow(
+                &ctx.accounts.token_program,
+                session,
+                &mut ctx.accounts.session_token_account,
+                Some(&ctx.accounts.user_token_account),
+                ctx.accounts.user.to_account_info(),
+            )?;
+        }
+
+        let session = &mut ctx.accounts.session;
+        session.remaining_balance = remaining;
+        session.state = SessionState::Resolved;
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.resolved_at = Clock::get()?.unix_timestamp;
+        return_dispute_bond(dispute, &ctx.accounts.raiser)?;
+
+        Ok(())
+    }
+
+    // ============== PROGRAM CONFIG ==============
+    // Economic parameters live in a singleton PDA so they can change without an
+    // upgrade. Every update is range-checked by ProgramConfig::apply.
+
+    // One-time setup with the historical defaults by the treasury authority,
+    // who becomes admin
+    pub fn initialize_program_config(ctx: Context<InitializeProgramConfig>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.treasury_share_bps = DEFAULT_TREASURY_SHARE_BPS;
+        config.weekly_price_lamports = DEFAULT_WEEKLY_PRICE_LAMPORTS;
+        config.monthly_price_lamports = DEFAULT_MONTHLY_PRICE_LAMPORTS;
+        config.yearly_price_lamports = DEFAULT_YEARLY_PRICE_LAMPORTS;
+        config.weekly_duration_seconds = DEFAULT_WEEKLY_DURATION_SECONDS;
+        config.monthly_duration_seconds = DEFAULT_MONTHLY_DURATION_SECONDS;
+        config.yearly_duration_seconds = DEFAULT_YEARLY_DURATION_SECONDS;
+        config.earnings_epoch_seconds = DEFAULT_EARNINGS_EPOCH_SECONDS;
+        config.max_endpoint_len = MAX_ENDPOINT_LEN as u16;
+        config.max_region_len = MAX_REGION_LEN as u16;
+        config.max_reason_len = MAX_DISPUTE_REASON_LEN as u16;
+        config.heartbeat_timeout_seconds = DEFAULT_HEARTBEAT_TIMEOUT_SECONDS;
+        config.unbonding_period_seconds = DEFAULT_UNBONDING_PERIOD_SECONDS;
+        config.min_stake_base_lamports = DEFAULT_MIN_STAKE_BASE_LAMPORTS;
+        config.min_stake_per_node_lamports = DEFAULT_MIN_STAKE_PER_NODE_LAMPORTS;
+        config.min_stake_per_capacity_lamports = DEFAULT_MIN_STAKE_PER_CAPACITY_LAMPORTS;
+        config.hashchain_grace_seconds = DEFAULT_HASHCHAIN_GRACE_SECONDS;
+        config.governance = Pubkey::default();
+        config.slash_user_bps = DEFAULT_SLASH_USER_BPS;
+        config.slash_insurance_bps = DEFAULT_SLASH_INSURANCE_BPS;
+        config.expire_crank_fee_lamports = DEFAULT_EXPIRE_CRANK_FEE_LAMPORTS;
+        config.bond_winner_bps = DEFAULT_BOND_WINNER_BPS;
+        config.bump = ctx.bumps.config;
+        Ok(())
+    }
+
+    pub fn update_program_config(
+        ctx: Context<UpdateProgramConfig>,
+        param: ConfigParam,
+    ) -> Result<()> {
+        ctx.accounts.config.apply(param)
+    }
+
+    // Hand config control to a new admin (e.g. the multisig PDA)
+    pub fn set_config_admin(
+        ctx: Context<UpdateProgramConfig>,
+        new_admin: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.config.admin = new_admin;
+        Ok(())
+    }
+
+    // Let passed provider governance proposals write the config alongside the
+    // admin (Pubkey::default() disables). A multisig admin uses SetConfigGovernance.
+    pub fn set_config_governance(
+        ctx: Context<UpdateProgramConfig>,
+        governance: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.config.governance = governance;
+        Ok(())
+    }
+
+    // ============== SUBSCRIPTION FUNCTIONS ==============
+
+    // Initialize treasury (one-time setup)
+    pub fn initialize_treasury(ctx: Context<InitializeTreasury>) -> Result<()> {
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.authority = ctx.accounts.authority.key();
+        treasury.total_collected = 0;
+        treasury.bump = ctx.bumps.treasury;
+        Ok(())
+    }
+
+    // Initialize insurance fund that receives a share of slashed stake (one-time setup)
+    pub fn initialize_insurance_fund(ctx: Context<InitializeInsuranceFund>) -> Result<()> {
+        let fund = &mut ctx.accounts.insurance_fund;
+        fund.authority = ctx.accounts.authority.key();
+        fund.total_received = 0;
+        fund.bump = ctx.bumps.insurance_fund;
+        Ok(())
+    }
+
+    // Create subscription - user pays SOL to escrow
+    pub fn create_subscription(
+        ctx: Context<CreateSubscription>,
+        plan: SubscriptionPlan,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        
+        // Calculate price and duration based on plan
+        let (price_lamports, duration_seconds) = ctx.accounts.config.plan_terms(plan);
+
+        // Transfer SOL from user -> subscription PDA (escrow)
+        let ix = anchor_lang::solana_program::system_instruction::transfer(
+            &ctx.accounts.user.key(),
+            &ctx.accounts.subscription.key(),
+            price_lamports,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.user.to_account_info(),
+                ctx.accounts.subscription.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.user = ctx.accounts.user.key();
+        subscription.plan = plan;
+        subscription.escrow_lamports = price_lamports;
+        subscription.start_ts = now;
+        subscription.end_ts = now + duration_seconds;
+        subscription.state = SubscriptionState::Active;
+        subscription.bump = ctx.bumps.subscription;
+        subscription.refund_to = ctx.accounts.user.key();
+
+        Ok(())
+    }
+
+    // Renew subscription - for expired/cancelled subscriptions
+    pub fn renew_subscription(
+        ctx: Context<RenewSubscription>,
+        plan: SubscriptionPlan,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        
+        // Verify the subscription belongs to this user
+        require_keys_eq!(ctx.accounts.subscription.user, ctx.accounts.user.key(), DvpnError::Unauthorized);
+        
+        // Only allow renewal if expired or cancelled
+        let is_expired = now >= ctx.accounts.subscription.end_ts;
+        let is_cancelled = ctx.accounts.subscription.state == SubscriptionState::Cancelled;
+        require!(is_expired || is_cancelled, DvpnError::SubscriptionStillActive);
+
+        // Calculate price and duration based on plan
+        let (price_lamports, duration_seconds) = ctx.accounts.config.plan_terms(plan);
+
+        // Transfer SOL from user -> subscription PDA (escrow)
+        let ix = anchor_lang::solana_program::system_instruction::transfer(
+            &ctx.accounts.user.key(),
+            &ctx.accounts.subscription.key(),
+            price_lamports,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.user.to_account_info(),
+                ctx.accounts.subscription.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        // Update subscription
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.plan = plan;
+        subscription.escrow_lamports = price_lamports;
+        subscription.start_ts = now;
+        subscription.end_ts = now + duration_seconds;
+        subscription.state = SubscriptionState::Active;
+        subscription.refund_to = ctx.accounts.user.key();
+
+        Ok(())
+    }
+
+    // Cancel subscription - user gets refund for unused time
+    pub fn cancel_subscription(ctx: Context<CancelSubscription>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let subscription = &mut ctx.accounts.subscription;
+
+        require_keys_eq!(subscription.user, ctx.accounts.user.key(), DvpnError::Unauthorized);
+        require!(subscription.state == SubscriptionState::Active, DvpnError::SubscriptionNotActive);
+
+        // Calculate refund for unused time
+        let elapsed = now.saturating_sub(subscription.start_ts);
+        let total_duration = subscription.end_ts.saturating_sub(subscription.start_ts);
+        
+        let refund = if elapsed < total_duration {
+            let used_fraction = (elapsed as u128)
+                .checked_mul(subscription.escrow_lamports as u128)
+                .ok_or(DvpnError::MathOverflow)?;
+            let used_amount = (used_fraction / total_duration as u128) as u64;
+            subscription.escrow_lamports.saturating_sub(used_amount)
+        } else {
+            0
+        };
+
+        // Transfer used portion to treasury
+        let used_amount = subscription.escrow_lamports.saturating_sub(refund);
+        if used_amount > 0 {
+            **subscription.to_account_info().try_borrow_mut_lamports()? -= used_amount;
+            **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += used_amount;
+            ctx.accounts.treasury.total_collected = ctx.accounts.treasury.total_collected.saturating_add(used_amount);
+        }
+
+        // Refund unused portion to wherever the escrow came from
+        if refund > 0 {
+            **subscription.to_account_info().try_borrow_mut_lamports()? -= refund;
+            **ctx.accounts.refund_to.try_borrow_mut_lamports()? += refund;
+        }
+
+        subscription.state = SubscriptionState::Cancelled;
+
+        Ok(())
+    }
+
+    // Claim subscription - provider claims their share after subscription expires
+    pub fn claim_subscription(ctx: Context<ClaimSubscription>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let subscription = &mut ctx.accounts.subscription;
+        let provider = &mut ctx.accounts.provider;
+        let node = &mut ctx.accounts.node;
+
+        require!(subscription.state == SubscriptionState::Active, DvpnError::SubscriptionNotActive);
+        require!(now >= subscription.end_ts, DvpnError::SubscriptionNotExpired);
+        
+        // Verify the provider owns this node
+        authorize_provider(provider, &ctx.accounts.authority.key(), &ctx.accounts.operator, OPERATOR_PERMISSION_CLAIM)?;
+
+        let amount = subscription.escrow_lamports;
+
+        if amount > 0 {
+            // Split between provider and treasury (ProgramConfig.treasury_share_bps)
+            let treasury_share = ctx.accounts.config.treasury_share(amount)?;
+            let provider_share = amount.saturating_sub(treasury_share);
+
+            **subscription.to_account_info().try_borrow_mut_lamports()? -= amount;
+            
+            // Provider share
+            **ctx.accounts.payout.to_account_info().try_borrow_mut_lamports()? += provider_share;
+            
+            // Treasury share
+            **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += treasury_share;
+            ctx.accounts.treasury.total_collected = ctx.accounts.treasury.total_collected.saturating_add(treasury_share);
+            
+            // Track provider earnings
+            provider.total_earnings = provider.total_earnings.saturating_add(provider_share);
+            node.total_earnings = node.total_earnings.saturating_add(provider_share);
+        }
+
+        subscription.escrow_lamports = 0;
+        subscription.state = SubscriptionState::Claimed;
+
+        Ok(())
+    }
+
+    // Check if user has active subscription (view function for frontend)
+    pub fn check_subscription(ctx: Context<CheckSubscription>) -> Result<bool> {
+        let now = Clock::get()?.unix_timestamp;
+        let subscription = &ctx.accounts.subscription;
+
+        let is_active = subscription.state == SubscriptionState::Active && now < subscription.end_ts;
+        
+        Ok(is_active)
+    }
+
+    // Withdraw from treasury (admin only)
+    pub fn withdraw_treasury(
+        ctx: Context<WithdrawTreasury>,
+        amount: u64,
+    ) -> Result<()> {
+        let treasury = &mut ctx.accounts.treasury;
+
+        require_keys_eq!(treasury.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+        require!(amount > 0, DvpnError::InvalidAmount);
+
+        **treasury.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        Ok(())
+    }
+
+    // Hand the treasury to a new authority; initialize_multisig hands it to the multisig PDA
+    pub fn set_treasury_authority(
+        ctx: Context<WithdrawTreasury>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let treasury = &mut ctx.accounts.treasury;
+        require_keys_eq!(treasury.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+
+        treasury.authority = new_authority;
+        Ok(())
+    }
+
+    // ============== MULTISIG ==============
+    // M-of-N owners govern the treasury and ProgramConfig once their authority
+    // is set to the multisig PDA. Sensitive actions wait out a timelock after
+    // reaching threshold so pending changes are visible on-chain first.
+
+    // One-time setup by the treasury authority, which hands the treasury to the
+    // multisig and stays on only as earnings reporter
+    pub fn initialize_multisig(
+        ctx: Context<InitializeMultisig>,
+        owners: Vec<Pubkey>,
+        threshold: u8,
+        timelock_seconds: i64,
+        withdraw_timelock_threshold_lamports: u64,
+    ) -> Result<()> {
+        for (i, owner) in owners.iter().enumerate() {
+            require!(!owners[..i].contains(owner), DvpnError::InvalidMultisigOwners);
+        }
+        require!(owners.len() <= MAX_MULTISIG_OWNERS, DvpnError::InvalidMultisigOwners);
+        require!(threshold >= 1 && threshold as usize <= owners.len(), DvpnError::InvalidThreshold);
+        require!(
+            (MIN_TIMELOCK_SECONDS..=MAX_TIMELOCK_SECONDS).contains(&timelock_seconds),
+            DvpnError::ConfigOutOfRange
+        );
+
+        let multisig = &mut ctx.accounts.multisig;
+        multisig.owners = owners;
+        multisig.threshold = threshold;
+        multisig.timelock_seconds = timelock_seconds;
+        multisig.withdraw_timelock_threshold_lamports = withdraw_timelock_threshold_lamports;
+        multisig.proposal_count = 0;
+        multisig.earnings_reporter = ctx.accounts.authority.key();
+        multisig.bump = ctx.bumps.multisig;
+
+        ctx.accounts.treasury.authority = multisig.key();
+        Ok(())
+    }
+
+    // Owner proposes an action; counts as their approval
+    pub fn create_multisig_proposal(
+        ctx: Context<CreateMultisigProposal>,
+        action: MultisigAction,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let owner = ctx.accounts.owner.key();
+        let multisig = &mut ctx.accounts.multisig;
+        require!(multisig.owners.contains(&owner), DvpnError::NotMultisigOwner);
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.multisig = multisig.key();
+        proposal.proposal_id = multisig.proposal_count;
+        proposal.proposer = owner;
+        proposal.action = action;
+        proposal.timelocked = multisig.requires_timelock(&action);
+        proposal.approvals = vec![owner];
+        proposal.created_at = now;
+        proposal.eta = 0;
+        proposal.executed = false;
+        proposal.bump = ctx.bumps.proposal;
+        proposal.queue_if_approved(multisig, now);
+
+        multisig.proposal_count = multisig.proposal_count.checked_add(1).ok_or(DvpnError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn approve_multisig_proposal(ctx: Context<ApproveMultisigProposal>) -> Result<()> {
+        let owner = ctx.accounts.owner.key();
+        let multisig = &ctx.accounts.multisig;
+        let proposal = &mut ctx.accounts.proposal;
+
+        require!(multisig.owners.contains(&owner), DvpnError::NotMultisigOwner);
+        require!(!proposal.executed, DvpnError::ProposalAlreadyExecuted);
+        require!(!proposal.approvals.contains(&owner), DvpnError::AlreadyApproved);
+
+        proposal.approvals.push(owner);
+        proposal.queue_if_approved(multisig, Clock::get()?.unix_timestamp);
+        Ok(())
+    }
+
+    // Permissionless once approved and past the timelock. Accounts the action
+    // doesn't touch may be omitted.
+    pub fn execute_multisig_proposal(ctx: Context<ExecuteMultisigProposal>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let multisig_key = ctx.accounts.multisig.key();
+        let multisig = &mut ctx.accounts.multisig;
+        let proposal = &mut ctx.accounts.proposal;
+
+        require!(!proposal.executed, DvpnError::ProposalAlreadyExecuted);
+        // Approvals from since-removed owners no longer count
+        require!(multisig.approval_count(&proposal.approvals) >= multisig.threshold as usize, DvpnError::NotEnoughApprovals);
+        require!(proposal.eta != 0 && now >= proposal.eta, DvpnError::TimelockNotElapsed);
+
+        match proposal.action {
+            MultisigAction::WithdrawTreasury { recipient, amount } => {
+                let treasury = ctx.accounts.treasury.as_mut().ok_or(DvpnError::ActionAccountMissing)?;
+                let to = ctx.accounts.recipient.as_ref().ok_or(DvpnError::ActionAccountMissing)?;
+                require_keys_eq!(treasury.authority, multisig_key, DvpnError::Unauthorized);
+                require_keys_eq!(to.key(), recipient, DvpnError::Unauthorized);
+                require!(amount > 0, DvpnError::InvalidAmount);
+
+                **treasury.to_account_info().try_borrow_mut_lamports()? -= amount;
+                **to.try_borrow_mut_lamports()? += amount;
+            }
+            MultisigAction::FinalizeEarningsPool { pool, total_weighted_score } => {
+                let treasury = ctx.accounts.treasury.as_ref().ok_or(DvpnError::ActionAccountMissing)?;
+                let earnings_pool = ctx.accounts.earnings_pool.as_mut().ok_or(DvpnError::ActionAccountMissing)?;
+                require_keys_eq!(treasury.authority, multisig_key, DvpnError::Unauthorized);
+                require_keys_eq!(earnings_pool.key(), pool, DvpnError::Unauthorized);
+                require!(!earnings_pool.is_finalized, DvpnError::PoolAlreadyFinalized);
+                require!(now >= earnings_pool.end_ts, DvpnError::PoolNotEnded);
+
+                earnings_pool.total_weighted_score = total_weighted_score;
+                earnings_pool.is_finalized = true;
+            }
+            MultisigAction::UpdateConfig(param) => {
+                let config = ctx.accounts.config.as_mut().ok_or(DvpnError::ActionAccountMissing)?;
+                require_keys_eq!(config.admin, multisig_key, DvpnError::Unauthorized);
+                config.apply(param)?;
+            }
+            MultisigAction::SetConfigGovernance(governance) => {
+                let config = ctx.accounts.config.as_mut().ok_or(DvpnError::ActionAccountMissing)?;
+                require_keys_eq!(config.admin, multisig_key, DvpnError::Unauthorized);
+                config.governance = governance;
+            }
+            MultisigAction::SetGovernanceParams(params) => {
+                let config = ctx.accounts.config.as_ref().ok_or(DvpnError::ActionAccountMissing)?;
+                let governance = ctx.accounts.governance.as_mut().ok_or(DvpnError::ActionAccountMissing)?;
+                require_keys_eq!(config.admin, multisig_key, DvpnError::Unauthorized);
+                params.validate()?;
+                governance.params = params;
+            }
+            MultisigAction::AddOwner(owner) => {
+                require!(!multisig.owners.contains(&owner), DvpnError::InvalidMultisigOwners);
+                require!(multisig.owners.len() < MAX_MULTISIG_OWNERS, DvpnError::InvalidMultisigOwners);
+                multisig.owners.push(owner);
+            }
+            MultisigAction::RemoveOwner(owner) => {
+                let before = multisig.owners.len();
+                multisig.owners.retain(|o| *o != owner);
+                require!(multisig.owners.len() < before, DvpnError::NotMultisigOwner);
+                require!(multisig.threshold as usize <= multisig.owners.len(), DvpnError::InvalidThreshold);
+            }
+            MultisigAction::SetThreshold(threshold) => {
+                require!(threshold >= 1 && threshold as usize <= multisig.owners.len(), DvpnError::InvalidThreshold);
+                multisig.threshold = threshold;
+            }
+            MultisigAction::SetTimelock(seconds) => {
+                require!(
+                    (MIN_TIMELOCK_SECONDS..=MAX_TIMELOCK_SECONDS).contains(&seconds),
+                    DvpnError::ConfigOutOfRange
+                );
+                multisig.timelock_seconds = seconds;
+            }
+            MultisigAction::SetWithdrawTimelockThreshold(lamports) => {
+                multisig.withdraw_timelock_threshold_lamports = lamports;
+            }
+            MultisigAction::SetEarningsReporter(reporter) => {
+                multisig.earnings_reporter = reporter;
+            }
+        }
+
+        proposal.executed = true;
+        Ok(())
+    }
+
+    // ============== PROVIDER GOVERNANCE ==============
+    // Providers vote on typed ProgramConfig changes weighted by their own stake.
+    // Only stake already in place when a proposal was created may vote on it.
+    // Passing proposals apply while config.governance is the governance PDA;
+    // the admin (typically the multisig) keeps direct control and can revoke it.
+
+    // One-time setup by the config admin
+    pub fn initialize_governance(
+        ctx: Context<InitializeGovernance>,
+        params: GovernanceParams,
+    ) -> Result<()> {
+        params.validate()?;
+
+        let governance = &mut ctx.accounts.governance;
+        governance.params = params;
+        governance.proposal_count = 0;
+        governance.bump = ctx.bumps.governance;
+        Ok(())
+    }
+
+    // Applies to proposals created after the change
+    pub fn set_governance_params(
+        ctx: Context<UpdateGovernance>,
+        params: GovernanceParams,
+    ) -> Result<()> {
+        params.validate()?;
+        ctx.accounts.governance.params = params;
+        Ok(())
+    }
+
+    pub fn create_governance_proposal(
+        ctx: Context<CreateGovernanceProposal>,
+        param: ConfigParam,
+    ) -> Result<()> {
+        let provider = &ctx.accounts.provider;
+        let governance = &mut ctx.accounts.governance;
+
+        require_keys_eq!(provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+        require!(provider.stake_lamports >= governance.params.min_proposal_stake_lamports, DvpnError::InsufficientStake);
+
+        // Reject out-of-range values now rather than at execution
+        let mut preview = (*ctx.accounts.config).clone();
+        preview.apply(param)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.proposal_id = governance.proposal_count;
+        proposal.proposer = provider.key();
+        proposal.param = param;
+        proposal.created_at = now;
+        proposal.voting_ends_at = now.saturating_add(governance.params.voting_period_seconds);
+        proposal.quorum_lamports = governance.params.quorum_lamports;
+        proposal.approval_bps = governance.params.approval_bps;
+        proposal.yes_lamports = 0;
+        proposal.no_lamports = 0;
+        proposal.executed = false;
+        proposal.bump = ctx.bumps.proposal;
+
+        governance.proposal_count = governance.proposal_count.checked_add(1).ok_or(DvpnError::MathOverflow)?;
+        Ok(())
+    }
+
+    // One vote per provider per proposal (enforced by the vote record PDA)
+    pub fn cast_governance_vote(
+        ctx: Context<CastGovernanceVote>,
+        approve: bool,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let provider = &ctx.accounts.provider;
+        let proposal = &mut ctx.accounts.proposal;
+
+        require_keys_eq!(provider.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+        require!(now < proposal.voting_ends_at, DvpnError::VotingClosed);
+
+        // Stake held at the snapshot and still held now; top-ups don't count
+        let weight = provider.governance_weight(proposal.created_at);
+        require!(weight > 0, DvpnError::InsufficientStake);
+        if approve {
+            proposal.yes_lamports = proposal.yes_lamports.checked_add(weight).ok_or(DvpnError::MathOverflow)?;
+        } else {
+            proposal.no_lamports = proposal.no_lamports.checked_add(weight).ok_or(DvpnError::MathOverflow)?;
+        }
+
+        let vote = &mut ctx.accounts.vote;
+        vote.proposal = proposal.key();
+        vote.provider = provider.key();
+        vote.approve = approve;
+        vote.weight_lamports = weight;
+        vote.bump = ctx.bumps.vote;
+        Ok(())
+    }
+
+    // Permissionless once voting has closed and the proposal passed
+    pub fn execute_governance_proposal(ctx: Context<ExecuteGovernanceProposal>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &mut ctx.accounts.proposal;
+        let config = &mut ctx.accounts.config;
+
+        require!(!proposal.executed, DvpnError::ProposalAlreadyExecuted);
+        require!(now >= proposal.voting_ends_at, DvpnError::VotingStillOpen);
+        require!(proposal.passed(), DvpnError::ProposalNotPassed);
+        require_keys_eq!(config.governance, ctx.accounts.governance.key(), DvpnError::Unauthorized);
+
+        config.apply(proposal.param)?;
+        proposal.executed = true;
+        Ok(())
+    }
+
+    // ============== USER VAULT ==============
+    // Prepaid deposit that funds sessions and subscriptions; refunds of
+    // vault-funded sessions flow back into it (Session.refund_to).
+
+    pub fn initialize_user_vault(
+        ctx: Context<InitializeUserVault>,
+        daily_limit_lamports: u64,
+        daily_limit_tokens: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.user_vault;
+        vault.owner = ctx.accounts.user.key();
+        vault.daily_limit_lamports = daily_limit_lamports;
+        vault.daily_limit_tokens = daily_limit_tokens;
+        vault.spent_today_lamports = 0;
+        vault.spent_today_tokens = 0;
+        vault.day_start_ts = Clock::get()?.unix_timestamp;
+        vault.bump = ctx.bumps.user_vault;
+        Ok(())
+    }
+
+    // 0 disables the corresponding limit
+    pub fn set_vault_limits(
+        ctx: Context<UpdateUserVault>,
+        daily_limit_lamports: u64,
+        daily_limit_tokens: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.user_vault;
+        vault.daily_limit_lamports = daily_limit_lamports;
+        vault.daily_limit_tokens = daily_limit_tokens;
+        Ok(())
+    }
+
+    pub fn deposit_vault(
+        ctx: Context<DepositVault>,
+        amount_lamports: u64,
+    ) -> Result<()> {
+        require!(amount_lamports > 0, DvpnError::InvalidAmount);
+
+        // Transfer SOL from user -> vault PDA
+        let ix = anchor_lang::solana_program::system_instruction::transfer(
+            &ctx.accounts.user.key(),
+            &ctx.accounts.user_vault.key(),
+            amount_lamports,
+        );
+        anchor_lang::solana_program::program::invoke(
+            &ix,
+            &[
+                ctx.accounts.user.to_account_info(),
+                ctx.accounts.user_vault.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn withdraw_vault(
+        ctx: Context<UpdateUserVault>,
+        amount_lamports: u64,
+    ) -> Result<()> {
+        require!(amount_lamports > 0, DvpnError::InvalidAmount);
+
+        let vault = &ctx.accounts.user_vault;
+        require!(UserVault::available_lamports(&vault.to_account_info())? >= amount_lamports, DvpnError::InsufficientBalance);
+
+        **vault.to_account_info().try_borrow_mut_lamports()? -= amount_lamports;
+        **ctx.accounts.user.to_account_info().try_borrow_mut_lamports()? += amount_lamports;
+
+        Ok(())
+    }
+
+    pub fn deposit_vault_spl(
+        ctx: Context<DepositVaultSpl>,
+        amount_tokens: u64,
+    ) -> Result<()> {
+        require!(amount_tokens > 0, DvpnError::InvalidAmount);
+
+        // Transfer SPL tokens from user -> vault token account
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.user_token_account.to_account_info(),
+                to: ctx.accounts.vault_token_account.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        );
+        token::transfer(cpi_ctx, amount_tokens)?;
+
+        Ok(())
+    }
+
+    pub fn withdraw_vault_spl(
+        ctx: Context<WithdrawVaultSpl>,
+        amount_tokens: u64,
+    ) -> Result<()> {
+        require!(amount_tokens > 0, DvpnError::InvalidAmount);
+
+        vault_token_transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.user_vault,
+            &ctx.accounts.vault_token_account,
+            &ctx.accounts.user_token_account,
+            amount_tokens,
+        )
+    }
+
+    // Same as open_session, but escrow is debited from the user's vault
+    pub fn open_session_from_vault(
+        ctx: Context<OpenSessionFromVault>,
+        session_id: u64,
+        minutes: u32,
+    ) -> Result<()> {
+        require!(minutes > 0, DvpnError::InvalidMinutes);
+
+        let now = Clock::get()?.unix_timestamp;
+        let node = &mut ctx.accounts.node;
+        node.promote_wg_key(now);
+
+        require!(node.is_active, DvpnError::NodeInactive);
+        require!(node.active_sessions < node.max_capacity, DvpnError::NodeAtCapacity);
+        require!(ctx.accounts.provider.meets_minimum_stake(&ctx.accounts.config), DvpnError::BelowMinimumStake);
+
+        let cost = (minutes as u64)
+            .checked_mul(node.price_per_minute_lamports)
+            .ok_or(DvpnError::MathOverflow)?;
+
+        // Debit vault -> session PDA (escrow)
+        let vault = &mut ctx.accounts.user_vault;
+        require!(UserVault::available_lamports(&vault.to_account_info())? >= cost, DvpnError::InsufficientBalance);
+        vault.record_spend_lamports(cost, now)?;
+        **vault.to_account_info().try_borrow_mut_lamports()? -= cost;
+        **ctx.accounts.session.to_account_info().try_borrow_mut_lamports()? += cost;
+
+        let session = &mut ctx.accounts.session;
+        session.init(
+            ctx.accounts.user.key(),
+            node,
+            session_id,
+            now,
+            now + (minutes as i64) * 60,
+            cost,
+            Pubkey::default(),
+            vault.key(),
+            ctx.bumps.session,
+        );
+
+        node.active_sessions = node.active_sessions.saturating_add(1);
+        node.unsettled_sessions = node.unsettled_sessions.saturating_add(1);
+        ctx.accounts.provider.total_sessions = ctx.accounts.provider.total_sessions.saturating_add(1);
+
+        Ok(())
+    }
+
+    // Same as open_session_spl, but tokens come from the vault token account
+    pub fn open_session_spl_from_vault(
+        ctx: Context<OpenSessionSplFromVault>,
+        session_id: u64,
+        minutes: u32,
+    ) -> Result<()> {
+        require!(minutes > 0, DvpnError::InvalidMinutes);
+
+        let now = Clock::get()?.unix_timestamp;
+        let node = &mut ctx.accounts.node;
+        node.promote_wg_key(now);
+
+        require!(node.is_active, DvpnError::NodeInactive);
+        require!(node.active_sessions < node.max_capacity, DvpnError::NodeAtCapacity);
+        require!(ctx.accounts.provider.meets_minimum_stake(&ctx.accounts.config), DvpnError::BelowMinimumStake);
+
+        // Price comes from the node's published list of accepted mints
+        let price_per_minute = ctx.accounts.node_pricing
+            .price_for(&ctx.accounts.mint.key())
+            .ok_or(DvpnError::MintNotAccepted)?;
+        let amount_tokens = (minutes as u64)
+            .checked_mul(price_per_minute)
+            .ok_or(DvpnError::MathOverflow)?;
+
+        ctx.accounts.user_vault.record_spend_tokens(amount_tokens, now)?;
+        vault_token_transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.user_vault,
+            &ctx.accounts.vault_token_account,
+            &ctx.accounts.session_token_account,
+            amount_tokens,
+        )?;
+
+        let session = &mut ctx.accounts.session;
+        session.init(
+            ctx.accounts.user.key(),
+            &ctx.accounts.node,
+            session_id,
+            now,
+            now + (minutes as i64) * 60,
+            amount_tokens,
+            ctx.accounts.mint.key(),
+            ctx.accounts.user_vault.key(),
+            ctx.bumps.session,
+        );
+
+        let node = &mut ctx.accounts.node;
+        node.active_sessions = node.active_sessions.saturating_add(1);
+        node.unsettled_sessions = node.unsettled_sessions.saturating_add(1);
+        ctx.accounts.provider.total_sessions = ctx.accounts.provider.total_sessions.saturating_add(1);
+
+        Ok(())
+    }
+
+    // Same as extend_session, for a session opened from the vault
+    pub fn extend_session_from_vault(
+        ctx: Context<ExtendSessionFromVault>,
+        minutes: u32,
+    ) -> Result<()> {
+        require!(minutes > 0, DvpnError::InvalidMinutes);
+
+        let now = Clock::get()?.unix_timestamp;
+        let session = &ctx.accounts.session;
+        let node = &ctx.accounts.node;
+
+        require_keys_eq!(session.user, ctx.accounts.user.key(), DvpnError::Unauthorized);
+        require!(session.state == SessionState::Active, DvpnError::SessionNotActive);
+        require!(session.payment_token == Pubkey::default(), DvpnError::WrongPaymentToken);
+        require!(now < session.end_ts, DvpnError::SessionEnded);
+        require!(node.is_active, DvpnError::NodeInactive);
+
+        let cost = (minutes as u64)
+            .checked_mul(node.price_per_minute_lamports)
+            .ok_or(DvpnError::MathOverflow)?;
+
+        // Debit vault -> session PDA (escrow)
+        let vault = &mut ctx.accounts.user_vault;
+        require!(UserVault::available_lamports(&vault.to_account_info())? >= cost, DvpnError::InsufficientBalance);
+        vault.record_spend_lamports(cost, now)?;
+        **vault.to_account_info().try_borrow_mut_lamports()? -= cost;
+        **ctx.accounts.session.to_account_info().try_borrow_mut_lamports()? += cost;
+
+        ctx.accounts.session.extend(minutes, cost)
+    }
+
+    // Same as extend_session_spl, for a session opened from the vault
+    pub fn extend_session_spl_from_vault(
+        ctx: Context<ExtendSessionSplFromVault>,
+        minutes: u32,
+    ) -> Result<()> {
+        require!(minutes > 0, DvpnError::InvalidMinutes);
+
+        let now = Clock::get()?.unix_timestamp;
+        let session = &ctx.accounts.session;
+
+        require_keys_eq!(session.user, ctx.accounts.user.key(), DvpnError::Unauthorized);
+        require!(session.state == SessionState::Active, DvpnError::SessionNotActive);
+        require!(session.payment_token != Pubkey::default(), DvpnError::WrongPaymentToken);
+        require!(now < session.end_ts, DvpnError::SessionEnded);
+        require!(ctx.accounts.node.is_active, DvpnError::NodeInactive);
+
+        let price_per_minute = ctx.accounts.node_pricing
+            .price_for(&session.payment_token)
+            .ok_or(DvpnError::MintNotAccepted)?;
+        let amount_tokens = (minutes as u64)
+            .checked_mul(price_per_minute)
+            .ok_or(DvpnError::MathOverflow)?;
+
+        ctx.accounts.user_vault.record_spend_tokens(amount_tokens, now)?;
+        vault_token_transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.user_vault,
+            &ctx.accounts.vault_token_account,
+            &ctx.accounts.session_token_account,
+            amount_tokens,
+        )?;
+
+        ctx.accounts.session.extend(minutes, amount_tokens)
+    }
+
+    // Same as create_subscription, but paid from the user's vault
+    pub fn create_subscription_from_vault(
+        ctx: Context<CreateSubscriptionFromVault>,
+        plan: SubscriptionPlan,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+
+        // Calculate price and duration based on plan
+        let (price_lamports, duration_seconds) = ctx.accounts.config.plan_terms(plan);
+
+        // Debit vault -> subscription PDA (escrow)
+        let vault = &mut ctx.accounts.user_vault;
+        require!(UserVault::available_lamports(&vault.to_account_info())? >= price_lamports, DvpnError::InsufficientBalance);
+        vault.record_spend_lamports(price_lamports, now)?;
+        **vault.to_account_info().try_borrow_mut_lamports()? -= price_lamports;
+        **ctx.accounts.subscription.to_account_info().try_borrow_mut_lamports()? += price_lamports;
+
+        let subscription = &mut ctx.accounts.subscription;
+        subscription.user = ctx.accounts.user.key();
+        subscription.plan = plan;
+        subscription.escrow_lamports = price_lamports;
+        subscription.start_ts = now;
+        subscription.end_ts = now + duration_seconds;
+        subscription.state = SubscriptionState::Active;
+        subscription.bump = ctx.bumps.subscription;
+        subscription.refund_to = vault.key();
+
+        Ok(())
+    }
+
+    // ============== FAIR EARNINGS DISTRIBUTION ==============
+
+    // Initialize earnings pool for an epoch (weekly distribution)
+    pub fn initialize_earnings_pool(
+        ctx: Context<InitializeEarningsPool>,
+        epoch: u64,
+    ) -> Result<()> {
+        authorize_earnings_reporter(&ctx.accounts.treasury, &ctx.accounts.multisig, &ctx.accounts.authority.key())?;
+
+        let pool = &mut ctx.accounts.earnings_pool;
+        pool.epoch = epoch;
+        pool.total_subscription_revenue = 0;
+        pool.total_distributed = 0;
+        pool.start_ts = Clock::get()?.unix_timestamp;
+        pool.end_ts = pool.start_ts + ctx.accounts.config.earnings_epoch_seconds;
+        pool.is_finalized = false;
+        pool.bump = ctx.bumps.earnings_pool;
+        Ok(())
+    }
+
+    // Record node usage when user ends a session
+    // This tracks how much each node was used for fair distribution; reported
+    // by the treasury authority, or the earnings reporter once the multisig
+    // holds the treasury. Only the treasury authority finalizes the pool.
+    pub fn record_node_usage(
+        ctx: Context<RecordNodeUsage>,
+        epoch: u64,
+        duration_seconds: u64,
+        bytes_transferred: u64,
+        user_rating: u8,  // 1-5 stars
+    ) -> Result<()> {
+        require!((1..=5).contains(&user_rating), DvpnError::InvalidAmount);
+        authorize_earnings_reporter(&ctx.accounts.treasury, &ctx.accounts.multisig, &ctx.accounts.authority.key())?;
+        
+        let node = &mut ctx.accounts.node;
+        let pool = &mut ctx.accounts.earnings_pool;
+        let usage = &mut ctx.accounts.usage_record;
+        
+        require!(!pool.is_finalized, DvpnError::PoolAlreadyFinalized);
+        
+        // Update node statistics
+        node.total_uptime_seconds = node.total_uptime_seconds.saturating_add(duration_seconds);
+        node.total_bytes_served = node.total_bytes_served.saturating_add(bytes_transferred);
+        node.rating_sum = node.rating_sum.saturating_add(user_rating as u64);
+        node.rating_count = node.rating_count.saturating_add(1);
+        
+        // Calculate quality score (0-10000)
+        // Based on: bandwidth weight + uptime weight + rating weight
+        let avg_rating = if node.rating_count > 0 {
+            (node.rating_sum * 2000) / node.rating_count as u64  // Max 10000 (5 stars * 2000)
+        } else {
+            10000  // Default perfect if no ratings
+        };
+        node.quality_score = (avg_rating as u16).min(10000);
+        
+        // Update or create usage record for this node in this epoch
+        usage.node = node.key();
+        usage.epoch = epoch;
+        usage.usage_seconds = usage.usage_seconds.saturating_add(duration_seconds);
+        usage.bytes_served = usage.bytes_served.saturating_add(bytes_transferred);
+        usage.session_count = usage.session_count.saturating_add(1);
+        usage.bump = ctx.bumps.usage_record;
+        
+        Ok(())
+    }
+
+    // Rate a node after using it
+    pub fn rate_node(
+        ctx: Context<RateNode>,
+        rating: u8,  // 1-5 stars
+    ) -> Result<()> {
+        require!((1..=5).contains(&rating), DvpnError::InvalidAmount);
+        
+        let node = &mut ctx.accounts.node;
+        node.rating_sum = node.rating_sum.saturating_add(rating as u64);
+        node.rating_count = node.rating_count.saturating_add(1);
+        
+        // Recalculate quality score
+        let avg_rating = (node.rating_sum * 2000) / node.rating_count as u64;
+        node.quality_score = (avg_rating as u16).min(10000);
+        
+        Ok(())
+    }
+
+    // Claim proportional earnings from the pool based on node contribution
+    // Formula: node_share = (usage_weight * quality_weight * bandwidth_weight) / total_weights
+    pub fn claim_proportional_earnings(
+        ctx: Context<ClaimProportionalEarnings>,
+        epoch: u64,
+    ) -> Result<()> {
+        let _ = epoch; // Only selects the pool and usage record via seeds
+        let pool = &mut ctx.accounts.earnings_pool;
+        let node = &mut ctx.accounts.node;
+        let provider = &mut ctx.accounts.provider;
+        let usage = &ctx.accounts.usage_record;
+
+        authorize_provider(provider, &ctx.accounts.authority.key(), &ctx.accounts.operator, OPERATOR_PERMISSION_CLAIM)?;
+        
+        require!(pool.is_finalized, DvpnError::PoolNotFinalized);
+        require!(!usage.claimed, DvpnError::AlreadyClaimed);
+        
+        // Calculate node's weighted contribution
+        // Weight factors:
+        // - Usage time: 40% weight
+        // - Bandwidth: 30% weight  
+        // - Quality score: 30% weight
+        
+        let usage_weight = usage.usage_seconds as u128;
+        let bandwidth_weight = (node.bandwidth_mbps as u128) * usage.usage_seconds as u128 / 100;
+        let quality_weight = (node.quality_score as u128) * usage.usage_seconds as u128 / 10000;
+        
+        let node_weighted_score = usage_weight
+            .saturating_mul(40)
+            .saturating_add(bandwidth_weight.saturating_mul(30))
+            .saturating_add(quality_weight.saturating_mul(30))
+            / 100;
+        
+        // Get total weighted score from pool (stored when finalized)
+        let total_weighted = pool.total_weighted_score;
+        require!(total_weighted > 0, DvpnError::InvalidAmount);
+        
+        // Calculate node's share of the pool
+        let pool_amount = pool.total_subscription_revenue.saturating_sub(pool.total_distributed);
+        let node_share = (pool_amount as u128)
+            .saturating_mul(node_weighted_score)
+            .checked_div(total_weighted)
+            .unwrap_or(0) as u64;
+        
+        if node_share > 0 {
+            // Split between provider and treasury (ProgramConfig.treasury_share_bps)
+            let treasury_share = ctx.accounts.config.treasury_share(node_share)?;
+            let provider_share = node_share.saturating_sub(treasury_share);
+            
+            // Transfer from pool to provider
+            **pool.to_account_info().try_borrow_mut_lamports()? -= node_share;
+            pay_provider_share(provider, &ctx.accounts.payout.to_account_info(), provider_share)?;
+            **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += treasury_share;
+            
+            pool.total_distributed = pool.total_distributed.saturating_add(node_share);
+            provider.total_earnings = provider.total_earnings.saturating_add(provider_share);
+            node.total_earnings = node.total_earnings.saturating_add(provider_share);
+            ctx.accounts.treasury.total_collected = ctx.accounts.treasury.total_collected.saturating_add(treasury_share);
+        }
+        
+        ctx.accounts.usage_record.claimed = true;
+
+        Ok(())
+    }
+
+    // Finalize pool - calculates total weighted scores and allows claiming
+    pub fn finalize_earnings_pool(
+        ctx: Context<FinalizeEarningsPool>,
+        total_weighted_score: u128,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.earnings_pool;
+        
+        require_keys_eq!(ctx.accounts.treasury.authority, ctx.accounts.authority.key(), DvpnError::Unauthorized);
+        require!(!pool.is_finalized, DvpnError::PoolAlreadyFinalized);
+        
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= pool.end_ts, DvpnError::PoolNotEnded);
+        
+        pool.total_weighted_score = total_weighted_score;
+        pool.is_finalized = true;
+        
+        Ok(())
+    }
+
+    // Add subscription revenue to the current epoch's pool
+    pub fn add_to_earnings_pool(
+        ctx: Context<AddToEarningsPool>,
+        amount: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.earnings_pool;
+        
+        authorize_earnings_reporter(&ctx.accounts.treasury, &ctx.accounts.multisig, &ctx.accounts.authority.key())?;
+        require!(!pool.is_finalized, DvpnError::PoolAlreadyFinalized);
+        
+        pool.total_subscription_revenue = pool.total_subscription_revenue.saturating_add(amount);
+        
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct RegisterProvider<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Provider::MAX_SIZE,
+        seeds = [PROVIDER_SEED, authority.key().as_ref()],
+        bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StakeProvider<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RequestUnstake<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawUnstaked<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+}
+
+#[derive(Accounts)]
+#[instruction(node_id: u64)]
+pub struct RegisterNode<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Node::MAX_SIZE,
+        seeds = [
+            NODE_SEED,
+            provider.key().as_ref(),
+            &node_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateNode<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        seeds = [OPERATOR_SEED, provider.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+
+    #[account(
+        mut,
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct CloseNode<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        mut,
+        close = authority,
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    /// CHECK: pricing PDA for this node; closed with it if it was ever set
+    #[account(mut, seeds = [NODE_PRICING_SEED, node.key().as_ref()], bump)]
+    pub node_pricing: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseProvider<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    /// CHECK: profile PDA for this provider; closed with it if it was created
+    #[account(mut, seeds = [PROVIDER_PROFILE_SEED, provider.key().as_ref()], bump)]
+    pub profile: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct NodeHeartbeat<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        seeds = [OPERATOR_SEED, provider.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+
+    #[account(
+        mut,
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+}
+
+#[derive(Accounts)]
+#[instruction(node_id: u64)]
+pub struct MigrateNode<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    /// CHECK: legacy-layout Node PDA; deserialized by hand after realloc
+    #[account(
+        mut,
+        seeds = [NODE_SEED, provider.key().as_ref(), &node_id.to_le_bytes()],
+        bump
+    )]
+    pub node: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAcceptedMint<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        seeds = [OPERATOR_SEED, provider.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+
+    #[account(
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + NodePricing::MAX_SIZE,
+        seeds = [NODE_PRICING_SEED, node.key().as_ref()],
+        bump
+    )]
+    pub node_pricing: Account<'info, NodePricing>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveAcceptedMint<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        seeds = [OPERATOR_SEED, provider.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+
+    #[account(
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        mut,
+        seeds = [NODE_PRICING_SEED, node.key().as_ref()],
+        bump = node_pricing.bump
+    )]
+    pub node_pricing: Account<'info, NodePricing>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_id: u64)]
+pub struct OpenSession<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        mut,
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Session::MAX_SIZE,
+        seeds = [
+            SESSION_SEED,
+            user.key().as_ref(),
+            node.key().as_ref(),
+            &session_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_id: u64)]
+pub struct OpenHashchainSession<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        mut,
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Session::MAX_SIZE,
+        seeds = [
+            SESSION_SEED,
+            user.key().as_ref(),
+            node.key().as_ref(),
+            &session_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_id: u64)]
+pub struct OpenSessionSpl<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        mut,
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Session::MAX_SIZE,
+        seeds = [
+            SESSION_SEED,
+            user.key().as_ref(),
+            node.key().as_ref(),
+            &session_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub session: Account<'info, Session>,
+
+    pub mint: Account<'info, Mint>,
+
+    #[account(
+        seeds = [NODE_PRICING_SEED, node.key().as_ref()],
+        bump = node_pricing.bump
+    )]
+    pub node_pricing: Account<'info, NodePricing>,
+
+    #[account(mut)]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = user,
+        token::mint = mint,
+        token::authority = session,
+    )]
+    pub session_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExtendSession<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    pub node: Account<'info, Node>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExtendSessionSpl<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    pub node: Account<'info, Node>,
+
+    #[account(mut)]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = session,
+    )]
+    pub session_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [NODE_PRICING_SEED, node.key().as_ref()],
+        bump = node_pricing.bump
+    )]
+    pub node_pricing: Account<'info, NodePricing>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct CloseSession<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(mut)]
+    pub node: Account<'info, Node>,
+
+    /// CHECK: refund destination recorded at open (user wallet or vault)
+    #[account(mut, address = session.refund_to @ DvpnError::Unauthorized)]
+    pub refund_to: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_session_id: u64)]
+pub struct MigrateSession<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = old_session.node == old_node.key() @ DvpnError::Unauthorized
+    )]
+    pub old_session: Account<'info, Session>,
+
+    #[account(mut)]
+    pub old_node: Account<'info, Node>,
+
+    #[account(
+        mut,
+        constraint = new_node.provider == new_provider.key() @ DvpnError::Unauthorized
+    )]
+    pub new_provider: Account<'info, Provider>,
+
+    #[account(mut)]
+    pub new_node: Account<'info, Node>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Session::MAX_SIZE,
+        seeds = [
+            SESSION_SEED,
+            user.key().as_ref(),
+            new_node.key().as_ref(),
+            &new_session_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pub new_session: Account<'info, Session>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimDowntimeRefund<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(mut)]
+    pub node: Account<'info, Node>,
+
+    /// CHECK: refund destination recorded at open (user wallet or vault)
+    #[account(mut, address = session.refund_to @ DvpnError::Unauthorized)]
+    pub refund_to: AccountInfo<'info>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimDowntimeRefundSpl<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(mut)]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = session,
+    )]
+    pub session_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = session.refund_to,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPayout<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        seeds = [OPERATOR_SEED, provider.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+
+    /// CHECK: provider's configured payout address
+    #[account(mut, address = provider.payout_address @ DvpnError::Unauthorized)]
+    pub payout: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireSession<'info> {
+    #[account(mut)]
+    pub caller: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(mut)]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateSessionLayout<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: legacy-layout Session; owner and discriminator checked by hand after realloc
+    #[account(mut)]
+    pub session: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimChunk<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        seeds = [OPERATOR_SEED, provider.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+
+    /// CHECK: provider's configured payout address
+    #[account(mut, address = provider.payout_address @ DvpnError::Unauthorized)]
+    pub payout: AccountInfo<'info>,
+
+    #[account(
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    /// CHECK: instructions sysvar, used to read the Ed25519 receipt verification
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimHashchain<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        seeds = [OPERATOR_SEED, provider.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+
+    /// CHECK: provider's configured payout address
+    #[account(mut, address = provider.payout_address @ DvpnError::Unauthorized)]
+    pub payout: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct RefundHashchain<'info> {
+    #[account(mut)]
+    pub session: Account<'info, Session>,
+
+    /// CHECK: refund destination recorded at open (user wallet or vault)
+    #[account(mut, address = session.refund_to @ DvpnError::Unauthorized)]
+    pub refund_to: AccountInfo<'info>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct RaiseDispute<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        seeds = [ARBITER_REGISTRY_SEED],
+        bump = arbiter_registry.bump
+    )]
+    pub arbiter_registry: Account<'info, ArbiterRegistry>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Dispute::MAX_SIZE,
+        seeds = [DISPUTE_SEED, session.key().as_ref()],
+        bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitEvidence<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        seeds = [DISPUTE_SEED, session.key().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawDispute<'info> {
+    #[account(mut, address = dispute.raised_by @ DvpnError::Unauthorized)]
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [DISPUTE_SEED, session.key().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    /// CHECK: panel PDA for this session; must not exist yet
+    #[account(seeds = [DISPUTE_PANEL_SEED, session.key().as_ref()], bump)]
+    pub dispute_panel: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleDispute<'info> {
+    #[account(address = session.user @ DvpnError::Unauthorized)]
+    pub user: Signer<'info>,
+
+    #[account(address = provider.authority @ DvpnError::Unauthorized)]
+    pub provider_authority: Signer<'info>,
+
+    #[account(
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+    #[account(
+        mut,
+        seeds = [DISPUTE_SEED, session.key().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    /// CHECK: party that raised the dispute, gets the bond back if they win
+    #[account(mut, address = dispute.raised_by @ DvpnError::Unauthorized)]
+    pub raiser: AccountInfo<'info>,
+
+    /// CHECK: refund destination recorded at open (user wallet or vault)
+    #[account(mut, address = session.refund_to @ DvpnError::Unauthorized)]
+    pub refund_to: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleDisputeSpl<'info> {
+    #[account(mut, address = session.user @ DvpnError::Unauthorized)]
+    pub user: Signer<'info>,
+
+    #[account(address = provider.authority @ DvpnError::Unauthorized)]
+    pub provider_authority: Signer<'info>,
+
+    #[account(
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+    #[account(
+        mut,
+        seeds = [DISPUTE_SEED, session.key().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    /// CHECK: party that raised the dispute, gets the bond back if they win
+    #[account(mut, address = dispute.raised_by @ DvpnError::Unauthorized)]
+    pub raiser: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = session,
+    )]
+    pub session_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = session.refund_to,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct AutoResolveDispute<'info> {
+    #[account(mut)]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        seeds = [DISPUTE_SEED, session.key().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    /// CHECK: party that raised the dispute, gets the bond back if they win
+    #[account(mut, address = dispute.raised_by @ DvpnError::Unauthorized)]
+    pub raiser: AccountInfo<'info>,
+
+    /// CHECK: panel PDA for this session; may not exist if none was seated
+    #[account(seeds = [DISPUTE_PANEL_SEED, session.key().as_ref()], bump)]
+    pub dispute_panel: UncheckedAccount<'info>,
+
+    /// CHECK: refund destination recorded at open (user wallet or vault)
+    #[account(mut, address = session.refund_to @ DvpnError::Unauthorized)]
+    pub user: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveDispute<'info> {
+    // Anyone may execute a decided ruling; pays the slash record rent
+    #[account(mut)]
+    pub resolver: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        seeds = [DISPUTE_PANEL_SEED, session.key().as_ref()],
+        bump = dispute_panel.bump
+    )]
+    pub dispute_panel: Account<'info, DisputePanel>,
+
+    #[account(
+        mut,
+        seeds = [DISPUTE_SEED, session.key().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    /// CHECK: party that raised the dispute, gets the bond back if they win
+    #[account(mut, address = dispute.raised_by @ DvpnError::Unauthorized)]
+    pub raiser: AccountInfo<'info>,
+
+    /// CHECK: provider's configured payout address, receives a forfeited user bond
+    #[account(mut, address = provider.payout_address @ DvpnError::Unauthorized)]
+    pub payout: AccountInfo<'info>,
+
+    /// CHECK: refund destination recorded at open (user wallet or vault)
+    #[account(mut, address = session.refund_to @ DvpnError::Unauthorized)]
+    pub user: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        mut,
+        seeds = [INSURANCE_FUND_SEED],
+        bump = insurance_fund.bump
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        init,
+        payer = resolver,
+        space = 8 + SlashRecord::MAX_SIZE,
+        seeds = [SLASH_RECORD_SEED, session.key().as_ref()],
+        bump
+    )]
+    pub slash_record: Account<'info, SlashRecord>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateReputation<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub provider: Account<'info, Provider>,
+
+    pub session: Account<'info, Session>,
+}
+
+// ============== ARBITRATION CONTEXTS ==============
+
+#[derive(Accounts)]
+pub struct InitializeArbiterRegistry<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump,
+        constraint = config.admin == authority.key() @ DvpnError::Unauthorized
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ArbiterRegistry::MAX_SIZE,
+        seeds = [ARBITER_REGISTRY_SEED],
+        bump
+    )]
+    pub arbiter_registry: Account<'info, ArbiterRegistry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateArbiterRegistry<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [ARBITER_REGISTRY_SEED],
+        bump = arbiter_registry.bump,
+        has_one = authority @ DvpnError::Unauthorized
+    )]
+    pub arbiter_registry: Account<'info, ArbiterRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct AssignArbiters<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [ARBITER_REGISTRY_SEED],
+        bump = arbiter_registry.bump,
+        has_one = authority @ DvpnError::Unauthorized
+    )]
+    pub arbiter_registry: Account<'info, ArbiterRegistry>,
+
+    pub session: Account<'info, Session>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + DisputePanel::MAX_SIZE,
+        seeds = [DISPUTE_PANEL_SEED, session.key().as_ref()],
+        bump
+    )]
+    pub dispute_panel: Account<'info, DisputePanel>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CastDisputeVote<'info> {
+    pub arbiter: Signer<'info>,
+
+    #[account(
+        seeds = [ARBITER_REGISTRY_SEED],
+        bump = arbiter_registry.bump
+    )]
+    pub arbiter_registry: Account<'info, ArbiterRegistry>,
+
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        seeds = [DISPUTE_PANEL_SEED, session.key().as_ref()],
+        bump = dispute_panel.bump
+    )]
+    pub dispute_panel: Account<'info, DisputePanel>,
+}
+
+// ============== PROVIDER AUTHORITY TRANSFER CONTEXTS ==============
+
+#[derive(Accounts)]
+pub struct ProposeAuthorityTransfer<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthorityTransfer<'info> {
+    pub new_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateProvider<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: legacy-layout Provider PDA; deserialized by hand after realloc
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, authority.key().as_ref()],
+        bump
+    )]
+    pub provider: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// ============== OPERATOR KEY CONTEXTS ==============
+
+#[derive(Accounts)]
+#[instruction(operator_key: Pubkey)]
+pub struct AddOperator<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Operator::MAX_SIZE,
+        seeds = [OPERATOR_SEED, provider.key().as_ref(), operator_key.as_ref()],
+        bump
+    )]
+    pub operator: Account<'info, Operator>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateOperator<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        mut,
+        seeds = [OPERATOR_SEED, provider.key().as_ref(), operator.key.as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Account<'info, Operator>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveOperator<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [OPERATOR_SEED, provider.key().as_ref(), operator.key.as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Account<'info, Operator>,
+}
+
+#[derive(Accounts)]
+pub struct SetPayoutAddress<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+}
+
+// ============== PROVIDER PROFILE CONTEXTS ==============
+
+#[derive(Accounts)]
+pub struct CreateProviderProfile<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + ProviderProfile::MAX_SIZE,
+        seeds = [PROVIDER_PROFILE_SEED, provider.key().as_ref()],
+        bump
+    )]
+    pub profile: Account<'info, ProviderProfile>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProviderProfile<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_PROFILE_SEED, provider.key().as_ref()],
+        bump = profile.bump
+    )]
+    pub profile: Account<'info, ProviderProfile>,
+}
+
+// ============== DELEGATED STAKING CONTEXTS ==============
+
+#[derive(Accounts)]
+pub struct SetCommission<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+}
+
+#[derive(Accounts)]
+pub struct DelegateStake<'info> {
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        init_if_needed,
+        payer = delegator,
+        space = 8 + Delegation::MAX_SIZE,
+        seeds = [DELEGATION_SEED, provider.key().as_ref(), delegator.key().as_ref()],
+        bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateDelegation<'info> {
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        mut,
+        seeds = [DELEGATION_SEED, provider.key().as_ref(), delegator.key().as_ref()],
+        bump = delegation.bump
+    )]
+    pub delegation: Account<'info, Delegation>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+// ============== SPL SETTLEMENT CONTEXTS ==============
+
+#[derive(Accounts)]
+pub struct CloseSessionSpl<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(mut)]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = session,
+    )]
+    pub session_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = session.refund_to,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPayoutSpl<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        seeds = [OPERATOR_SEED, provider.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+
+    #[account(
+        mut,
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    // Omitted once a refund has emptied and closed the escrow
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = session,
+    )]
+    pub session_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = provider.payout_address,
+    )]
+    pub provider_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = treasury,
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    // Receives tokens sent to the escrow beyond the session balance
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = session.refund_to,
+    )]
+    pub user_token_account: Option<Account<'info, TokenAccount>>,
+
+    /// CHECK: session owner, receives the escrow token account rent
+    #[account(mut, address = session.user @ DvpnError::Unauthorized)]
+    pub user: AccountInfo<'info>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimChunkSpl<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        seeds = [OPERATOR_SEED, provider.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+
+    #[account(
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = session,
+    )]
+    pub session_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = provider.payout_address,
+    )]
+    pub provider_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = treasury,
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    /// CHECK: instructions sysvar, used to read the Ed25519 receipt verification
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveDisputeSpl<'info> {
+    // Anyone may execute a decided ruling; pays the slash record rent
+    #[account(mut)]
+    pub resolver: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        seeds = [DISPUTE_PANEL_SEED, session.key().as_ref()],
+        bump = dispute_panel.bump
+    )]
+    pub dispute_panel: Account<'info, DisputePanel>,
+
+    #[account(
+        mut,
+        seeds = [DISPUTE_SEED, session.key().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    /// CHECK: party that raised the dispute, gets the bond back if they win
+    #[account(mut, address = dispute.raised_by @ DvpnError::Unauthorized)]
+    pub raiser: AccountInfo<'info>,
+
+    /// CHECK: provider's configured payout address, receives a forfeited user bond
+    #[account(mut, address = provider.payout_address @ DvpnError::Unauthorized)]
+    pub payout: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = session,
+    )]
+    pub session_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = session.refund_to,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: session owner, receives the escrow token account rent
+    #[account(mut, address = session.user @ DvpnError::Unauthorized)]
+    pub user: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        mut,
+        seeds = [INSURANCE_FUND_SEED],
+        bump = insurance_fund.bump
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        init,
+        payer = resolver,
+        space = 8 + SlashRecord::MAX_SIZE,
+        seeds = [SLASH_RECORD_SEED, session.key().as_ref()],
+        bump
+    )]
+    pub slash_record: Account<'info, SlashRecord>,
+
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AutoResolveDisputeSpl<'info> {
+    #[account(mut)]
+    pub session: Account<'info, Session>,
+
+    #[account(
+        mut,
+        seeds = [DISPUTE_SEED, session.key().as_ref()],
+        bump = dispute.bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    /// CHECK: party that raised the dispute, gets the bond back if they win
+    #[account(mut, address = dispute.raised_by @ DvpnError::Unauthorized)]
+    pub raiser: AccountInfo<'info>,
+
+    /// CHECK: panel PDA for this session; may not exist if none was seated
+    #[account(seeds = [DISPUTE_PANEL_SEED, session.key().as_ref()], bump)]
+    pub dispute_panel: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = session,
+    )]
+    pub session_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = session.payment_token,
+        token::authority = session.refund_to,
+    )]
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    /// CHECK: session owner, receives the escrow token account rent
+    #[account(mut, address = session.user @ DvpnError::Unauthorized)]
+    pub user: AccountInfo<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// ============== PROGRAM CONFIG CONTEXTS ==============
+
+#[derive(Accounts)]
+pub struct InitializeProgramConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump,
+        constraint = treasury.authority == admin.key() @ DvpnError::Unauthorized
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProgramConfig::MAX_SIZE,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProgramConfig<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ DvpnError::Unauthorized
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+// ============== SUBSCRIPTION ACCOUNT CONTEXTS ==============
+
+#[derive(Accounts)]
+pub struct InitializeTreasury<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Treasury::MAX_SIZE,
+        seeds = [TREASURY_SEED],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeInsuranceFund<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + InsuranceFund::MAX_SIZE,
+        seeds = [INSURANCE_FUND_SEED],
+        bump
+    )]
+    pub insurance_fund: Account<'info, InsuranceFund>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(plan: SubscriptionPlan)]
+pub struct CreateSubscription<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Subscription::MAX_SIZE,
+        seeds = [SUBSCRIPTION_SEED, user.key().as_ref()],
+        bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(plan: SubscriptionPlan)]
+pub struct RenewSubscription<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [SUBSCRIPTION_SEED, user.key().as_ref()],
+        bump = subscription.bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelSubscription<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [SUBSCRIPTION_SEED, user.key().as_ref()],
+        bump = subscription.bump
+    )]
+    pub subscription: Account<'info, Subscription>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    /// CHECK: refund destination recorded at create (user wallet or vault)
+    #[account(mut, address = subscription.refund_to @ DvpnError::Unauthorized)]
+    pub refund_to: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction()]
+pub struct ClaimSubscription<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
+    pub provider: Account<'info, Provider>,
+
+    #[account(
+        seeds = [OPERATOR_SEED, provider.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+
+    /// CHECK: provider's configured payout address
+    #[account(mut, address = provider.payout_address @ DvpnError::Unauthorized)]
+    pub payout: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
+
+    #[account(mut)]
+    pub subscription: Account<'info, Subscription>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
 
-    // Add subscription revenue to the current epoch's pool
-    pub fn add_to_earnings_pool(
-        ctx: Context<AddToEarningsPool>,
-        amount: u64,
-    ) -> Result<()> {
-        let pool = &mut ctx.accounts.earnings_pool;
-        
-        require!(!pool.is_finalized, DvpnError::PoolAlreadyFinalized);
-        
-        pool.total_subscription_revenue = pool.total_subscription_revenue.saturating_add(amount);
-        
-        Ok(())
-    }
+#[derive(Accounts)]
+pub struct CheckSubscription<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [SUBSCRIPTION_SEED, user.key().as_ref()],
+        bump = subscription.bump
+    )]
+    pub subscription: Account<'info, Subscription>,
 }
 
 #[derive(Accounts)]
-pub struct RegisterProvider<'info> {
+pub struct WithdrawTreasury<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+}
+
+// ============== MULTISIG CONTEXTS ==============
+
+#[derive(Accounts)]
+pub struct InitializeMultisig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump,
+        has_one = authority @ DvpnError::Unauthorized
+    )]
+    pub treasury: Account<'info, Treasury>,
+
     #[account(
         init,
         payer = authority,
-        space = 8 + Provider::MAX_SIZE,
-        seeds = [PROVIDER_SEED, authority.key().as_ref()],
+        space = 8 + Multisig::MAX_SIZE,
+        seeds = [MULTISIG_SEED],
         bump
     )]
-    pub provider: Account<'info, Provider>,
+    pub multisig: Account<'info, Multisig>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct StakeProvider<'info> {
+pub struct CreateMultisigProposal<'info> {
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub owner: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [PROVIDER_SEED, authority.key().as_ref()],
-        bump = provider.bump
+        seeds = [MULTISIG_SEED],
+        bump = multisig.bump
     )]
-    pub provider: Account<'info, Provider>,
+    pub multisig: Account<'info, Multisig>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + MultisigProposal::MAX_SIZE,
+        seeds = [MULTISIG_PROPOSAL_SEED, multisig.proposal_count.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub proposal: Account<'info, MultisigProposal>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct UnstakeProvider<'info> {
+pub struct ApproveMultisigProposal<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [MULTISIG_SEED],
+        bump = multisig.bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+
+    #[account(
+        mut,
+        seeds = [MULTISIG_PROPOSAL_SEED, proposal.proposal_id.to_le_bytes().as_ref()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, MultisigProposal>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteMultisigProposal<'info> {
+    #[account(
+        mut,
+        seeds = [MULTISIG_SEED],
+        bump = multisig.bump
+    )]
+    pub multisig: Account<'info, Multisig>,
+
+    #[account(
+        mut,
+        seeds = [MULTISIG_PROPOSAL_SEED, proposal.proposal_id.to_le_bytes().as_ref()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, MultisigProposal>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump
+    )]
+    pub treasury: Option<Account<'info, Treasury>>,
+
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Option<Account<'info, ProgramConfig>>,
+
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub earnings_pool: Option<Account<'info, EarningsPool>>,
 
     #[account(
         mut,
-        seeds = [PROVIDER_SEED, authority.key().as_ref()],
-        bump = provider.bump
+        seeds = [GOVERNANCE_SEED],
+        bump = governance.bump
     )]
-    pub provider: Account<'info, Provider>,
+    pub governance: Option<Account<'info, Governance>>,
 
-    pub system_program: Program<'info, System>,
+    /// CHECK: withdrawal destination, checked against the proposal
+    #[account(mut)]
+    pub recipient: Option<AccountInfo<'info>>,
 }
 
+// ============== PROVIDER GOVERNANCE CONTEXTS ==============
+
 #[derive(Accounts)]
-#[instruction(node_id: u64)]
-pub struct RegisterNode<'info> {
+pub struct InitializeGovernance<'info> {
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub admin: Signer<'info>,
 
     #[account(
-        mut,
-        seeds = [PROVIDER_SEED, authority.key().as_ref()],
-        bump = provider.bump
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ DvpnError::Unauthorized
     )]
-    pub provider: Account<'info, Provider>,
+    pub config: Account<'info, ProgramConfig>,
 
     #[account(
         init,
-        payer = authority,
-        space = 8 + Node::MAX_SIZE,
-        seeds = [
-            NODE_SEED,
-            provider.key().as_ref(),
-            &node_id.to_le_bytes()
-        ],
+        payer = admin,
+        space = 8 + Governance::MAX_SIZE,
+        seeds = [GOVERNANCE_SEED],
         bump
     )]
-    pub node: Account<'info, Node>,
+    pub governance: Account<'info, Governance>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(session_id: u64)]
-pub struct OpenSession<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+pub struct UpdateGovernance<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump,
+        has_one = admin @ DvpnError::Unauthorized
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        seeds = [GOVERNANCE_SEED],
+        bump = governance.bump
+    )]
+    pub governance: Account<'info, Governance>,
+}
 
+#[derive(Accounts)]
+pub struct CreateGovernanceProposal<'info> {
     #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
     pub provider: Account<'info, Provider>,
 
-    #[account(mut)]
-    pub node: Account<'info, Node>,
+    #[account(
+        mut,
+        seeds = [GOVERNANCE_SEED],
+        bump = governance.bump
+    )]
+    pub governance: Account<'info, Governance>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
 
     #[account(
         init,
-        payer = user,
-        space = 8 + Session::MAX_SIZE,
-        seeds = [
-            SESSION_SEED,
-            user.key().as_ref(),
-            node.key().as_ref(),
-            &session_id.to_le_bytes()
-        ],
+        payer = authority,
+        space = 8 + GovernanceProposal::MAX_SIZE,
+        seeds = [GOVERNANCE_PROPOSAL_SEED, governance.proposal_count.to_le_bytes().as_ref()],
         bump
     )]
-    pub session: Account<'info, Session>,
+    pub proposal: Account<'info, GovernanceProposal>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(session_id: u64)]
-pub struct OpenSessionSpl<'info> {
+pub struct CastGovernanceVote<'info> {
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub authority: Signer<'info>,
 
-    #[account(mut)]
+    #[account(
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
+        bump = provider.bump
+    )]
     pub provider: Account<'info, Provider>,
 
-    #[account(mut)]
-    pub node: Account<'info, Node>,
+    #[account(
+        mut,
+        seeds = [GOVERNANCE_PROPOSAL_SEED, proposal.proposal_id.to_le_bytes().as_ref()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, GovernanceProposal>,
 
     #[account(
         init,
-        payer = user,
-        space = 8 + Session::MAX_SIZE,
-        seeds = [
-            SESSION_SEED,
-            user.key().as_ref(),
-            node.key().as_ref(),
-            &session_id.to_le_bytes()
-        ],
+        payer = authority,
+        space = 8 + GovernanceVote::MAX_SIZE,
+        seeds = [GOVERNANCE_VOTE_SEED, proposal.key().as_ref(), provider.key().as_ref()],
         bump
     )]
-    pub session: Account<'info, Session>,
+    pub vote: Account<'info, GovernanceVote>,
 
-    pub mint: Account<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
 
+#[derive(Accounts)]
+pub struct ExecuteGovernanceProposal<'info> {
+    #[account(
+        seeds = [GOVERNANCE_SEED],
+        bump = governance.bump
+    )]
+    pub governance: Account<'info, Governance>,
+
+    #[account(
+        mut,
+        seeds = [GOVERNANCE_PROPOSAL_SEED, proposal.proposal_id.to_le_bytes().as_ref()],
+        bump = proposal.bump
+    )]
+    pub proposal: Account<'info, GovernanceProposal>,
+
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+// ============== USER VAULT CONTEXTS ==============
+
+#[derive(Accounts)]
+pub struct InitializeUserVault<'info> {
     #[account(mut)]
-    pub user_token_account: Account<'info, TokenAccount>,
+    pub user: Signer<'info>,
 
     #[account(
         init,
         payer = user,
-        token::mint = mint,
-        token::authority = session,
+        space = 8 + UserVault::MAX_SIZE,
+        seeds = [USER_VAULT_SEED, user.key().as_ref()],
+        bump
     )]
-    pub session_token_account: Account<'info, TokenAccount>,
+    pub user_vault: Account<'info, UserVault>,
 
-    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct CloseSession<'info> {
+pub struct UpdateUserVault<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
 
-    #[account(mut)]
-    pub session: Account<'info, Session>,
+    #[account(
+        mut,
+        seeds = [USER_VAULT_SEED, user.key().as_ref()],
+        bump = user_vault.bump
+    )]
+    pub user_vault: Account<'info, UserVault>,
+}
 
+#[derive(Accounts)]
+pub struct DepositVault<'info> {
     #[account(mut)]
-    pub node: Account<'info, Node>,
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [USER_VAULT_SEED, user.key().as_ref()],
+        bump = user_vault.bump
+    )]
+    pub user_vault: Account<'info, UserVault>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimPayout<'info> {
+pub struct DepositVaultSpl<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [USER_VAULT_SEED, user.key().as_ref()],
+        bump = user_vault.bump
+    )]
+    pub user_vault: Account<'info, UserVault>,
+
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub user_token_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        seeds = [PROVIDER_SEED, authority.key().as_ref()],
-        bump = provider.bump
+        token::mint = user_token_account.mint,
+        token::authority = user_vault,
     )]
-    pub provider: Account<'info, Provider>,
+    pub vault_token_account: Account<'info, TokenAccount>,
 
-    #[account(mut)]
-    pub node: Account<'info, Node>,
+    pub token_program: Program<'info, Token>,
+}
 
-    #[account(mut)]
-    pub session: Account<'info, Session>,
+#[derive(Accounts)]
+pub struct WithdrawVaultSpl<'info> {
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [USER_VAULT_SEED, user.key().as_ref()],
+        bump = user_vault.bump
+    )]
+    pub user_vault: Account<'info, UserVault>,
+
+    #[account(
+        mut,
+        token::authority = user_vault,
+    )]
+    pub vault_token_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        seeds = [TREASURY_SEED],
-        bump = treasury.bump
+        token::mint = vault_token_account.mint,
     )]
-    pub treasury: Account<'info, Treasury>,
+    pub user_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimChunk<'info> {
+#[instruction(session_id: u64)]
+pub struct OpenSessionFromVault<'info> {
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub user: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [PROVIDER_SEED, authority.key().as_ref()],
-        bump = provider.bump
+        seeds = [USER_VAULT_SEED, user.key().as_ref()],
+        bump = user_vault.bump
     )]
-    pub provider: Account<'info, Provider>,
+    pub user_vault: Account<'info, UserVault>,
 
     #[account(mut)]
-    pub session: Account<'info, Session>,
+    pub provider: Account<'info, Provider>,
 
     #[account(
         mut,
-        seeds = [TREASURY_SEED],
-        bump = treasury.bump
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
     )]
-    pub treasury: Account<'info, Treasury>,
-}
-
-#[derive(Accounts)]
-pub struct RaiseDispute<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    pub node: Account<'info, Node>,
 
     #[account(
-        seeds = [PROVIDER_SEED, provider.authority.as_ref()],
-        bump = provider.bump
+        init,
+        payer = user,
+        space = 8 + Session::MAX_SIZE,
+        seeds = [
+            SESSION_SEED,
+            user.key().as_ref(),
+            node.key().as_ref(),
+            &session_id.to_le_bytes()
+        ],
+        bump
     )]
-    pub provider: Account<'info, Provider>,
-
-    #[account(mut)]
     pub session: Account<'info, Session>,
-}
-
-#[derive(Accounts)]
-pub struct ResolveDispute<'info> {
-    #[account(mut)]
-    pub resolver: Signer<'info>, // In MVP can be provider, later should be governance
-
-    #[account(mut)]
-    pub provider: Account<'info, Provider>,
 
-    #[account(mut)]
-    pub session: Account<'info, Session>,
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
 
-    /// CHECK: user account for refund
-    #[account(mut)]
-    pub user: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateReputation<'info> {
+#[instruction(session_id: u64)]
+pub struct OpenSessionSplFromVault<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
 
+    #[account(
+        mut,
+        seeds = [USER_VAULT_SEED, user.key().as_ref()],
+        bump = user_vault.bump
+    )]
+    pub user_vault: Account<'info, UserVault>,
+
     #[account(mut)]
     pub provider: Account<'info, Provider>,
 
-    pub session: Account<'info, Session>,
-}
-
-// ============== SUBSCRIPTION ACCOUNT CONTEXTS ==============
-
-#[derive(Accounts)]
-pub struct InitializeTreasury<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+    )]
+    pub node: Account<'info, Node>,
 
     #[account(
         init,
-        payer = authority,
-        space = 8 + Treasury::MAX_SIZE,
-        seeds = [TREASURY_SEED],
+        payer = user,
+        space = 8 + Session::MAX_SIZE,
+        seeds = [
+            SESSION_SEED,
+            user.key().as_ref(),
+            node.key().as_ref(),
+            &session_id.to_le_bytes()
+        ],
         bump
     )]
-    pub treasury: Account<'info, Treasury>,
+    pub session: Account<'info, Session>,
 
-    pub system_program: Program<'info, System>,
-}
+    pub mint: Account<'info, Mint>,
 
-#[derive(Accounts)]
-#[instruction(plan: SubscriptionPlan)]
-pub struct CreateSubscription<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+    #[account(
+        seeds = [NODE_PRICING_SEED, node.key().as_ref()],
+        bump = node_pricing.bump
+    )]
+    pub node_pricing: Account<'info, NodePricing>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = user_vault,
+    )]
+    pub vault_token_account: Account<'info, TokenAccount>,
 
     #[account(
         init,
         payer = user,
-        space = 8 + Subscription::MAX_SIZE,
-        seeds = [SUBSCRIPTION_SEED, user.key().as_ref()],
-        bump
+        token::mint = mint,
+        token::authority = session,
     )]
-    pub subscription: Account<'info, Subscription>,
+    pub session_token_account: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
 
+    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(plan: SubscriptionPlan)]
-pub struct RenewSubscription<'info> {
+pub struct ExtendSessionFromVault<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [SUBSCRIPTION_SEED, user.key().as_ref()],
-        bump = subscription.bump
+        seeds = [USER_VAULT_SEED, user.key().as_ref()],
+        bump = user_vault.bump
     )]
-    pub subscription: Account<'info, Subscription>,
+    pub user_vault: Account<'info, UserVault>,
 
-    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        constraint = session.node == node.key() @ DvpnError::Unauthorized,
+        constraint = session.refund_to == user_vault.key() @ DvpnError::Unauthorized
+    )]
+    pub session: Account<'info, Session>,
+
+    pub node: Account<'info, Node>,
 }
 
 #[derive(Accounts)]
-pub struct CancelSubscription<'info> {
+pub struct ExtendSessionSplFromVault<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [SUBSCRIPTION_SEED, user.key().as_ref()],
-        bump = subscription.bump
+        seeds = [USER_VAULT_SEED, user.key().as_ref()],
+        bump = user_vault.bump
     )]
-    pub subscription: Account<'info, Subscription>,
+    pub user_vault: Account<'info, UserVault>,
 
     #[account(
         mut,
-        seeds = [TREASURY_SEED],
-        bump = treasury.bump
+        constraint = session.node == node.key() @ DvpnError::Unauthorized,
+        constraint = session.refund_to == user_vault.key() @ DvpnError::Unauthorized
     )]
-    pub treasury: Account<'info, Treasury>,
-}
+    pub session: Account<'info, Session>,
 
-#[derive(Accounts)]
-#[instruction()]
-pub struct ClaimSubscription<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    pub node: Account<'info, Node>,
 
     #[account(
         mut,
-        seeds = [PROVIDER_SEED, authority.key().as_ref()],
-        bump = provider.bump
+        token::mint = session.payment_token,
+        token::authority = user_vault,
     )]
-    pub provider: Account<'info, Provider>,
+    pub vault_token_account: Account<'info, TokenAccount>,
 
     #[account(
         mut,
-        constraint = node.provider == provider.key() @ DvpnError::Unauthorized
+        token::mint = session.payment_token,
+        token::authority = session,
     )]
-    pub node: Account<'info, Node>,
-
-    #[account(mut)]
-    pub subscription: Account<'info, Subscription>,
+    pub session_token_account: Account<'info, TokenAccount>,
 
     #[account(
-        mut,
-        seeds = [TREASURY_SEED],
-        bump = treasury.bump
+        seeds = [NODE_PRICING_SEED, node.key().as_ref()],
+        bump = node_pricing.bump
     )]
-    pub treasury: Account<'info, Treasury>,
+    pub node_pricing: Account<'info, NodePricing>,
+
+    pub token_program: Program<'info, Token>,
 }
 
 #[derive(Accounts)]
-pub struct CheckSubscription<'info> {
+#[instruction(plan: SubscriptionPlan)]
+pub struct CreateSubscriptionFromVault<'info> {
+    #[account(mut)]
     pub user: Signer<'info>,
 
     #[account(
+        mut,
+        seeds = [USER_VAULT_SEED, user.key().as_ref()],
+        bump = user_vault.bump
+    )]
+    pub user_vault: Account<'info, UserVault>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + Subscription::MAX_SIZE,
         seeds = [SUBSCRIPTION_SEED, user.key().as_ref()],
-        bump = subscription.bump
+        bump
     )]
     pub subscription: Account<'info, Subscription>,
-}
-
-#[derive(Accounts)]
-pub struct WithdrawTreasury<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
 
     #[account(
-        mut,
-        seeds = [TREASURY_SEED],
-        bump = treasury.bump
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
     )]
-    pub treasury: Account<'info, Treasury>,
+    pub config: Account<'info, ProgramConfig>,
+
+    pub system_program: Program<'info, System>,
 }
 
 // ============== FAIR EARNINGS DISTRIBUTION CONTEXTS ==============
@@ -1210,6 +5878,24 @@ pub struct InitializeEarningsPool<'info> {
     )]
     pub earnings_pool: Account<'info, EarningsPool>,
 
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+
+    #[account(
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        seeds = [MULTISIG_SEED],
+        bump = multisig.bump
+    )]
+    pub multisig: Option<Account<'info, Multisig>>,
+
     pub system_program: Program<'info, System>,
 }
 
@@ -1238,6 +5924,18 @@ pub struct RecordNodeUsage<'info> {
     )]
     pub usage_record: Account<'info, UsageRecord>,
 
+    #[account(
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        seeds = [MULTISIG_SEED],
+        bump = multisig.bump
+    )]
+    pub multisig: Option<Account<'info, Multisig>>,
+
     pub system_program: Program<'info, System>,
 }
 
@@ -1258,11 +5956,21 @@ pub struct ClaimProportionalEarnings<'info> {
 
     #[account(
         mut,
-        seeds = [PROVIDER_SEED, authority.key().as_ref()],
+        seeds = [PROVIDER_SEED, provider.seed_authority.as_ref()],
         bump = provider.bump
     )]
     pub provider: Account<'info, Provider>,
 
+    #[account(
+        seeds = [OPERATOR_SEED, provider.key().as_ref(), authority.key().as_ref()],
+        bump = operator.bump
+    )]
+    pub operator: Option<Account<'info, Operator>>,
+
+    /// CHECK: provider's configured payout address
+    #[account(mut, address = provider.payout_address @ DvpnError::Unauthorized)]
+    pub payout: AccountInfo<'info>,
+
     #[account(
         mut,
         constraint = node.provider == provider.key() @ DvpnError::Unauthorized
@@ -1289,6 +5997,12 @@ pub struct ClaimProportionalEarnings<'info> {
         bump = treasury.bump
     )]
     pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED],
+        bump = config.bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
 }
 
 #[derive(Accounts)]
@@ -1312,23 +6026,180 @@ pub struct AddToEarningsPool<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
-    #[account(mut)]
-    pub earnings_pool: Account<'info, EarningsPool>,
-}
+    #[account(mut)]
+    pub earnings_pool: Account<'info, EarningsPool>,
+
+    #[account(
+        seeds = [TREASURY_SEED],
+        bump = treasury.bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+
+    #[account(
+        seeds = [MULTISIG_SEED],
+        bump = multisig.bump
+    )]
+    pub multisig: Option<Account<'info, Multisig>>,
+}
+
+#[account]
+pub struct Provider {
+    pub authority: Pubkey,
+    pub node_count: u64,
+    pub stake_lamports: u64,
+    pub reputation_score: u16,
+    pub total_uptime_seconds: u64,
+    pub total_sessions: u64,
+    pub total_earnings: u64,
+    pub bump: u8,
+    // Unbonding queue (request_unstake -> withdraw_unstaked)
+    pub unbonding_lamports: u64,
+    pub unbonding_unlock_ts: i64,
+    pub total_capacity: u64,              // Sum of max_capacity over registered nodes
+    // Delegated stake pools (share-based so slashing applies pro rata)
+    pub delegated_lamports: u64,
+    pub delegated_shares: u64,
+    pub delegated_unbonding_lamports: u64,
+    pub delegated_unbonding_shares: u64,
+    pub commission_bps: u16,              // Provider's cut of delegator rewards
+    pub acc_reward_per_share: u128,       // Scaled by REWARD_PRECISION
+    pub payout_address: Pubkey,           // Receives claims, including operator-signed ones
+    pub seed_authority: Pubkey,           // Authority at registration; fixes the PDA address
+    pub pending_authority: Pubkey,        // Nominated by propose_authority_transfer
+    pub stake_checkpoints: [StakeCheckpoint; STAKE_CHECKPOINTS], // Recent stake increases, for governance snapshots
+    pub operator_count: u32,              // Open Operator PDAs; must be zero to close
+}
+impl Provider {
+    pub const MAX_SIZE: usize = 32 + 8 + 8 + 2 + 8 + 8 + 8 + 1 +
+        8 + 8 + // unbonding_lamports, unbonding_unlock_ts
+        8 +     // total_capacity
+        8 + 8 + 8 + 8 + 2 + 16 + // delegation pools, commission, reward accumulator
+        32 +                     // payout_address
+        32 + 32 +                // seed_authority, pending_authority
+        STAKE_CHECKPOINTS * StakeCheckpoint::SIZE + // stake_checkpoints
+        4;                       // operator_count
+
+    // Call before stake grows. Proposals stay open at most
+    // MAX_VOTING_PERIOD_SECONDS, so older checkpoints can't predate an open
+    // proposal and are reused. With none stale, the two oldest merge into one
+    // that keeps the later time and the lower stake.
+    pub fn record_stake_increase(&mut self, now: i64) {
+        let stale_before = now.saturating_sub(MAX_VOTING_PERIOD_SECONDS);
+        let checkpoints = &mut self.stake_checkpoints;
+        let slot = match checkpoints.iter().position(|c| c.ts <= stale_before) {
+            Some(i) => i,
+            None => {
+                let oldest = (0..STAKE_CHECKPOINTS).min_by_key(|&i| checkpoints[i].ts).unwrap_or(0);
+                let next = (0..STAKE_CHECKPOINTS)
+                    .filter(|&i| i != oldest)
+                    .min_by_key(|&i| checkpoints[i].ts)
+                    .unwrap_or(0);
+                checkpoints[next].stake_before = checkpoints[next].stake_before.min(checkpoints[oldest].stake_before);
+                oldest
+            }
+        };
+        checkpoints[slot] = StakeCheckpoint { ts: now, stake_before: self.stake_lamports };
+    }
+
+    // Governance weight for a proposal created at `snapshot_ts`: the stake held
+    // before the first increase since then (a lower bound on the stake held at
+    // the snapshot), capped at what is still staked now
+    pub fn governance_weight(&self, snapshot_ts: i64) -> u64 {
+        let held_then = self
+            .stake_checkpoints
+            .iter()
+            .filter(|c| c.ts >= snapshot_ts)
+            .min_by_key(|c| (c.ts, c.stake_before))
+            .map_or(self.stake_lamports, |c| c.stake_before);
+        held_then.min(self.stake_lamports)
+    }
+
+    // Own stake plus actively delegated stake
+    pub fn bonded_lamports(&self) -> u64 {
+        self.stake_lamports.saturating_add(self.delegated_lamports)
+    }
+
+    // Minimum own stake for the provider's current footprint; none without nodes.
+    // Delegated stake doesn't count, so slashing always reaches the operator.
+    pub fn required_stake(&self, config: &ProgramConfig) -> u64 {
+        if self.node_count == 0 {
+            return 0;
+        }
+        config
+            .min_stake_base_lamports
+            .saturating_add(self.node_count.saturating_mul(config.min_stake_per_node_lamports))
+            .saturating_add(self.total_capacity.saturating_mul(config.min_stake_per_capacity_lamports))
+    }
+
+    pub fn meets_minimum_stake(&self, config: &ProgramConfig) -> bool {
+        self.stake_lamports >= self.required_stake(config)
+    }
+
+    pub fn reward_debt_for(&self, shares: u64) -> u128 {
+        (shares as u128).saturating_mul(self.acc_reward_per_share) / REWARD_PRECISION
+    }
+
+    // Carve the delegators' portion out of a provider payout and credit it to the
+    // reward accumulator. Returns the lamports that must stay on the provider PDA.
+    pub fn accrue_delegator_rewards(&mut self, provider_share: u64) -> Result<u64> {
+        let bonded = self.bonded_lamports();
+        if self.delegated_shares == 0 || bonded == 0 {
+            return Ok(0);
+        }
+        let delegated_portion = (provider_share as u128)
+            .checked_mul(self.delegated_lamports as u128)
+            .ok_or(DvpnError::MathOverflow)?
+            / bonded as u128;
+        let commission = delegated_portion * self.commission_bps as u128 / BPS_DENOMINATOR as u128;
+        let delegator_share = delegated_portion - commission;
+
+        self.acc_reward_per_share = self.acc_reward_per_share
+            .checked_add(delegator_share * REWARD_PRECISION / self.delegated_shares as u128)
+            .ok_or(DvpnError::MathOverflow)?;
+        Ok(delegator_share as u64)
+    }
+
+    // Own and delegated stake, including anything still unbonding
+    pub fn slashable_lamports(&self) -> u64 {
+        self.stake_lamports
+            .saturating_add(self.unbonding_lamports)
+            .saturating_add(self.delegated_lamports)
+            .saturating_add(self.delegated_unbonding_lamports)
+    }
+
+    // Slash own and delegated stake pro rata, including unbonding amounts
+    pub fn slash(&mut self, amount: u64) {
+        let total = self.slashable_lamports();
+        if total == 0 {
+            return;
+        }
+        let amount = amount.min(total);
+        let mut remaining = amount;
+
+        for bucket in [
+            &mut self.stake_lamports,
+            &mut self.unbonding_lamports,
+            &mut self.delegated_lamports,
+            &mut self.delegated_unbonding_lamports,
+        ] {
+            let cut = ((amount as u128 * *bucket as u128) / total as u128) as u64;
+            let cut = cut.min(remaining);
+            *bucket -= cut;
+            remaining -= cut;
+        }
 
-#[account]
-pub struct Provider {
-    pub authority: Pubkey,
-    pub node_count: u64,
-    pub stake_lamports: u64,
-    pub reputation_score: u16,
-    pub total_uptime_seconds: u64,
-    pub total_sessions: u64,
-    pub total_earnings: u64,
-    pub bump: u8,
-}
-impl Provider {
-    pub const MAX_SIZE: usize = 32 + 8 + 8 + 2 + 8 + 8 + 8 + 1;
+        // Rounding dust comes out of whichever buckets still hold lamports
+        for bucket in [
+            &mut self.stake_lamports,
+            &mut self.unbonding_lamports,
+            &mut self.delegated_lamports,
+            &mut self.delegated_unbonding_lamports,
+        ] {
+            let cut = remaining.min(*bucket);
+            *bucket -= cut;
+            remaining -= cut;
+        }
+    }
 }
 
 #[account]
@@ -1351,18 +6222,66 @@ pub struct Node {
     pub rating_sum: u64,                  // Sum of all ratings (for average)
     pub rating_count: u32,                // Number of ratings
     pub bump: u8,
+    // Liveness
+    pub last_heartbeat_ts: i64,
+    pub last_heartbeat_slot: u64,
+    pub unsettled_sessions: u32,          // Opened but not yet claimed (includes disputed)
+    // Scheduled WireGuard key rotation (activation_ts == 0 means none pending)
+    pub next_wg_server_pubkey: [u8; 32],
+    pub next_wg_key_activation_ts: i64,
 }
 impl Node {
     // String storage: 4 bytes len + max bytes
     pub const MAX_SIZE: usize =
         32 + 8 +
-        (4 + 80) +
-        (4 + 12) +
+        (4 + MAX_ENDPOINT_LEN) +
+        (4 + MAX_REGION_LEN) +
         8 +
         32 +
         4 + 4 + 8 + 8 + 1 +
         4 + 2 + 8 + 8 + 4 + // NEW: bandwidth, quality, bytes, rating_sum, rating_count
-        1;
+        1 +
+        8 + 8 +             // last_heartbeat_ts, last_heartbeat_slot
+        4 +                 // unsettled_sessions
+        32 + 8;             // next_wg_server_pubkey, next_wg_key_activation_ts
+
+    // Account body size of earlier layouts, for migrate_node
+    pub const LEGACY_LAYOUT_SIZE: usize = Self::MAX_SIZE - (8 + 8) - 4 - (32 + 8);
+    pub const HEARTBEAT_LAYOUT_SIZE: usize = Self::LEGACY_LAYOUT_SIZE + 8 + 8;
+    pub const UNSETTLED_LAYOUT_SIZE: usize = Self::HEARTBEAT_LAYOUT_SIZE + 4;
+
+    // Make a scheduled key current once its activation time has passed
+    pub fn promote_wg_key(&mut self, now: i64) {
+        if self.next_wg_key_activation_ts != 0 && now >= self.next_wg_key_activation_ts {
+            self.wg_server_pubkey = self.next_wg_server_pubkey;
+            self.next_wg_server_pubkey = [0u8; 32];
+            self.next_wg_key_activation_ts = 0;
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct MintPrice {
+    pub mint: Pubkey,
+    pub price_per_minute: u64,            // In the mint's base units
+}
+
+// Per-node list of accepted SPL mints and their prices
+#[account]
+pub struct NodePricing {
+    pub node: Pubkey,
+    pub accepted_mints: Vec<MintPrice>,   // <= MAX_ACCEPTED_MINTS
+    pub bump: u8,
+}
+impl NodePricing {
+    pub const MAX_SIZE: usize = 32 + (4 + MAX_ACCEPTED_MINTS * (32 + 8)) + 1;
+
+    pub fn price_for(&self, mint: &Pubkey) -> Option<u64> {
+        self.accepted_mints
+            .iter()
+            .find(|m| m.mint == *mint)
+            .map(|m| m.price_per_minute)
+    }
 }
 
 #[account]
@@ -1379,10 +6298,137 @@ pub struct Session {
     pub payment_token: Pubkey, // Pubkey::default() for SOL, or SPL mint address
     pub state: SessionState,
     pub bump: u8,
+    // Hash-chain mode (price_per_link_lamports == 0 means disabled)
+    pub hashchain_tip: [u8; 32],          // Last redeemed link, starts at commitment Hn
+    pub hashchain_length: u32,            // Total links N in the chain
+    pub hashchain_redeemed: u32,          // Links redeemed so far
+    pub price_per_link_lamports: u64,
+    // User-signed usage receipts (claim_chunk)
+    pub receipt_nonce: u64,               // Nonce of the last accepted receipt
+    pub receipt_amount: u64,              // Cumulative amount paid out via receipts
+    pub capacity_released: bool,          // Node slot already given back
+    pub refund_to: Pubkey,                // User wallet, or UserVault PDA if vault-funded
+    pub wg_server_pubkey: [u8; 32],       // Node WireGuard key the session was opened under
+    pub refunded_amount: u64,             // Escrow already returned to the user (close / downtime refunds)
 }
 impl Session {
     pub const MAX_SIZE: usize =
-        32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 32 + 32 + 1 + 1;
+        Self::LEGACY_LAYOUT_SIZE +
+        32 + 4 + 4 + 8 + // hash-chain: tip, length, redeemed, price_per_link
+        8 + 8 +          // receipts: nonce, amount
+        1 +              // capacity_released
+        32 +             // refund_to
+        32 +             // wg_server_pubkey
+        8;               // refunded_amount
+
+    // Account body size of earlier layouts, for migrate_session_layout
+    pub const LEGACY_LAYOUT_SIZE: usize = 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 32 + 32 + 1 + 1;
+    pub const CAPACITY_LAYOUT_SIZE: usize = Self::LEGACY_LAYOUT_SIZE + (32 + 4 + 4 + 8) + (8 + 8) + 1;
+    pub const REFUND_TO_LAYOUT_SIZE: usize = Self::CAPACITY_LAYOUT_SIZE + 32;
+
+    // Escrow owed back to the user for time a silent node left unserved. The
+    // node must have missed heartbeats for longer than `timeout` within the
+    // session window; service counts as stopped at the last heartbeat.
+    pub fn downtime_refund(&self, node: &Node, timeout: i64, now: i64) -> Result<u64> {
+        let observed_until = now.min(self.end_ts);
+        require!(
+            observed_until.saturating_sub(node.last_heartbeat_ts) > timeout,
+            DvpnError::NodeStillAlive
+        );
+
+        // Unredeemed hash-chain links were never earned
+        if self.price_per_link_lamports > 0 {
+            return Ok(self.remaining_balance);
+        }
+
+        let outage_start = node.last_heartbeat_ts.max(self.start_ts);
+        let total_duration = self.end_ts.saturating_sub(self.start_ts);
+        let unserved = self.end_ts.saturating_sub(outage_start);
+        let refund = if total_duration > 0 && unserved > 0 {
+            let unserved_fraction = (unserved as u128)
+                .checked_mul(self.escrow_lamports as u128)
+                .ok_or(DvpnError::MathOverflow)?;
+            (unserved_fraction / total_duration as u128) as u64
+        } else {
+            0
+        };
+        Ok(refund.min(self.remaining_balance))
+    }
+
+    // Escrow owed back to the user if service stopped at `until`: what remains
+    // beyond the provider's time-proportional share, net of earlier payouts.
+    // Refunds the user already received count against the refund, not as payouts.
+    pub fn prorated_refund(&self, until: i64) -> u64 {
+        let duration = self.end_ts.saturating_sub(self.start_ts).max(1) as u128;
+        let elapsed = until.clamp(self.start_ts, self.end_ts).saturating_sub(self.start_ts) as u128;
+        let earned = (self.escrow_lamports as u128 * elapsed / duration) as u64;
+        let paid = self.escrow_lamports
+            .saturating_sub(self.remaining_balance)
+            .saturating_sub(self.refunded_amount);
+        self.remaining_balance.saturating_sub(earned.saturating_sub(paid))
+    }
+
+    // Fresh Active session; hash-chain mode off until the opener sets it
+    #[allow(clippy::too_many_arguments)]
+    pub fn init(
+        &mut self,
+        user: Pubkey,
+        node: &Account<Node>,
+        session_id: u64,
+        now: i64,
+        end_ts: i64,
+        escrow: u64,
+        payment_token: Pubkey,
+        refund_to: Pubkey,
+        bump: u8,
+    ) {
+        self.user = user;
+        self.node = node.key();
+        self.session_id = session_id;
+        self.start_ts = now;
+        self.end_ts = end_ts;
+        self.escrow_lamports = escrow;
+        self.remaining_balance = escrow;
+        self.bytes_used = 0;
+        self.last_proof_hash = [0u8; 32];
+        self.state = SessionState::Active;
+        self.payment_token = payment_token;
+        self.hashchain_tip = [0u8; 32];
+        self.hashchain_length = 0;
+        self.hashchain_redeemed = 0;
+        self.price_per_link_lamports = 0;
+        self.receipt_nonce = 0;
+        self.receipt_amount = 0;
+        self.refunded_amount = 0;
+        self.capacity_released = false;
+        self.refund_to = refund_to;
+        self.wg_server_pubkey = node.wg_server_pubkey;
+        self.bump = bump;
+    }
+
+    // Escrow still promised to hash-chain links not yet redeemed; 0 outside hash-chain mode
+    pub fn unredeemed_link_value(&self) -> u64 {
+        (self.hashchain_length.saturating_sub(self.hashchain_redeemed) as u64)
+            .saturating_mul(self.price_per_link_lamports)
+    }
+
+    // Push end_ts out by `minutes` and add the matching top-up to escrow
+    pub fn extend(&mut self, minutes: u32, amount: u64) -> Result<()> {
+        self.end_ts = self.end_ts
+            .checked_add((minutes as i64) * 60)
+            .ok_or(DvpnError::MathOverflow)?;
+        self.escrow_lamports = self.escrow_lamports.checked_add(amount).ok_or(DvpnError::MathOverflow)?;
+        self.remaining_balance = self.remaining_balance.checked_add(amount).ok_or(DvpnError::MathOverflow)?;
+        Ok(())
+    }
+
+    // Give the session's slot back to its node, at most once
+    pub fn release_capacity(&mut self, node: &mut Node) {
+        if !self.capacity_released {
+            node.active_sessions = node.active_sessions.saturating_sub(1);
+            self.capacity_released = true;
+        }
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
@@ -1392,6 +6438,7 @@ pub enum SessionState {
     Claimed,
     Disputed,
     Resolved,
+    Expired,
 }
 
 // ============== SUBSCRIPTION TYPES ==============
@@ -1411,6 +6458,347 @@ pub enum SubscriptionState {
     Claimed,
 }
 
+// Singleton holding the tunable economic parameters
+#[account]
+pub struct ProgramConfig {
+    pub admin: Pubkey,
+    pub treasury_share_bps: u16,          // Treasury cut of revenue; provider gets the rest
+    pub weekly_price_lamports: u64,
+    pub monthly_price_lamports: u64,
+    pub yearly_price_lamports: u64,
+    pub weekly_duration_seconds: i64,
+    pub monthly_duration_seconds: i64,
+    pub yearly_duration_seconds: i64,
+    pub earnings_epoch_seconds: i64,
+    pub max_endpoint_len: u16,            // <= MAX_ENDPOINT_LEN
+    pub max_region_len: u16,              // <= MAX_REGION_LEN
+    pub max_reason_len: u16,              // <= MAX_DISPUTE_REASON_LEN
+    pub heartbeat_timeout_seconds: i64,   // Node silent longer than this counts as down
+    pub unbonding_period_seconds: i64,    // Cooldown before unstaked / undelegated lamports are released
+    pub min_stake_base_lamports: u64,     // Provider::required_stake terms
+    pub min_stake_per_node_lamports: u64,
+    pub min_stake_per_capacity_lamports: u64,
+    pub hashchain_grace_seconds: i64,     // Provider may still redeem links this long after a hash-chain session ends
+    pub governance: Pubkey,               // Governance PDA allowed to apply passed proposals; default = disabled
+    pub slash_user_bps: u16,              // Share of slashed stake paid to the injured user
+    pub slash_insurance_bps: u16,         // Share paid to the insurance fund; treasury gets the rest
+    pub expire_crank_fee_lamports: u64,   // <= MAX_EXPIRE_CRANK_FEE_LAMPORTS
+    pub bond_winner_bps: u16,             // Forfeited bond share to the winner; <= BPS_DENOMINATOR
+    pub bump: u8,
+}
+impl ProgramConfig {
+    pub const MAX_SIZE: usize = 32 + 2 + 8 * 3 + 8 * 3 + 8 + 2 * 3 + 8 + 8 + 8 * 3 + 8 + 32 + 2 * 2 + 8 + 2 + 1;
+
+    pub fn treasury_share(&self, amount: u64) -> Result<u64> {
+        Ok(amount
+            .checked_mul(self.treasury_share_bps as u64)
+            .ok_or(DvpnError::MathOverflow)?
+            / BPS_DENOMINATOR)
+    }
+
+    // (to_user, to_insurance) out of a slashed amount
+    pub fn slash_shares(&self, amount: u64) -> Result<(u64, u64)> {
+        let to_user = amount
+            .checked_mul(self.slash_user_bps as u64)
+            .ok_or(DvpnError::MathOverflow)?
+            / BPS_DENOMINATOR;
+        let to_insurance = amount
+            .checked_mul(self.slash_insurance_bps as u64)
+            .ok_or(DvpnError::MathOverflow)?
+            / BPS_DENOMINATOR;
+        Ok((to_user, to_insurance))
+    }
+
+    // (price_lamports, duration_seconds)
+    pub fn plan_terms(&self, plan: SubscriptionPlan) -> (u64, i64) {
+        match plan {
+            SubscriptionPlan::Weekly => (self.weekly_price_lamports, self.weekly_duration_seconds),
+            SubscriptionPlan::Monthly => (self.monthly_price_lamports, self.monthly_duration_seconds),
+            SubscriptionPlan::Yearly => (self.yearly_price_lamports, self.yearly_duration_seconds),
+        }
+    }
+
+    // Validate and apply a single parameter change
+    pub fn apply(&mut self, param: ConfigParam) -> Result<()> {
+        match param {
+            ConfigParam::TreasuryShareBps(bps) => {
+                require!(bps <= MAX_TREASURY_SHARE_BPS, DvpnError::ConfigOutOfRange);
+                self.treasury_share_bps = bps;
+            }
+            ConfigParam::PlanPrice { plan, lamports } => {
+                require!(lamports > 0, DvpnError::ConfigOutOfRange);
+                match plan {
+                    SubscriptionPlan::Weekly => self.weekly_price_lamports = lamports,
+                    SubscriptionPlan::Monthly => self.monthly_price_lamports = lamports,
+                    SubscriptionPlan::Yearly => self.yearly_price_lamports = lamports,
+                }
+            }
+            ConfigParam::PlanDuration { plan, seconds } => {
+                require!(
+                    (MIN_PLAN_DURATION_SECONDS..=MAX_PLAN_DURATION_SECONDS).contains(&seconds),
+                    DvpnError::ConfigOutOfRange
+                );
+                match plan {
+                    SubscriptionPlan::Weekly => self.weekly_duration_seconds = seconds,
+                    SubscriptionPlan::Monthly => self.monthly_duration_seconds = seconds,
+                    SubscriptionPlan::Yearly => self.yearly_duration_seconds = seconds,
+                }
+            }
+            ConfigParam::EarningsEpochSeconds(seconds) => {
+                require!(
+                    (MIN_EARNINGS_EPOCH_SECONDS..=MAX_EARNINGS_EPOCH_SECONDS).contains(&seconds),
+                    DvpnError::ConfigOutOfRange
+                );
+                self.earnings_epoch_seconds = seconds;
+            }
+            ConfigParam::MaxEndpointLen(len) => {
+                require!(len > 0 && len as usize <= MAX_ENDPOINT_LEN, DvpnError::ConfigOutOfRange);
+                self.max_endpoint_len = len;
+            }
+            ConfigParam::MaxRegionLen(len) => {
+                require!(len > 0 && len as usize <= MAX_REGION_LEN, DvpnError::ConfigOutOfRange);
+                self.max_region_len = len;
+            }
+            ConfigParam::MaxReasonLen(len) => {
+                require!(len > 0 && len as usize <= MAX_DISPUTE_REASON_LEN, DvpnError::ConfigOutOfRange);
+                self.max_reason_len = len;
+            }
+            ConfigParam::HeartbeatTimeoutSeconds(seconds) => {
+                require!(
+                    (MIN_HEARTBEAT_TIMEOUT_SECONDS..=MAX_HEARTBEAT_TIMEOUT_SECONDS).contains(&seconds),
+                    DvpnError::ConfigOutOfRange
+                );
+                self.heartbeat_timeout_seconds = seconds;
+            }
+            ConfigParam::UnbondingPeriodSeconds(seconds) => {
+                require!(
+                    (MIN_UNBONDING_PERIOD_SECONDS..=MAX_UNBONDING_PERIOD_SECONDS).contains(&seconds),
+                    DvpnError::ConfigOutOfRange
+                );
+                self.unbonding_period_seconds = seconds;
+            }
+            ConfigParam::MinStakeBaseLamports(lamports) => {
+                require!(
+                    (MIN_MIN_STAKE_BASE_LAMPORTS..=MAX_MIN_STAKE_BASE_LAMPORTS).contains(&lamports),
+                    DvpnError::ConfigOutOfRange
+                );
+                self.min_stake_base_lamports = lamports;
+            }
+            ConfigParam::MinStakePerNodeLamports(lamports) => {
+                require!(lamports <= MAX_MIN_STAKE_PER_NODE_LAMPORTS, DvpnError::ConfigOutOfRange);
+                self.min_stake_per_node_lamports = lamports;
+            }
+            ConfigParam::MinStakePerCapacityLamports(lamports) => {
+                require!(lamports <= MAX_MIN_STAKE_PER_CAPACITY_LAMPORTS, DvpnError::ConfigOutOfRange);
+                self.min_stake_per_capacity_lamports = lamports;
+            }
+            ConfigParam::HashchainGraceSeconds(seconds) => {
+                require!(
+                    (MIN_HASHCHAIN_GRACE_SECONDS..=MAX_HASHCHAIN_GRACE_SECONDS).contains(&seconds),
+                    DvpnError::ConfigOutOfRange
+                );
+                self.hashchain_grace_seconds = seconds;
+            }
+            ConfigParam::SlashSplit { user_bps, insurance_bps } => {
+                require!(
+                    user_bps as u64 + insurance_bps as u64 <= BPS_DENOMINATOR,
+                    DvpnError::ConfigOutOfRange
+                );
+                self.slash_user_bps = user_bps;
+                self.slash_insurance_bps = insurance_bps;
+            }
+            ConfigParam::ExpireCrankFeeLamports(lamports) => {
+                require!(lamports <= MAX_EXPIRE_CRANK_FEE_LAMPORTS, DvpnError::ConfigOutOfRange);
+                self.expire_crank_fee_lamports = lamports;
+            }
+            ConfigParam::BondWinnerBps(bps) => {
+                require!(bps as u64 <= BPS_DENOMINATOR, DvpnError::ConfigOutOfRange);
+                self.bond_winner_bps = bps;
+            }
+        }
+        Ok(())
+    }
+}
+
+// One typed ProgramConfig change
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigParam {
+    TreasuryShareBps(u16),
+    PlanPrice { plan: SubscriptionPlan, lamports: u64 },
+    PlanDuration { plan: SubscriptionPlan, seconds: i64 },
+    EarningsEpochSeconds(i64),
+    MaxEndpointLen(u16),
+    MaxRegionLen(u16),
+    MaxReasonLen(u16),
+    HeartbeatTimeoutSeconds(i64),
+    UnbondingPeriodSeconds(i64),
+    MinStakeBaseLamports(u64),
+    MinStakePerNodeLamports(u64),
+    MinStakePerCapacityLamports(u64),
+    HashchainGraceSeconds(i64),
+    SlashSplit { user_bps: u16, insurance_bps: u16 },
+    ExpireCrankFeeLamports(u64),
+    BondWinnerBps(u16),
+}
+
+// M-of-N owner set controlling the treasury and ProgramConfig
+#[account]
+pub struct Multisig {
+    pub owners: Vec<Pubkey>,              // <= MAX_MULTISIG_OWNERS
+    pub threshold: u8,
+    pub timelock_seconds: i64,            // Delay between approval and execution of sensitive actions
+    pub withdraw_timelock_threshold_lamports: u64, // Withdrawals above this are timelocked
+    pub proposal_count: u64,              // Next proposal id
+    pub earnings_reporter: Pubkey,        // Opens earnings pools and records usage; can't finalize
+    pub bump: u8,
+}
+impl Multisig {
+    pub const MAX_SIZE: usize = (4 + MAX_MULTISIG_OWNERS * 32) + 1 + 8 + 8 + 8 + 32 + 1;
+
+    pub fn requires_timelock(&self, action: &MultisigAction) -> bool {
+        match action {
+            MultisigAction::WithdrawTreasury { amount, .. } => *amount > self.withdraw_timelock_threshold_lamports,
+            _ => true,
+        }
+    }
+
+    pub fn approval_count(&self, approvals: &[Pubkey]) -> usize {
+        approvals.iter().filter(|a| self.owners.contains(a)).count()
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MultisigAction {
+    WithdrawTreasury { recipient: Pubkey, amount: u64 },
+    FinalizeEarningsPool { pool: Pubkey, total_weighted_score: u128 },
+    UpdateConfig(ConfigParam),
+    SetConfigGovernance(Pubkey),
+    SetGovernanceParams(GovernanceParams),
+    AddOwner(Pubkey),
+    RemoveOwner(Pubkey),
+    SetThreshold(u8),
+    SetTimelock(i64),
+    SetWithdrawTimelockThreshold(u64),
+    SetEarningsReporter(Pubkey),
+}
+impl MultisigAction {
+    pub const MAX_SIZE: usize = 1 + 32 + 16; // FinalizeEarningsPool is the largest variant
+}
+
+#[account]
+pub struct MultisigProposal {
+    pub multisig: Pubkey,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub action: MultisigAction,
+    pub timelocked: bool,
+    pub approvals: Vec<Pubkey>,           // <= MAX_MULTISIG_OWNERS
+    pub created_at: i64,
+    pub eta: i64,                         // Earliest execution time; 0 until threshold is reached
+    pub executed: bool,
+    pub bump: u8,
+}
+impl MultisigProposal {
+    pub const MAX_SIZE: usize =
+        32 + 8 + 32 +                           // multisig, proposal_id, proposer
+        MultisigAction::MAX_SIZE + 1 +          // action, timelocked
+        (4 + MAX_MULTISIG_OWNERS * 32) +        // approvals
+        8 + 8 + 1 + 1;                          // created_at, eta, executed, bump
+
+    // Start the timelock the first time approvals reach the threshold
+    pub fn queue_if_approved(&mut self, multisig: &Multisig, now: i64) {
+        if self.eta == 0 && multisig.approval_count(&self.approvals) >= multisig.threshold as usize {
+            self.eta = if self.timelocked { now.saturating_add(multisig.timelock_seconds) } else { now };
+        }
+    }
+}
+
+// Own stake just before a stake_provider top-up
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StakeCheckpoint {
+    pub ts: i64,                          // 0 = unused
+    pub stake_before: u64,
+}
+impl StakeCheckpoint {
+    pub const SIZE: usize = 8 + 8;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct GovernanceParams {
+    pub voting_period_seconds: i64,
+    pub quorum_lamports: u64,             // Minimum total stake that must vote
+    pub approval_bps: u16,                // Share of cast stake voting yes needed to pass
+    pub min_proposal_stake_lamports: u64, // Own stake required to open a proposal
+}
+impl GovernanceParams {
+    pub const SIZE: usize = 8 + 8 + 2 + 8;
+
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            (MIN_VOTING_PERIOD_SECONDS..=MAX_VOTING_PERIOD_SECONDS).contains(&self.voting_period_seconds),
+            DvpnError::ConfigOutOfRange
+        );
+        require!(self.quorum_lamports > 0, DvpnError::ConfigOutOfRange);
+        require!(
+            self.approval_bps as u64 > BPS_DENOMINATOR / 2 && self.approval_bps as u64 <= BPS_DENOMINATOR,
+            DvpnError::ConfigOutOfRange
+        );
+        Ok(())
+    }
+}
+
+// Stake-weighted provider governance over ProgramConfig
+#[account]
+pub struct Governance {
+    pub params: GovernanceParams,
+    pub proposal_count: u64,              // Next proposal id
+    pub bump: u8,
+}
+impl Governance {
+    pub const MAX_SIZE: usize = GovernanceParams::SIZE + 8 + 1;
+}
+
+#[account]
+pub struct GovernanceProposal {
+    pub proposal_id: u64,
+    pub proposer: Pubkey,                 // Provider PDA
+    pub param: ConfigParam,
+    pub created_at: i64,                  // Stake snapshot time
+    pub voting_ends_at: i64,
+    pub quorum_lamports: u64,             // Thresholds fixed at creation
+    pub approval_bps: u16,
+    pub yes_lamports: u64,
+    pub no_lamports: u64,
+    pub executed: bool,
+    pub bump: u8,
+}
+impl GovernanceProposal {
+    pub const MAX_SIZE: usize =
+        8 + 32 +                // proposal_id, proposer
+        (1 + 1 + 8) +           // param (largest variant: plan + u64/i64)
+        8 + 8 + 8 + 2 +         // created_at, voting_ends_at, quorum, approval_bps
+        8 + 8 + 1 + 1;          // tallies, executed, bump
+
+    pub fn passed(&self) -> bool {
+        let cast = self.yes_lamports as u128 + self.no_lamports as u128;
+        cast >= self.quorum_lamports as u128
+            && self.yes_lamports as u128 * BPS_DENOMINATOR as u128 >= cast * self.approval_bps as u128
+    }
+}
+
+// One provider's vote on one proposal
+#[account]
+pub struct GovernanceVote {
+    pub proposal: Pubkey,
+    pub provider: Pubkey,
+    pub approve: bool,
+    pub weight_lamports: u64,
+    pub bump: u8,
+}
+impl GovernanceVote {
+    pub const MAX_SIZE: usize = 32 + 32 + 1 + 8 + 1;
+}
+
 #[account]
 pub struct Subscription {
     pub user: Pubkey,
@@ -1420,9 +6808,249 @@ pub struct Subscription {
     pub end_ts: i64,
     pub state: SubscriptionState,
     pub bump: u8,
+    pub refund_to: Pubkey,                // User wallet, or UserVault PDA if vault-funded
 }
 impl Subscription {
-    pub const MAX_SIZE: usize = 32 + 1 + 8 + 8 + 8 + 1 + 1;
+    pub const MAX_SIZE: usize = 32 + 1 + 8 + 8 + 8 + 1 + 1 + 32;
+}
+
+// Receives a share of slashed provider stake
+#[account]
+pub struct InsuranceFund {
+    pub authority: Pubkey,
+    pub total_received: u64,
+    pub bump: u8,
+}
+impl InsuranceFund {
+    pub const MAX_SIZE: usize = 32 + 8 + 1;
+}
+
+// How a dispute's slash was split, one per resolved session
+#[account]
+pub struct SlashRecord {
+    pub session: Pubkey,
+    pub provider: Pubkey,
+    pub total_slashed: u64,
+    pub to_user: u64,
+    pub to_treasury: u64,
+    pub to_insurance: u64,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+impl SlashRecord {
+    pub const MAX_SIZE: usize = 32 + 32 + 8 + 8 + 8 + 8 + 8 + 1;
+}
+
+// Prepaid user deposit. SOL lives on the PDA itself; SPL tokens live in
+// token accounts owned by the PDA.
+#[account]
+pub struct UserVault {
+    pub owner: Pubkey,
+    pub daily_limit_lamports: u64,        // 0 = unlimited
+    pub daily_limit_tokens: u64,          // 0 = unlimited, counted across mints
+    pub spent_today_lamports: u64,
+    pub spent_today_tokens: u64,
+    pub day_start_ts: i64,
+    pub bump: u8,
+}
+impl UserVault {
+    pub const MAX_SIZE: usize = 32 + 8 + 8 + 8 + 8 + 8 + 1;
+
+    // Lamports above the rent-exempt minimum
+    pub fn available_lamports(vault: &AccountInfo) -> Result<u64> {
+        let rent_min = Rent::get()?.minimum_balance(8 + Self::MAX_SIZE);
+        Ok(vault.lamports().saturating_sub(rent_min))
+    }
+
+    fn roll_day(&mut self, now: i64) {
+        if now.saturating_sub(self.day_start_ts) >= VAULT_DAY_SECONDS {
+            self.day_start_ts = now;
+            self.spent_today_lamports = 0;
+            self.spent_today_tokens = 0;
+        }
+    }
+
+    pub fn record_spend_lamports(&mut self, amount: u64, now: i64) -> Result<()> {
+        self.roll_day(now);
+        let spent = self.spent_today_lamports.checked_add(amount).ok_or(DvpnError::MathOverflow)?;
+        require!(
+            self.daily_limit_lamports == 0 || spent <= self.daily_limit_lamports,
+            DvpnError::DailyLimitExceeded
+        );
+        self.spent_today_lamports = spent;
+        Ok(())
+    }
+
+    pub fn record_spend_tokens(&mut self, amount: u64, now: i64) -> Result<()> {
+        self.roll_day(now);
+        let spent = self.spent_today_tokens.checked_add(amount).ok_or(DvpnError::MathOverflow)?;
+        require!(
+            self.daily_limit_tokens == 0 || spent <= self.daily_limit_tokens,
+            DvpnError::DailyLimitExceeded
+        );
+        self.spent_today_tokens = spent;
+        Ok(())
+    }
+}
+
+// Scoped hot key acting for a provider
+#[account]
+pub struct Operator {
+    pub provider: Pubkey,
+    pub key: Pubkey,
+    pub permissions: u8,                  // OPERATOR_PERMISSION_* bitmask
+    pub bump: u8,
+}
+impl Operator {
+    pub const MAX_SIZE: usize = 32 + 32 + 1 + 1;
+}
+
+// Governance-managed set of keys eligible to sit on dispute panels
+#[account]
+pub struct ArbiterRegistry {
+    pub authority: Pubkey,                // Governance key
+    pub arbiters: Vec<Pubkey>,            // <= MAX_ARBITERS
+    pub min_panel_size: u8,
+    pub ruling_timeout_seconds: i64,      // After this, auto_resolve_dispute applies
+    pub user_bond: BondSchedule,          // Bond for disputes raised by the user
+    pub provider_bond: BondSchedule,      // Bond for disputes raised by the provider
+    pub bump: u8,
+}
+impl ArbiterRegistry {
+    pub const MAX_SIZE: usize = 32 + (4 + MAX_ARBITERS * 32) + 1 + 8 + 2 * BondSchedule::SIZE + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct BondSchedule {
+    pub base_lamports: u64,               // Flat minimum
+    pub escrow_bps: u16,                  // Share of SOL session escrow
+}
+impl BondSchedule {
+    pub const SIZE: usize = 8 + 2;
+
+    pub fn bond_for(&self, escrow_lamports: u64) -> Result<u64> {
+        let scaled = escrow_lamports
+            .checked_mul(self.escrow_bps as u64)
+            .ok_or(DvpnError::MathOverflow)?
+            / BPS_DENOMINATOR;
+        Ok(self.base_lamports.max(scaled))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct ArbiterVote {
+    pub arbiter: Pubkey,
+    pub refund_to_user: u64,
+    pub slash_amount: u64,
+}
+
+// Arbiters seated on one disputed session and their votes
+#[account]
+pub struct DisputePanel {
+    pub session: Pubkey,
+    pub arbiters: Vec<Pubkey>,            // <= MAX_DISPUTE_PANEL
+    pub votes: Vec<ArbiterVote>,
+    pub decided: bool,
+    pub refund_to_user: u64,              // Winning outcome once decided
+    pub slash_amount: u64,
+    pub bump: u8,
+}
+impl DisputePanel {
+    pub const MAX_SIZE: usize =
+        32 +                                    // session
+        (4 + MAX_DISPUTE_PANEL * 32) +          // arbiters
+        (4 + MAX_DISPUTE_PANEL * (32 + 8 + 8)) + // votes
+        1 + 8 + 8 + 1;                          // decided, outcome, bump
+
+    // Decide once a strict majority of the panel backs the same outcome
+    pub fn tally(&mut self, refund_to_user: u64, slash_amount: u64) {
+        let backing = self
+            .votes
+            .iter()
+            .filter(|v| v.refund_to_user == refund_to_user && v.slash_amount == slash_amount)
+            .count();
+        if backing * 2 > self.arbiters.len() {
+            self.decided = true;
+            self.refund_to_user = refund_to_user;
+            self.slash_amount = slash_amount;
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq)]
+pub struct Evidence {
+    pub submitter: Pubkey,
+    pub hash: [u8; 32],                   // Hash of the evidence artifact
+    pub uri: String,                      // <= MAX_EVIDENCE_URI_LEN
+    pub submitted_at: i64,
+}
+
+// Record of a dispute raised on a session
+#[account]
+pub struct Dispute {
+    pub session: Pubkey,
+    pub raised_by: Pubkey,
+    pub raised_by_user: bool,             // false = raised by the provider
+    pub reason: String,                   // Max 200 chars
+    pub raised_at: i64,
+    pub deadline_ts: i64,                 // auto_resolve_dispute allowed from here
+    pub resolved_at: i64,                 // 0 while open
+    pub evidence: Vec<Evidence>,          // <= MAX_DISPUTE_EVIDENCE
+    pub bond_lamports: u64,               // Held on this account until the outcome
+    pub prior_state: SessionState,        // Restored if the dispute is withdrawn
+    pub bump: u8,
+}
+impl Dispute {
+    pub const MAX_SIZE: usize =
+        32 + 32 + 1 +                                       // session, raised_by, raised_by_user
+        (4 + MAX_DISPUTE_REASON_LEN) +                      // reason
+        8 + 8 + 8 +                                         // raised_at, deadline_ts, resolved_at
+        (4 + MAX_DISPUTE_EVIDENCE * (32 + 32 + (4 + MAX_EVIDENCE_URI_LEN) + 8)) + // evidence
+        8 + 1 +                                             // bond_lamports, prior_state
+        1;                                                  // bump
+}
+
+// Public provider identity and no-logs policy commitment
+#[account]
+pub struct ProviderProfile {
+    pub provider: Pubkey,
+    pub display_name: String,             // <= MAX_DISPLAY_NAME_LEN
+    pub website: String,                  // <= MAX_WEBSITE_LEN
+    pub jurisdiction: String,             // ISO 3166 country code, <= MAX_JURISDICTION_LEN
+    pub policy_hash: [u8; 32],            // SHA-256 of the signed no-logs policy
+    pub policy_uri: String,               // <= MAX_POLICY_URI_LEN
+    pub policy_version: u32,              // Bumped on every policy change
+    pub policy_updated_at: i64,
+    pub updated_at: i64,
+    pub bump: u8,
+}
+impl ProviderProfile {
+    pub const MAX_SIZE: usize =
+        32 +                // provider
+        (4 + MAX_DISPLAY_NAME_LEN) +  // display_name
+        (4 + MAX_WEBSITE_LEN) +       // website
+        (4 + MAX_JURISDICTION_LEN) +  // jurisdiction
+        32 +                // policy_hash
+        (4 + MAX_POLICY_URI_LEN) +    // policy_uri
+        4 +                 // policy_version
+        8 +                 // policy_updated_at
+        8 +                 // updated_at
+        1;                  // bump
+}
+
+// Per-delegator position in a provider's delegated pool
+#[account]
+pub struct Delegation {
+    pub provider: Pubkey,
+    pub delegator: Pubkey,
+    pub shares: u64,                      // Active shares of the delegated pool
+    pub reward_debt: u128,                // shares * acc_reward_per_share at last settlement
+    pub unbonding_shares: u64,            // Shares of the delegated unbonding pool
+    pub unbonding_unlock_ts: i64,
+    pub bump: u8,
+}
+impl Delegation {
+    pub const MAX_SIZE: usize = 32 + 32 + 8 + 16 + 8 + 8 + 1;
 }
 
 #[account]
@@ -1468,6 +7096,359 @@ impl UsageRecord {
     pub const MAX_SIZE: usize = 32 + 8 + 8 + 8 + 4 + 1 + 1;
 }
 
+// ============== SPL ESCROW HELPERS ==============
+
+// Move tokens out of a session escrow token account, signed by the session PDA
+fn session_token_transfer<'info>(
+    token_program: &Program<'info, Token>,
+    session: &Account<'info, Session>,
+    from: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    amount: u64,
+) -> Result<()> {
+    let session_id = session.session_id.to_le_bytes();
+    let bump = [session.bump];
+    let seeds: &[&[u8]] = &[
+        SESSION_SEED,
+        session.user.as_ref(),
+        session.node.as_ref(),
+        &session_id,
+        &bump,
+    ];
+    let signer_seeds = &[seeds];
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        Transfer {
+            from: from.to_account_info(),
+            to: to.to_account_info(),
+            authority: session.to_account_info(),
+        },
+        signer_seeds,
+    );
+    token::transfer(cpi_ctx, amount)
+}
+
+// Close an emptied session escrow token account, returning its rent to `destination`
+fn session_token_close<'info>(
+    token_program: &Program<'info, Token>,
+    session: &Account<'info, Session>,
+    escrow: &Account<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+) -> Result<()> {
+    let session_id = session.session_id.to_le_bytes();
+    let bump = [session.bump];
+    let seeds: &[&[u8]] = &[
+        SESSION_SEED,
+        session.user.as_ref(),
+        session.node.as_ref(),
+        &session_id,
+        &bump,
+    ];
+    let signer_seeds = &[seeds];
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: escrow.to_account_info(),
+            destination,
+            authority: session.to_account_info(),
+        },
+        signer_seeds,
+    );
+    token::close_account(cpi_ctx)
+}
+
+// Close a settled session escrow. Tokens beyond the tracked balance (e.g. sent
+// to the escrow directly) go back to the user first, since a token account
+// with a non-zero balance cannot be closed.
+fn sweep_and_close_escrow<'info>(
+    token_program: &Program<'info, Token>,
+    session: &Account<'info, Session>,
+    escrow: &mut Account<'info, TokenAccount>,
+    user_token_account: Option<&Account<'info, TokenAccount>>,
+    destination: AccountInfo<'info>,
+) -> Result<()> {
+    escrow.reload()?;
+    if escrow.amount > 0 {
+        let to = user_token_account.ok_or(DvpnError::TokenAccountMissing)?;
+        session_token_transfer(token_program, session, escrow, to, escrow.amount)?;
+    }
+    session_token_close(token_program, session, escrow, destination)
+}
+
+// Move slashed lamports off the provider PDA, split between the injured user,
+// the treasury and the insurance fund, and record the split. The provider's
+// stake counters must already have been reduced by `amount`.
+#[allow(clippy::too_many_arguments)]
+fn distribute_slash<'info>(
+    config: &ProgramConfig,
+    provider: &Account<'info, Provider>,
+    user: &AccountInfo<'info>,
+    treasury: &mut Account<'info, Treasury>,
+    insurance_fund: &mut Account<'info, InsuranceFund>,
+    slash_record: &mut Account<'info, SlashRecord>,
+    session: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let (to_user, to_insurance) = config.slash_shares(amount)?;
+    let to_treasury = amount.saturating_sub(to_user).saturating_sub(to_insurance);
+
+    if amount > 0 {
+        **provider.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **user.try_borrow_mut_lamports()? += to_user;
+        **treasury.to_account_info().try_borrow_mut_lamports()? += to_treasury;
+        **insurance_fund.to_account_info().try_borrow_mut_lamports()? += to_insurance;
+    }
+
+    treasury.total_collected = treasury.total_collected.saturating_add(to_treasury);
+    insurance_fund.total_received = insurance_fund.total_received.saturating_add(to_insurance);
+
+    slash_record.session = session;
+    slash_record.provider = provider.key();
+    slash_record.total_slashed = amount;
+    slash_record.to_user = to_user;
+    slash_record.to_treasury = to_treasury;
+    slash_record.to_insurance = to_insurance;
+    slash_record.timestamp = Clock::get()?.unix_timestamp;
+    Ok(())
+}
+
+// Pay out a dispute bond: back to the raiser if they won, otherwise split
+// between the winning counterparty and the treasury
+fn settle_dispute_bond<'info>(
+    config: &ProgramConfig,
+    dispute: &mut Account<'info, Dispute>,
+    raiser: &AccountInfo<'info>,
+    counterparty: &AccountInfo<'info>,
+    treasury: &mut Account<'info, Treasury>,
+    raiser_won: bool,
+) -> Result<()> {
+    if raiser_won {
+        return return_dispute_bond(dispute, raiser);
+    }
+
+    let bond = dispute.bond_lamports;
+    let to_winner = bond
+        .checked_mul(config.bond_winner_bps as u64)
+        .ok_or(DvpnError::MathOverflow)?
+        / BPS_DENOMINATOR;
+    let to_treasury = bond.saturating_sub(to_winner);
+
+    if bond > 0 {
+        **dispute.to_account_info().try_borrow_mut_lamports()? -= bond;
+        **counterparty.try_borrow_mut_lamports()? += to_winner;
+        **treasury.to_account_info().try_borrow_mut_lamports()? += to_treasury;
+        treasury.total_collected = treasury.total_collected.saturating_add(to_treasury);
+    }
+    dispute.bond_lamports = 0;
+    Ok(())
+}
+
+fn return_dispute_bond<'info>(
+    dispute: &mut Account<'info, Dispute>,
+    raiser: &AccountInfo<'info>,
+) -> Result<()> {
+    let bond = dispute.bond_lamports;
+    if bond > 0 {
+        **dispute.to_account_info().try_borrow_mut_lamports()? -= bond;
+        **raiser.try_borrow_mut_lamports()? += bond;
+    }
+    dispute.bond_lamports = 0;
+    Ok(())
+}
+
+// A resolved dispute is final: hash-chain links not redeemed by then were never
+// earned, so they go back to the user and claim_payout can settle the session
+fn refund_unredeemed_links<'info>(
+    session: &mut Account<'info, Session>,
+    refund_to: &AccountInfo<'info>,
+) -> Result<()> {
+    let refund = session.remaining_balance;
+    if session.price_per_link_lamports > 0 && refund > 0 {
+        **session.to_account_info().try_borrow_mut_lamports()? -= refund;
+        **refund_to.try_borrow_mut_lamports()? += refund;
+        session.remaining_balance = 0;
+    }
+    Ok(())
+}
+
+// Whether a (possibly uninitialized) dispute panel PDA holds a decided ruling
+fn panel_decided(panel: &UncheckedAccount) -> Result<bool> {
+    if panel.data_is_empty() {
+        return Ok(false);
+    }
+    let data = panel.try_borrow_data()?;
+    let panel = DisputePanel::try_deserialize(&mut &data[..])?;
+    Ok(panel.decided)
+}
+
+// Close a PDA that may never have been created, sending its rent to `destination`
+fn close_if_initialized<'info>(account: &UncheckedAccount<'info>, destination: &AccountInfo<'info>) -> Result<()> {
+    if account.data_is_empty() {
+        return Ok(());
+    }
+    require_keys_eq!(*account.owner, crate::ID, DvpnError::Unauthorized);
+    let info = account.to_account_info();
+    **destination.try_borrow_mut_lamports()? += info.lamports();
+    **info.try_borrow_mut_lamports()? = 0;
+    info.assign(&anchor_lang::system_program::ID);
+    info.realloc(0, false)?;
+    Ok(())
+}
+
+// Signer must be the treasury authority, or the multisig's earnings reporter
+// once the multisig holds the treasury
+fn authorize_earnings_reporter(
+    treasury: &Account<Treasury>,
+    multisig: &Option<Account<Multisig>>,
+    signer: &Pubkey,
+) -> Result<()> {
+    if treasury.authority == *signer {
+        return Ok(());
+    }
+    let multisig = multisig.as_ref().ok_or(DvpnError::Unauthorized)?;
+    require_keys_eq!(treasury.authority, multisig.key(), DvpnError::Unauthorized);
+    require_keys_eq!(multisig.earnings_reporter, *signer, DvpnError::Unauthorized);
+    Ok(())
+}
+
+// Signer must be the provider authority, or an operator of this provider
+// holding `permission`. Contexts that accept operators take the signer's
+// Operator PDA as an optional `operator` account; the authority omits it.
+fn authorize_provider(
+    provider: &Account<Provider>,
+    signer: &Pubkey,
+    operator: &Option<Account<Operator>>,
+    permission: u8,
+) -> Result<()> {
+    if provider.authority == *signer {
+        return Ok(());
+    }
+    let operator = operator.as_ref().ok_or(DvpnError::Unauthorized)?;
+    require_keys_eq!(operator.provider, provider.key(), DvpnError::Unauthorized);
+    require_keys_eq!(operator.key, *signer, DvpnError::Unauthorized);
+    require!(operator.permissions & permission != 0, DvpnError::Unauthorized);
+    Ok(())
+}
+
+// Pay the provider's share of a settlement to `payout`, less the delegators'
+// commission-adjusted cut, which stays on the provider PDA until claimed
+fn pay_provider_share<'info>(
+    provider: &mut Account<'info, Provider>,
+    payout: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let delegator_share = provider.accrue_delegator_rewards(amount)?;
+    **payout.try_borrow_mut_lamports()? += amount - delegator_share;
+    **provider.to_account_info().try_borrow_mut_lamports()? += delegator_share;
+    Ok(())
+}
+
+// Pay a delegator the rewards accrued on their active shares since the last
+// settlement. Caller must reset reward_debt after changing shares.
+fn pay_delegation_rewards<'info>(
+    provider: &mut Account<'info, Provider>,
+    delegation: &mut Delegation,
+    delegator: &AccountInfo<'info>,
+) -> Result<()> {
+    let accrued = provider.reward_debt_for(delegation.shares);
+    let pending = accrued.saturating_sub(delegation.reward_debt) as u64;
+    if pending > 0 {
+        **provider.to_account_info().try_borrow_mut_lamports()? -= pending;
+        **delegator.try_borrow_mut_lamports()? += pending;
+    }
+    delegation.reward_debt = accrued;
+    Ok(())
+}
+
+// Move tokens out of a user vault token account, signed by the vault PDA
+fn vault_token_transfer<'info>(
+    token_program: &Program<'info, Token>,
+    vault: &Account<'info, UserVault>,
+    from: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    amount: u64,
+) -> Result<()> {
+    let bump = [vault.bump];
+    let seeds: &[&[u8]] = &[USER_VAULT_SEED, vault.owner.as_ref(), &bump];
+    let signer_seeds = &[seeds];
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        Transfer {
+            from: from.to_account_info(),
+            to: to.to_account_info(),
+            authority: vault.to_account_info(),
+        },
+        signer_seeds,
+    );
+    token::transfer(cpi_ctx, amount)
+}
+
+// ============== USAGE RECEIPTS ==============
+
+// Receipt message signed by the user: session PDA | cumulative bytes | cumulative amount | nonce
+pub fn receipt_message(
+    session: &Pubkey,
+    cumulative_bytes: u64,
+    cumulative_amount: u64,
+    nonce: u64,
+) -> [u8; 56] {
+    let mut message = [0u8; 56];
+    message[..32].copy_from_slice(session.as_ref());
+    message[32..40].copy_from_slice(&cumulative_bytes.to_le_bytes());
+    message[40..48].copy_from_slice(&cumulative_amount.to_le_bytes());
+    message[48..56].copy_from_slice(&nonce.to_le_bytes());
+    message
+}
+
+// Check that the instruction right before this one is an Ed25519 program
+// verification of `message` by `signer`, with all data inline in that instruction
+fn verify_ed25519_receipt(
+    instructions_sysvar: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    use anchor_lang::solana_program::sysvar::instructions::{
+        load_current_index_checked, load_instruction_at_checked,
+    };
+
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    require!(current_index > 0, DvpnError::InvalidReceiptSignature);
+    let ix = load_instruction_at_checked((current_index - 1) as usize, instructions_sysvar)?;
+
+    require_keys_eq!(ix.program_id, anchor_lang::solana_program::ed25519_program::ID, DvpnError::InvalidReceiptSignature);
+    require!(ix.accounts.is_empty(), DvpnError::InvalidReceiptSignature);
+
+    // Header: num_signatures (u8), padding (u8), then one 14-byte offsets struct
+    let data = &ix.data;
+    require!(data.len() >= 16 && data[0] == 1, DvpnError::InvalidReceiptSignature);
+
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let signature_ix_index = read_u16(4);
+    let pubkey_offset = read_u16(6) as usize;
+    let pubkey_ix_index = read_u16(8);
+    let message_offset = read_u16(10) as usize;
+    let message_size = read_u16(12) as usize;
+    let message_ix_index = read_u16(14);
+
+    // u16::MAX means "this instruction"; anything else could point at unverified data
+    require!(
+        signature_ix_index == u16::MAX && pubkey_ix_index == u16::MAX && message_ix_index == u16::MAX,
+        DvpnError::InvalidReceiptSignature
+    );
+
+    let pubkey = data
+        .get(pubkey_offset..pubkey_offset + 32)
+        .ok_or(DvpnError::InvalidReceiptSignature)?;
+    require!(pubkey == signer.as_ref(), DvpnError::InvalidReceiptSignature);
+
+    let signed = data
+        .get(message_offset..message_offset + message_size)
+        .ok_or(DvpnError::InvalidReceiptSignature)?;
+    require!(signed == message, DvpnError::InvalidReceiptSignature);
+
+    Ok(())
+}
+
 #[error_code]
 pub enum DvpnError {
     #[msg("Unauthorized")]
@@ -1518,4 +7499,106 @@ pub enum DvpnError {
     PoolNotFinalized,
     #[msg("Pool epoch not ended")]
     PoolNotEnded,
+    #[msg("Session uses hash-chain payments")]
+    HashchainSession,
+    #[msg("Session does not use hash-chain payments")]
+    NotHashchainSession,
+    #[msg("Hash-chain proof does not match last redeemed link")]
+    InvalidHashchainProof,
+    #[msg("Hash-chain exhausted")]
+    HashchainExhausted,
+    #[msg("Hash-chain redemption window still open")]
+    RedemptionWindowOpen,
+    #[msg("Account already migrated")]
+    AlreadyMigrated,
+    #[msg("Receipt signature missing or invalid")]
+    InvalidReceiptSignature,
+    #[msg("Receipt is stale or replayed")]
+    StaleReceipt,
+    #[msg("Wrong payment token for this session")]
+    WrongPaymentToken,
+    #[msg("Session already ended")]
+    SessionEnded,
+    #[msg("Token account required to settle the escrow")]
+    TokenAccountMissing,
+    #[msg("Mint not accepted by this node")]
+    MintNotAccepted,
+    #[msg("Too many accepted mints")]
+    TooManyMints,
+    #[msg("Node heartbeat still within timeout")]
+    NodeStillAlive,
+    #[msg("Vault daily spending limit exceeded")]
+    DailyLimitExceeded,
+    #[msg("Session is funded from a vault; extend it from the vault")]
+    VaultFundedSession,
+    #[msg("Target node must differ from current node")]
+    SameNode,
+    #[msg("Nothing is unbonding")]
+    NothingUnbonding,
+    #[msg("Stake is still unbonding")]
+    StillUnbonding,
+    #[msg("Provider stake below minimum requirement")]
+    BelowMinimumStake,
+    #[msg("Delegation pool fully slashed")]
+    DelegationPoolDepleted,
+    #[msg("Invalid operator permissions")]
+    InvalidPermissions,
+    #[msg("No pending authority transfer")]
+    NoPendingTransfer,
+    #[msg("Node still has open or unclaimed sessions")]
+    NodeHasSessions,
+    #[msg("Provider still has nodes")]
+    ProviderHasNodes,
+    #[msg("Provider still has stake or delegations")]
+    ProviderHasStake,
+    #[msg("Provider still has operator keys")]
+    ProviderHasOperators,
+    #[msg("Key activation must leave the minimum overlap window")]
+    KeyOverlapTooShort,
+    #[msg("Invalid arbiter panel")]
+    InvalidPanel,
+    #[msg("Too many arbiters")]
+    TooManyArbiters,
+    #[msg("Arbiter already registered")]
+    ArbiterAlreadyRegistered,
+    #[msg("Signer is not an eligible arbiter")]
+    NotAnArbiter,
+    #[msg("Arbiter already voted")]
+    AlreadyVoted,
+    #[msg("Dispute ruling not decided yet")]
+    DisputeUndecided,
+    #[msg("Dispute ruling already decided")]
+    DisputeAlreadyDecided,
+    #[msg("Too much evidence submitted")]
+    TooMuchEvidence,
+    #[msg("Dispute ruling deadline not reached")]
+    DisputeDeadlineNotReached,
+    #[msg("Session is under dispute")]
+    SessionDisputed,
+    #[msg("Arbiters already seated on this dispute")]
+    PanelAlreadySeated,
+    #[msg("Config value out of allowed range")]
+    ConfigOutOfRange,
+    #[msg("Invalid multisig owner set")]
+    InvalidMultisigOwners,
+    #[msg("Invalid multisig threshold")]
+    InvalidThreshold,
+    #[msg("Signer is not a multisig owner")]
+    NotMultisigOwner,
+    #[msg("Proposal already executed")]
+    ProposalAlreadyExecuted,
+    #[msg("Owner already approved")]
+    AlreadyApproved,
+    #[msg("Not enough approvals")]
+    NotEnoughApprovals,
+    #[msg("Timelock has not elapsed")]
+    TimelockNotElapsed,
+    #[msg("Account required by this action is missing")]
+    ActionAccountMissing,
+    #[msg("Voting period has ended")]
+    VotingClosed,
+    #[msg("Voting period still open")]
+    VotingStillOpen,
+    #[msg("Proposal did not reach quorum or approval")]
+    ProposalNotPassed,
 }
\ No newline at end of file
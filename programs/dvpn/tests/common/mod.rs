@@ -0,0 +1,744 @@
+#![allow(dead_code)]
+
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_spl::token::spl_token;
+use solana_program_test::{
+    processor, BanksClientError, ProgramTest, ProgramTestBanksClientExt, ProgramTestContext,
+};
+use solana_sdk::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    ed25519_program,
+    instruction::{Instruction, InstructionError},
+    pubkey::Pubkey,
+    program_pack::Pack,
+    signature::{Keypair, Signer},
+    system_instruction, system_program,
+    sysvar::clock::Clock,
+    transaction::{Transaction, TransactionError},
+};
+
+use dvpn::{
+    DvpnError, GovernanceParams, MultisigAction, ARBITER_REGISTRY_SEED, DISPUTE_PANEL_SEED, DISPUTE_SEED,
+    GOVERNANCE_PROPOSAL_SEED, GOVERNANCE_SEED, GOVERNANCE_VOTE_SEED, INSURANCE_FUND_SEED, MULTISIG_PROPOSAL_SEED,
+    MULTISIG_SEED, NODE_PRICING_SEED, NODE_SEED, PROGRAM_CONFIG_SEED, PROVIDER_PROFILE_SEED, PROVIDER_SEED, SESSION_SEED,
+    SLASH_RECORD_SEED, TREASURY_SEED,
+};
+
+pub const LAMPORTS_PER_SOL: u64 = 1_000_000_000;
+pub const NODE_ID: u64 = 1;
+pub const NODE_PRICE_PER_MINUTE: u64 = 100_000;
+pub const NODE_CAPACITY: u32 = 10;
+pub const PROVIDER_STAKE: u64 = LAMPORTS_PER_SOL;
+
+// The generated entrypoint wants `&'a [AccountInfo<'a>]`; the test processor
+// hands out a shorter-lived slice, so pin a copy for the process lifetime.
+fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    dvpn::entry(program_id, accounts, data)
+}
+
+pub fn program_test() -> ProgramTest {
+    let mut test = ProgramTest::new("dvpn", dvpn::ID, processor!(process_instruction));
+    test.prefer_bpf(false);
+    test
+}
+
+pub async fn start() -> ProgramTestContext {
+    program_test().start_with_context().await
+}
+
+// ============== PDAs ==============
+
+pub fn config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[PROGRAM_CONFIG_SEED], &dvpn::ID).0
+}
+
+pub fn treasury_pda() -> Pubkey {
+    Pubkey::find_program_address(&[TREASURY_SEED], &dvpn::ID).0
+}
+
+pub fn provider_pda(authority: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[PROVIDER_SEED, authority.as_ref()], &dvpn::ID).0
+}
+
+pub fn provider_profile_pda(provider: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[PROVIDER_PROFILE_SEED, provider.as_ref()], &dvpn::ID).0
+}
+
+pub fn node_pda(provider: &Pubkey, node_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[NODE_SEED, provider.as_ref(), &node_id.to_le_bytes()], &dvpn::ID).0
+}
+
+pub fn node_pricing_pda(node: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[NODE_PRICING_SEED, node.as_ref()], &dvpn::ID).0
+}
+
+pub fn session_pda(user: &Pubkey, node: &Pubkey, session_id: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[SESSION_SEED, user.as_ref(), node.as_ref(), &session_id.to_le_bytes()],
+        &dvpn::ID,
+    )
+    .0
+}
+
+pub fn arbiter_registry_pda() -> Pubkey {
+    Pubkey::find_program_address(&[ARBITER_REGISTRY_SEED], &dvpn::ID).0
+}
+
+pub fn dispute_pda(session: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[DISPUTE_SEED, session.as_ref()], &dvpn::ID).0
+}
+
+pub fn dispute_panel_pda(session: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[DISPUTE_PANEL_SEED, session.as_ref()], &dvpn::ID).0
+}
+
+pub fn insurance_fund_pda() -> Pubkey {
+    Pubkey::find_program_address(&[INSURANCE_FUND_SEED], &dvpn::ID).0
+}
+
+pub fn slash_record_pda(session: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[SLASH_RECORD_SEED, session.as_ref()], &dvpn::ID).0
+}
+
+pub fn multisig_pda() -> Pubkey {
+    Pubkey::find_program_address(&[MULTISIG_SEED], &dvpn::ID).0
+}
+
+pub fn multisig_proposal_pda(proposal_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[MULTISIG_PROPOSAL_SEED, &proposal_id.to_le_bytes()], &dvpn::ID).0
+}
+
+pub fn governance_pda() -> Pubkey {
+    Pubkey::find_program_address(&[GOVERNANCE_SEED], &dvpn::ID).0
+}
+
+pub fn governance_proposal_pda(proposal_id: u64) -> Pubkey {
+    Pubkey::find_program_address(&[GOVERNANCE_PROPOSAL_SEED, &proposal_id.to_le_bytes()], &dvpn::ID).0
+}
+
+pub fn governance_vote_pda(proposal: &Pubkey, provider: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[GOVERNANCE_VOTE_SEED, proposal.as_ref(), provider.as_ref()], &dvpn::ID).0
+}
+
+// ============== Transport ==============
+
+pub fn ix(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: dvpn::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+// Identical transactions under one blockhash are deduplicated, so a resend of
+// an earlier transaction waits for a fresh blockhash
+pub async fn send(
+    ctx: &mut ProgramTestContext,
+    ixs: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let mut all: Vec<&Keypair> = vec![&ctx.payer];
+    all.extend(signers.iter().copied().filter(|k| k.pubkey() != ctx.payer.pubkey()));
+    let mut blockhash = ctx.banks_client.get_latest_blockhash().await?;
+    loop {
+        let tx = Transaction::new_signed_with_payer(ixs, Some(&ctx.payer.pubkey()), &all, blockhash);
+        if ctx.banks_client.get_transaction_status(tx.signatures[0]).await?.is_none() {
+            return ctx.banks_client.process_transaction(tx).await;
+        }
+        blockhash = ctx.banks_client.get_new_latest_blockhash(&blockhash).await?;
+    }
+}
+
+pub fn assert_dvpn_error(result: Result<(), BanksClientError>, expected: DvpnError) {
+    let code = u32::from(expected);
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(got),
+        ))) => assert_eq!(got, code, "expected {:?}", expected),
+        other => panic!("expected {:?}, got {:?}", expected, other),
+    }
+}
+
+pub async fn fetch<T: AccountDeserialize>(ctx: &mut ProgramTestContext, key: &Pubkey) -> T {
+    let account = ctx.banks_client.get_account(*key).await.unwrap().expect("account exists");
+    T::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+pub async fn exists(ctx: &mut ProgramTestContext, key: &Pubkey) -> bool {
+    ctx.banks_client.get_account(*key).await.unwrap().is_some()
+}
+
+pub async fn lamports(ctx: &mut ProgramTestContext, key: &Pubkey) -> u64 {
+    ctx.banks_client.get_balance(*key).await.unwrap()
+}
+
+pub async fn now(ctx: &mut ProgramTestContext) -> i64 {
+    ctx.banks_client.get_sysvar::<Clock>().await.unwrap().unix_timestamp
+}
+
+pub async fn set_time(ctx: &mut ProgramTestContext, unix_timestamp: i64) {
+    let mut clock: Clock = ctx.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp = unix_timestamp;
+    ctx.set_sysvar(&clock);
+}
+
+pub async fn advance(ctx: &mut ProgramTestContext, seconds: i64) {
+    let t = now(ctx).await;
+    set_time(ctx, t + seconds).await;
+}
+
+pub async fn funded_keypair(ctx: &mut ProgramTestContext, lamports: u64) -> Keypair {
+    let kp = Keypair::new();
+    let transfer = system_instruction::transfer(&ctx.payer.pubkey(), &kp.pubkey(), lamports);
+    send(ctx, &[transfer], &[]).await.unwrap();
+    kp
+}
+
+// ============== Fixtures ==============
+
+pub struct ProviderFixture {
+    pub authority: Keypair,
+    pub provider: Pubkey,
+    pub node: Pubkey,
+}
+
+// Program config + treasury, administered by the test payer
+pub async fn init_program(ctx: &mut ProgramTestContext) {
+    let admin = ctx.payer.pubkey();
+    let init_treasury = ix(
+        dvpn::accounts::InitializeTreasury {
+            authority: admin,
+            treasury: treasury_pda(),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::InitializeTreasury {},
+    );
+    let init_config = ix(
+        dvpn::accounts::InitializeProgramConfig {
+            admin,
+            treasury: treasury_pda(),
+            config: config_pda(),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::InitializeProgramConfig {},
+    );
+    send(ctx, &[init_treasury, init_config], &[]).await.unwrap();
+}
+
+// Registered, staked provider with one active node
+pub async fn setup_provider(ctx: &mut ProgramTestContext) -> ProviderFixture {
+    let authority = funded_keypair(ctx, 10 * LAMPORTS_PER_SOL).await;
+    let provider = provider_pda(&authority.pubkey());
+    let node = node_pda(&provider, NODE_ID);
+
+    let register = ix(
+        dvpn::accounts::RegisterProvider {
+            authority: authority.pubkey(),
+            provider,
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::RegisterProvider {},
+    );
+    let stake = ix(
+        dvpn::accounts::StakeProvider {
+            authority: authority.pubkey(),
+            provider,
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::StakeProvider { amount_lamports: PROVIDER_STAKE },
+    );
+    let register_node = ix(
+        dvpn::accounts::RegisterNode {
+            authority: authority.pubkey(),
+            provider,
+            node,
+            config: config_pda(),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::RegisterNode {
+            node_id: NODE_ID,
+            endpoint: "203.0.113.7:51820".to_string(),
+            region: "eu-west".to_string(),
+            price_per_minute_lamports: NODE_PRICE_PER_MINUTE,
+            wg_server_pubkey: [7u8; 32],
+            max_capacity: NODE_CAPACITY,
+            bandwidth_mbps: 100,
+        },
+    );
+    send(ctx, &[register, stake, register_node], &[&authority]).await.unwrap();
+
+    ProviderFixture { authority, provider, node }
+}
+
+pub async fn open_session(
+    ctx: &mut ProgramTestContext,
+    fixture: &ProviderFixture,
+    user: &Keypair,
+    session_id: u64,
+    minutes: u32,
+) -> Pubkey {
+    let session = session_pda(&user.pubkey(), &fixture.node, session_id);
+    let open = ix(
+        dvpn::accounts::OpenSession {
+            user: user.pubkey(),
+            provider: fixture.provider,
+            node: fixture.node,
+            session,
+            config: config_pda(),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::OpenSession { session_id, minutes },
+    );
+    send(ctx, &[open], &[user]).await.unwrap();
+    session
+}
+
+pub fn claim_payout_ix(fixture: &ProviderFixture, session: Pubkey) -> Instruction {
+    ix(
+        dvpn::accounts::ClaimPayout {
+            authority: fixture.authority.pubkey(),
+            provider: fixture.provider,
+            operator: None,
+            payout: fixture.authority.pubkey(),
+            node: fixture.node,
+            session,
+            treasury: treasury_pda(),
+            config: config_pda(),
+        },
+        dvpn::instruction::ClaimPayout {},
+    )
+}
+
+pub fn update_config_ix(admin: &Pubkey, param: dvpn::ConfigParam) -> Instruction {
+    ix(
+        dvpn::accounts::UpdateProgramConfig { admin: *admin, config: config_pda() },
+        dvpn::instruction::UpdateProgramConfig { param },
+    )
+}
+
+pub fn close_session_ix(user: &Pubkey, node: Pubkey, session: Pubkey) -> Instruction {
+    ix(
+        dvpn::accounts::CloseSession {
+            user: *user,
+            session,
+            node,
+            refund_to: *user,
+        },
+        dvpn::instruction::CloseSession {},
+    )
+}
+
+// Ed25519 precompile instruction carrying pubkey, signature and message inline,
+// the layout verify_ed25519_receipt expects
+pub fn ed25519_receipt_ix(signer: &Keypair, message: &[u8]) -> Instruction {
+    const HEADER: usize = 16;
+    let pubkey_offset = HEADER;
+    let signature_offset = pubkey_offset + 32;
+    let message_offset = signature_offset + 64;
+    let signature = signer.sign_message(message);
+
+    let mut data = vec![1u8, 0];
+    for value in [
+        signature_offset as u16,
+        u16::MAX,
+        pubkey_offset as u16,
+        u16::MAX,
+        message_offset as u16,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(signer.pubkey().as_ref());
+    data.extend_from_slice(signature.as_ref());
+    data.extend_from_slice(message);
+
+    Instruction { program_id: ed25519_program::ID, accounts: vec![], data }
+}
+
+// ============== SPL tokens ==============
+
+pub async fn create_mint(ctx: &mut ProgramTestContext) -> Pubkey {
+    let mint = Keypair::new();
+    let rent = ctx.banks_client.get_rent().await.unwrap();
+    let payer = ctx.payer.pubkey();
+    let ixs = [
+        system_instruction::create_account(
+            &payer,
+            &mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::ID,
+        ),
+        spl_token::instruction::initialize_mint(&spl_token::ID, &mint.pubkey(), &payer, None, 6).unwrap(),
+    ];
+    send(ctx, &ixs, &[&mint]).await.unwrap();
+    mint.pubkey()
+}
+
+pub async fn create_token_account(ctx: &mut ProgramTestContext, mint: &Pubkey, owner: &Pubkey) -> Pubkey {
+    let account = Keypair::new();
+    let rent = ctx.banks_client.get_rent().await.unwrap();
+    let payer = ctx.payer.pubkey();
+    let ixs = [
+        system_instruction::create_account(
+            &payer,
+            &account.pubkey(),
+            rent.minimum_balance(spl_token::state::Account::LEN),
+            spl_token::state::Account::LEN as u64,
+            &spl_token::ID,
+        ),
+        spl_token::instruction::initialize_account(&spl_token::ID, &account.pubkey(), mint, owner).unwrap(),
+    ];
+    send(ctx, &ixs, &[&account]).await.unwrap();
+    account.pubkey()
+}
+
+pub async fn mint_to(ctx: &mut ProgramTestContext, mint: &Pubkey, to: &Pubkey, amount: u64) {
+    let payer = ctx.payer.pubkey();
+    let mint_ix = spl_token::instruction::mint_to(&spl_token::ID, mint, to, &payer, &[], amount).unwrap();
+    send(ctx, &[mint_ix], &[]).await.unwrap();
+}
+
+pub async fn token_balance(ctx: &mut ProgramTestContext, account: &Pubkey) -> u64 {
+    let data = ctx.banks_client.get_account(*account).await.unwrap().expect("token account exists");
+    spl_token::state::Account::unpack(&data.data).unwrap().amount
+}
+
+pub const TOKEN_PRICE_PER_MINUTE: u64 = 1_000_000;
+pub const SPL_MINUTES: u32 = 10;
+pub const SPL_ESCROW: u64 = TOKEN_PRICE_PER_MINUTE * SPL_MINUTES as u64;
+
+pub struct SplSession {
+    pub provider: ProviderFixture,
+    pub mint: Pubkey,
+    pub user: Keypair,
+    pub user_token: Pubkey,
+    pub provider_token: Pubkey,
+    pub treasury_token: Pubkey,
+    pub session: Pubkey,
+    pub escrow: Pubkey,
+}
+
+// Staked provider whose node accepts a fresh mint, and a 10-minute session paid in it
+pub async fn open_spl_session(ctx: &mut ProgramTestContext) -> SplSession {
+    init_program(ctx).await;
+    let provider = setup_provider(ctx).await;
+    let mint = create_mint(ctx).await;
+    let node_pricing = node_pricing_pda(&provider.node);
+
+    let set_price = ix(
+        dvpn::accounts::SetAcceptedMint {
+            authority: provider.authority.pubkey(),
+            provider: provider.provider,
+            operator: None,
+            node: provider.node,
+            mint,
+            node_pricing,
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::SetAcceptedMint { price_per_minute_tokens: TOKEN_PRICE_PER_MINUTE },
+    );
+    send(ctx, &[set_price], &[&provider.authority]).await.unwrap();
+
+    let user = funded_keypair(ctx, LAMPORTS_PER_SOL).await;
+    let user_token = create_token_account(ctx, &mint, &user.pubkey()).await;
+    let provider_token = create_token_account(ctx, &mint, &provider.authority.pubkey()).await;
+    let treasury_token = create_token_account(ctx, &mint, &treasury_pda()).await;
+    mint_to(ctx, &mint, &user_token, SPL_ESCROW).await;
+
+    let session = session_pda(&user.pubkey(), &provider.node, 1);
+    let escrow = Keypair::new();
+    let open = ix(
+        dvpn::accounts::OpenSessionSpl {
+            user: user.pubkey(),
+            provider: provider.provider,
+            node: provider.node,
+            session,
+            mint,
+            node_pricing,
+            user_token_account: user_token,
+            session_token_account: escrow.pubkey(),
+            config: config_pda(),
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::OpenSessionSpl { session_id: 1, minutes: SPL_MINUTES },
+    );
+    send(ctx, &[open], &[&user, &escrow]).await.unwrap();
+
+    SplSession {
+        provider,
+        mint,
+        user,
+        user_token,
+        provider_token,
+        treasury_token,
+        session,
+        escrow: escrow.pubkey(),
+    }
+}
+
+// ============== Disputes ==============
+
+pub const RULING_TIMEOUT_SECONDS: i64 = 24 * 60 * 60;
+
+// Arbiter registry, set up and administered by the test payer as config admin
+pub async fn init_arbiter_registry(ctx: &mut ProgramTestContext, min_panel_size: u8) {
+    let init = ix(
+        dvpn::accounts::InitializeArbiterRegistry {
+            authority: ctx.payer.pubkey(),
+            config: config_pda(),
+            arbiter_registry: arbiter_registry_pda(),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::InitializeArbiterRegistry {
+            min_panel_size,
+            ruling_timeout_seconds: RULING_TIMEOUT_SECONDS,
+        },
+    );
+    send(ctx, &[init], &[]).await.unwrap();
+}
+
+pub fn raise_dispute_ix(fixture: &ProviderFixture, authority: &Pubkey, session: Pubkey) -> Instruction {
+    ix(
+        dvpn::accounts::RaiseDispute {
+            authority: *authority,
+            provider: fixture.provider,
+            node: fixture.node,
+            session,
+            arbiter_registry: arbiter_registry_pda(),
+            dispute: dispute_pda(&session),
+            config: config_pda(),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::RaiseDispute { reason: "no traffic routed".to_string() },
+    )
+}
+
+pub fn auto_resolve_dispute_ix(session: Pubkey, raiser: Pubkey, refund_to: Pubkey) -> Instruction {
+    ix(
+        dvpn::accounts::AutoResolveDispute {
+            session,
+            dispute: dispute_pda(&session),
+            raiser,
+            dispute_panel: dispute_panel_pda(&session),
+            user: refund_to,
+        },
+        dvpn::instruction::AutoResolveDispute {},
+    )
+}
+
+pub async fn init_insurance_fund(ctx: &mut ProgramTestContext) {
+    let init = ix(
+        dvpn::accounts::InitializeInsuranceFund {
+            authority: ctx.payer.pubkey(),
+            insurance_fund: insurance_fund_pda(),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::InitializeInsuranceFund {},
+    );
+    send(ctx, &[init], &[]).await.unwrap();
+}
+
+pub async fn add_arbiters(ctx: &mut ProgramTestContext, arbiters: &[Pubkey]) {
+    let adds: Vec<Instruction> = arbiters
+        .iter()
+        .map(|arbiter| {
+            ix(
+                dvpn::accounts::UpdateArbiterRegistry {
+                    authority: ctx.payer.pubkey(),
+                    arbiter_registry: arbiter_registry_pda(),
+                },
+                dvpn::instruction::AddArbiter { arbiter: *arbiter },
+            )
+        })
+        .collect();
+    send(ctx, &adds, &[]).await.unwrap();
+}
+
+pub fn assign_arbiters_ix(authority: &Pubkey, session: Pubkey, arbiters: Vec<Pubkey>) -> Instruction {
+    ix(
+        dvpn::accounts::AssignArbiters {
+            authority: *authority,
+            arbiter_registry: arbiter_registry_pda(),
+            session,
+            dispute_panel: dispute_panel_pda(&session),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::AssignArbiters { arbiters },
+    )
+}
+
+pub fn dispute_vote_ix(arbiter: &Pubkey, session: Pubkey, refund_to_user: u64, slash_amount: u64) -> Instruction {
+    ix(
+        dvpn::accounts::CastDisputeVote {
+            arbiter: *arbiter,
+            arbiter_registry: arbiter_registry_pda(),
+            session,
+            dispute_panel: dispute_panel_pda(&session),
+        },
+        dvpn::instruction::CastDisputeVote { refund_to_user, slash_amount },
+    )
+}
+
+// Anyone may resolve; `resolver` pays the slash record rent
+pub fn resolve_dispute_ix(
+    resolver: &Pubkey,
+    fixture: &ProviderFixture,
+    session: Pubkey,
+    raiser: Pubkey,
+    refund_to: Pubkey,
+) -> Instruction {
+    ix(
+        dvpn::accounts::ResolveDispute {
+            resolver: *resolver,
+            provider: fixture.provider,
+            node: fixture.node,
+            session,
+            dispute_panel: dispute_panel_pda(&session),
+            dispute: dispute_pda(&session),
+            raiser,
+            payout: fixture.authority.pubkey(),
+            user: refund_to,
+            treasury: treasury_pda(),
+            insurance_fund: insurance_fund_pda(),
+            config: config_pda(),
+            slash_record: slash_record_pda(&session),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::ResolveDispute {},
+    )
+}
+
+// ============== Multisig ==============
+
+pub const MULTISIG_TIMELOCK_SECONDS: i64 = dvpn::MIN_TIMELOCK_SECONDS;
+
+// Set up by the test payer as treasury authority, which stays on as earnings
+// reporter; withdrawals of any size skip the timelock
+pub async fn init_multisig(ctx: &mut ProgramTestContext, owners: &[Pubkey], threshold: u8) {
+    let init = ix(
+        dvpn::accounts::InitializeMultisig {
+            authority: ctx.payer.pubkey(),
+            treasury: treasury_pda(),
+            multisig: multisig_pda(),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::InitializeMultisig {
+            owners: owners.to_vec(),
+            threshold,
+            timelock_seconds: MULTISIG_TIMELOCK_SECONDS,
+            withdraw_timelock_threshold_lamports: u64::MAX,
+        },
+    );
+    send(ctx, &[init], &[]).await.unwrap();
+}
+
+pub fn create_multisig_proposal_ix(owner: &Pubkey, proposal_id: u64, action: MultisigAction) -> Instruction {
+    ix(
+        dvpn::accounts::CreateMultisigProposal {
+            owner: *owner,
+            multisig: multisig_pda(),
+            proposal: multisig_proposal_pda(proposal_id),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::CreateMultisigProposal { action },
+    )
+}
+
+pub fn approve_multisig_proposal_ix(owner: &Pubkey, proposal_id: u64) -> Instruction {
+    ix(
+        dvpn::accounts::ApproveMultisigProposal {
+            owner: *owner,
+            multisig: multisig_pda(),
+            proposal: multisig_proposal_pda(proposal_id),
+        },
+        dvpn::instruction::ApproveMultisigProposal {},
+    )
+}
+
+// Passes the treasury and config; governance and recipient only when given
+pub fn execute_multisig_proposal_ix(proposal_id: u64, governance: bool, recipient: Option<Pubkey>) -> Instruction {
+    ix(
+        dvpn::accounts::ExecuteMultisigProposal {
+            multisig: multisig_pda(),
+            proposal: multisig_proposal_pda(proposal_id),
+            treasury: Some(treasury_pda()),
+            config: Some(config_pda()),
+            earnings_pool: None,
+            governance: governance.then(governance_pda),
+            recipient,
+        },
+        dvpn::instruction::ExecuteMultisigProposal {},
+    )
+}
+
+// ============== Governance ==============
+
+pub const GOVERNANCE_PARAMS: GovernanceParams = GovernanceParams {
+    voting_period_seconds: dvpn::MIN_VOTING_PERIOD_SECONDS,
+    quorum_lamports: PROVIDER_STAKE,
+    approval_bps: 6_000,
+    min_proposal_stake_lamports: PROVIDER_STAKE / 10,
+};
+
+// Set up by the test payer as config admin
+pub async fn init_governance(ctx: &mut ProgramTestContext, params: GovernanceParams) {
+    let init = ix(
+        dvpn::accounts::InitializeGovernance {
+            admin: ctx.payer.pubkey(),
+            config: config_pda(),
+            governance: governance_pda(),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::InitializeGovernance { params },
+    );
+    send(ctx, &[init], &[]).await.unwrap();
+}
+
+pub fn create_governance_proposal_ix(
+    fixture: &ProviderFixture,
+    proposal_id: u64,
+    param: dvpn::ConfigParam,
+) -> Instruction {
+    ix(
+        dvpn::accounts::CreateGovernanceProposal {
+            authority: fixture.authority.pubkey(),
+            provider: fixture.provider,
+            governance: governance_pda(),
+            config: config_pda(),
+            proposal: governance_proposal_pda(proposal_id),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::CreateGovernanceProposal { param },
+    )
+}
+
+pub fn governance_vote_ix(fixture: &ProviderFixture, proposal_id: u64, approve: bool) -> Instruction {
+    let proposal = governance_proposal_pda(proposal_id);
+    ix(
+        dvpn::accounts::CastGovernanceVote {
+            authority: fixture.authority.pubkey(),
+            provider: fixture.provider,
+            proposal,
+            vote: governance_vote_pda(&proposal, &fixture.provider),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::CastGovernanceVote { approve },
+    )
+}
+
+pub fn execute_governance_proposal_ix(proposal_id: u64) -> Instruction {
+    ix(
+        dvpn::accounts::ExecuteGovernanceProposal {
+            governance: governance_pda(),
+            proposal: governance_proposal_pda(proposal_id),
+            config: config_pda(),
+        },
+        dvpn::instruction::ExecuteGovernanceProposal {},
+    )
+}
@@ -0,0 +1,298 @@
+mod common;
+
+use common::*;
+use dvpn::{ConfigParam, Dispute, DisputePanel, DvpnError, Session, SessionState};
+use anchor_spl::token::spl_token;
+use solana_program_test::ProgramTestContext;
+use solana_sdk::signature::{Keypair, Signer};
+
+const MINUTES: u32 = 10;
+
+#[tokio::test]
+async fn disputed_session_is_frozen_until_resolved() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    init_arbiter_registry(&mut ctx, 1).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &fixture, &user, 1, MINUTES).await;
+    let escrow = MINUTES as u64 * NODE_PRICE_PER_MINUTE;
+
+    advance(&mut ctx, (MINUTES as i64) * 30).await;
+    let raise = raise_dispute_ix(&fixture, &user.pubkey(), session);
+    send(&mut ctx, &[raise], &[&user]).await.unwrap();
+
+    advance(&mut ctx, (MINUTES as i64) * 30).await;
+    assert_dvpn_error(
+        send(&mut ctx, &[claim_payout_ix(&fixture, session)], &[&fixture.authority]).await,
+        DvpnError::SessionDisputed,
+    );
+    assert_dvpn_error(
+        send(&mut ctx, &[auto_resolve_dispute_ix(session, user.pubkey(), user.pubkey())], &[]).await,
+        DvpnError::DisputeDeadlineNotReached,
+    );
+
+    // No ruling in time: the user gets back the time after the dispute
+    advance(&mut ctx, RULING_TIMEOUT_SECONDS).await;
+    let dispute: Dispute = fetch(&mut ctx, &dispute_pda(&session)).await;
+    let before = lamports(&mut ctx, &user.pubkey()).await;
+    send(&mut ctx, &[auto_resolve_dispute_ix(session, user.pubkey(), user.pubkey())], &[]).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &user.pubkey()).await, before + escrow / 2 + dispute.bond_lamports);
+
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.state == SessionState::Resolved);
+    assert_eq!(state.remaining_balance, escrow / 2);
+
+    send(&mut ctx, &[claim_payout_ix(&fixture, session)], &[&fixture.authority]).await.unwrap();
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.state == SessionState::Claimed);
+}
+
+#[tokio::test]
+async fn auto_resolve_does_not_refund_a_closed_session_twice() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    init_arbiter_registry(&mut ctx, 1).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &fixture, &user, 1, MINUTES).await;
+    let escrow = MINUTES as u64 * NODE_PRICE_PER_MINUTE;
+
+    // Closing halfway refunds the unused half up front
+    advance(&mut ctx, (MINUTES as i64) * 30).await;
+    send(&mut ctx, &[close_session_ix(&user.pubkey(), fixture.node, session)], &[&user])
+        .await
+        .unwrap();
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert_eq!(state.remaining_balance, escrow / 2);
+    assert_eq!(state.refunded_amount, escrow / 2);
+
+    let raise = raise_dispute_ix(&fixture, &user.pubkey(), session);
+    send(&mut ctx, &[raise], &[&user]).await.unwrap();
+    advance(&mut ctx, RULING_TIMEOUT_SECONDS).await;
+
+    // The earned half stays with the provider; only the bond comes back
+    let dispute: Dispute = fetch(&mut ctx, &dispute_pda(&session)).await;
+    let before = lamports(&mut ctx, &user.pubkey()).await;
+    send(&mut ctx, &[auto_resolve_dispute_ix(session, user.pubkey(), user.pubkey())], &[]).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &user.pubkey()).await, before + dispute.bond_lamports);
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.state == SessionState::Resolved);
+    assert_eq!(state.remaining_balance, escrow / 2);
+
+    let payout_before = lamports(&mut ctx, &fixture.authority.pubkey()).await;
+    send(&mut ctx, &[claim_payout_ix(&fixture, session)], &[&fixture.authority]).await.unwrap();
+    assert!(lamports(&mut ctx, &fixture.authority.pubkey()).await > payout_before);
+}
+
+// Raise a dispute on a fresh session and have a one-arbiter panel rule on it.
+// Returns the session and the bond the raiser posted.
+async fn rule_on_dispute(
+    ctx: &mut ProgramTestContext,
+    fixture: &ProviderFixture,
+    user: &Keypair,
+    session_id: u64,
+    raised_by_user: bool,
+    arbiter: &Keypair,
+    refund_to_user: u64,
+) -> (solana_sdk::pubkey::Pubkey, u64) {
+    let session = open_session(ctx, fixture, user, session_id, MINUTES).await;
+    let raiser = if raised_by_user { user } else { &fixture.authority };
+    let raise = raise_dispute_ix(fixture, &raiser.pubkey(), session);
+    send(ctx, &[raise], &[raiser]).await.unwrap();
+
+    let payer = ctx.payer.pubkey();
+    let assign = assign_arbiters_ix(&payer, session, vec![arbiter.pubkey()]);
+    let vote = dispute_vote_ix(&arbiter.pubkey(), session, refund_to_user, 0);
+    send(ctx, &[assign, vote], &[arbiter]).await.unwrap();
+
+    let dispute: Dispute = fetch(ctx, &dispute_pda(&session)).await;
+    (session, dispute.bond_lamports)
+}
+
+#[tokio::test]
+async fn user_raiser_wins_or_forfeits_the_bond_by_ruling() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    init_insurance_fund(&mut ctx).await;
+    init_arbiter_registry(&mut ctx, 1).await;
+    let arbiter = Keypair::new();
+    add_arbiters(&mut ctx, &[arbiter.pubkey()]).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let escrow = MINUTES as u64 * NODE_PRICE_PER_MINUTE;
+    let payer = ctx.payer.pubkey();
+
+    // Ruled for the user: refund plus the bond back
+    let (session, bond) = rule_on_dispute(&mut ctx, &fixture, &user, 1, true, &arbiter, escrow / 2).await;
+    let user_before = lamports(&mut ctx, &user.pubkey()).await;
+    let resolve = resolve_dispute_ix(&payer, &fixture, session, user.pubkey(), user.pubkey());
+    send(&mut ctx, &[resolve], &[]).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &user.pubkey()).await - user_before, escrow / 2 + bond);
+
+    // Ruled for the provider: the bond is split between the provider and the treasury
+    let (session, bond) = rule_on_dispute(&mut ctx, &fixture, &user, 2, true, &arbiter, 0).await;
+    let user_before = lamports(&mut ctx, &user.pubkey()).await;
+    let payout_before = lamports(&mut ctx, &fixture.authority.pubkey()).await;
+    let treasury_before = lamports(&mut ctx, &treasury_pda()).await;
+    let resolve = resolve_dispute_ix(&payer, &fixture, session, user.pubkey(), user.pubkey());
+    send(&mut ctx, &[resolve], &[]).await.unwrap();
+    let to_winner = bond * dvpn::DEFAULT_BOND_WINNER_BPS as u64 / 10_000;
+    assert_eq!(lamports(&mut ctx, &user.pubkey()).await, user_before);
+    assert_eq!(lamports(&mut ctx, &fixture.authority.pubkey()).await - payout_before, to_winner);
+    assert_eq!(lamports(&mut ctx, &treasury_pda()).await - treasury_before, bond - to_winner);
+    let dispute: Dispute = fetch(&mut ctx, &dispute_pda(&session)).await;
+    assert_eq!(dispute.bond_lamports, 0);
+}
+
+#[tokio::test]
+async fn provider_raiser_wins_or_forfeits_the_bond_by_ruling() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    init_insurance_fund(&mut ctx).await;
+    init_arbiter_registry(&mut ctx, 1).await;
+    let arbiter = Keypair::new();
+    add_arbiters(&mut ctx, &[arbiter.pubkey()]).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let escrow = MINUTES as u64 * NODE_PRICE_PER_MINUTE;
+    let payer = ctx.payer.pubkey();
+    let raiser = fixture.authority.pubkey();
+
+    // No refund, no slash: the provider raised it and won, so the bond comes back
+    let (session, bond) = rule_on_dispute(&mut ctx, &fixture, &user, 1, false, &arbiter, 0).await;
+    let raiser_before = lamports(&mut ctx, &raiser).await;
+    let user_before = lamports(&mut ctx, &user.pubkey()).await;
+    let resolve = resolve_dispute_ix(&payer, &fixture, session, raiser, user.pubkey());
+    send(&mut ctx, &[resolve], &[]).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &raiser).await - raiser_before, bond);
+    assert_eq!(lamports(&mut ctx, &user.pubkey()).await, user_before);
+
+    // A refund rules against the provider: the user gets the winner's share of its bond
+    let (session, bond) = rule_on_dispute(&mut ctx, &fixture, &user, 2, false, &arbiter, escrow / 2).await;
+    let raiser_before = lamports(&mut ctx, &raiser).await;
+    let user_before = lamports(&mut ctx, &user.pubkey()).await;
+    let treasury_before = lamports(&mut ctx, &treasury_pda()).await;
+    let resolve = resolve_dispute_ix(&payer, &fixture, session, raiser, user.pubkey());
+    send(&mut ctx, &[resolve], &[]).await.unwrap();
+    let to_winner = bond * dvpn::DEFAULT_BOND_WINNER_BPS as u64 / 10_000;
+    assert_eq!(lamports(&mut ctx, &raiser).await, raiser_before);
+    assert_eq!(lamports(&mut ctx, &user.pubkey()).await - user_before, escrow / 2 + to_winner);
+    assert_eq!(lamports(&mut ctx, &treasury_pda()).await - treasury_before, bond - to_winner);
+}
+
+#[tokio::test]
+async fn only_the_config_admin_sets_up_the_arbiter_registry() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let squatter = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let init = ix(
+        dvpn::accounts::InitializeArbiterRegistry {
+            authority: squatter.pubkey(),
+            config: config_pda(),
+            arbiter_registry: arbiter_registry_pda(),
+            system_program: solana_sdk::system_program::ID,
+        },
+        dvpn::instruction::InitializeArbiterRegistry {
+            min_panel_size: 1,
+            ruling_timeout_seconds: RULING_TIMEOUT_SECONDS,
+        },
+    );
+    assert_dvpn_error(send(&mut ctx, &[init], &[&squatter]).await, DvpnError::Unauthorized);
+    init_arbiter_registry(&mut ctx, 1).await;
+}
+
+#[tokio::test]
+async fn panel_majority_decides_and_resolve_executes_it() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    init_insurance_fund(&mut ctx).await;
+    init_arbiter_registry(&mut ctx, 3).await;
+    let arbiters = [Keypair::new(), Keypair::new(), Keypair::new(), Keypair::new()];
+    let keys: Vec<_> = arbiters.iter().map(|a| a.pubkey()).collect();
+    add_arbiters(&mut ctx, &keys).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &fixture, &user, 1, MINUTES).await;
+    let escrow = MINUTES as u64 * NODE_PRICE_PER_MINUTE;
+    send(&mut ctx, &[raise_dispute_ix(&fixture, &user.pubkey(), session)], &[&user]).await.unwrap();
+
+    let payer = ctx.payer.pubkey();
+    assert_dvpn_error(
+        send(&mut ctx, &[assign_arbiters_ix(&payer, session, keys[..2].to_vec())], &[]).await,
+        DvpnError::InvalidPanel,
+    );
+    send(&mut ctx, &[assign_arbiters_ix(&payer, session, keys[..3].to_vec())], &[]).await.unwrap();
+    // Registered but not seated on this panel
+    assert_dvpn_error(
+        send(&mut ctx, &[dispute_vote_ix(&keys[3], session, escrow, 0)], &[&arbiters[3]]).await,
+        DvpnError::NotAnArbiter,
+    );
+
+    // Split 1-1: no ruling yet
+    let slash = PROVIDER_STAKE / 10;
+    send(&mut ctx, &[dispute_vote_ix(&keys[0], session, escrow, slash)], &[&arbiters[0]]).await.unwrap();
+    send(&mut ctx, &[dispute_vote_ix(&keys[1], session, 0, 0)], &[&arbiters[1]]).await.unwrap();
+    let resolve = resolve_dispute_ix(&payer, &fixture, session, user.pubkey(), user.pubkey());
+    assert_dvpn_error(send(&mut ctx, std::slice::from_ref(&resolve), &[]).await, DvpnError::DisputeUndecided);
+
+    // 2 of 3 agree
+    send(&mut ctx, &[dispute_vote_ix(&keys[2], session, escrow, slash)], &[&arbiters[2]]).await.unwrap();
+    let panel: DisputePanel = fetch(&mut ctx, &dispute_panel_pda(&session)).await;
+    assert!(panel.decided);
+
+    // The slash split is config; user + insurance can't exceed the whole
+    let split = |user_bps, insurance_bps| update_config_ix(&payer, ConfigParam::SlashSplit { user_bps, insurance_bps });
+    assert_dvpn_error(send(&mut ctx, &[split(8_000, 2_001)], &[]).await, DvpnError::ConfigOutOfRange);
+    send(&mut ctx, &[split(6_000, 1_000)], &[]).await.unwrap();
+
+    // Refund, the user's slash share and the bond back
+    let dispute: Dispute = fetch(&mut ctx, &dispute_pda(&session)).await;
+    let user_before = lamports(&mut ctx, &user.pubkey()).await;
+    send(&mut ctx, &[resolve], &[]).await.unwrap();
+    let to_user = slash * 6_000 / 10_000;
+    assert_eq!(lamports(&mut ctx, &user.pubkey()).await - user_before, escrow + to_user + dispute.bond_lamports);
+    let provider: dvpn::Provider = fetch(&mut ctx, &fixture.provider).await;
+    assert_eq!(provider.stake_lamports, PROVIDER_STAKE - slash);
+    let record: dvpn::SlashRecord = fetch(&mut ctx, &slash_record_pda(&session)).await;
+    assert_eq!(record.total_slashed, slash);
+    assert_eq!(record.to_user, to_user);
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.state == SessionState::Resolved);
+    assert_eq!(state.remaining_balance, 0);
+}
+
+#[tokio::test]
+async fn settled_spl_dispute_sweeps_dust_back_to_the_user() {
+    let mut ctx = start().await;
+    let s = open_spl_session(&mut ctx).await;
+    init_arbiter_registry(&mut ctx, 1).await;
+
+    let raise = raise_dispute_ix(&s.provider, &s.user.pubkey(), s.session);
+    send(&mut ctx, &[raise], &[&s.user]).await.unwrap();
+    // Tokens sent straight to the escrow sit outside the tracked balance
+    mint_to(&mut ctx, &s.mint, &s.escrow, 7).await;
+
+    let settle = ix(
+        dvpn::accounts::SettleDisputeSpl {
+            user: s.user.pubkey(),
+            provider_authority: s.provider.authority.pubkey(),
+            provider: s.provider.provider,
+            node: s.provider.node,
+            session: s.session,
+            dispute: dispute_pda(&s.session),
+            raiser: s.user.pubkey(),
+            session_token_account: s.escrow,
+            user_token_account: s.user_token,
+            token_program: spl_token::ID,
+        },
+        dvpn::instruction::SettleDisputeSpl { refund_to_user: SPL_ESCROW },
+    );
+    send(&mut ctx, &[settle], &[&s.user, &s.provider.authority]).await.unwrap();
+
+    assert!(!exists(&mut ctx, &s.escrow).await);
+    assert_eq!(token_balance(&mut ctx, &s.user_token).await, SPL_ESCROW + 7);
+    let state: Session = fetch(&mut ctx, &s.session).await;
+    assert!(state.state == SessionState::Resolved);
+    assert_eq!(state.remaining_balance, 0);
+}
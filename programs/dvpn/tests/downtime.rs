@@ -0,0 +1,184 @@
+mod common;
+
+use anchor_lang::AccountSerialize;
+use anchor_spl::token::spl_token;
+use common::*;
+use dvpn::{DvpnError, Node, Session, SessionState};
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solana_sdk::{account::Account, pubkey::Pubkey, signature::{Keypair, Signer}, system_program};
+
+const MINUTES: u32 = 10;
+
+async fn heartbeat(ctx: &mut ProgramTestContext, fixture: &ProviderFixture) {
+    let beat = ix(
+        dvpn::accounts::NodeHeartbeat {
+            authority: fixture.authority.pubkey(),
+            provider: fixture.provider,
+            operator: None,
+            node: fixture.node,
+        },
+        dvpn::instruction::NodeHeartbeat {},
+    );
+    send(ctx, &[beat], &[&fixture.authority]).await.unwrap();
+}
+
+async fn claim_downtime(
+    ctx: &mut ProgramTestContext,
+    node: Pubkey,
+    user: &Keypair,
+    session: Pubkey,
+) -> Result<(), BanksClientError> {
+    let claim = ix(
+        dvpn::accounts::ClaimDowntimeRefund {
+            user: user.pubkey(),
+            session,
+            node,
+            refund_to: user.pubkey(),
+            config: config_pda(),
+        },
+        dvpn::instruction::ClaimDowntimeRefund {},
+    );
+    send(ctx, &[claim], &[user]).await
+}
+
+#[tokio::test]
+async fn silent_node_refunds_time_since_last_heartbeat() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &fixture, &user, 1, MINUTES).await;
+    let escrow = MINUTES as u64 * NODE_PRICE_PER_MINUTE;
+
+    advance(&mut ctx, 2 * 60).await;
+    heartbeat(&mut ctx, &fixture).await;
+
+    advance(&mut ctx, 4 * 60).await;
+    assert_dvpn_error(
+        claim_downtime(&mut ctx, fixture.node, &user, session).await,
+        DvpnError::NodeStillAlive,
+    );
+
+    // Silent for 6 of the configured 5 minutes; served 2 of 10 minutes
+    advance(&mut ctx, 2 * 60).await;
+    let before = lamports(&mut ctx, &user.pubkey()).await;
+    claim_downtime(&mut ctx, fixture.node, &user, session).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &user.pubkey()).await - before, escrow * 8 / 10);
+
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.state == SessionState::Closed);
+    assert_eq!(state.remaining_balance, escrow * 2 / 10);
+}
+
+#[tokio::test]
+async fn silence_after_the_session_ended_is_not_downtime() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &fixture, &user, 1, MINUTES).await;
+
+    // Last heartbeat two minutes before the end; node shut down afterwards
+    advance(&mut ctx, 8 * 60).await;
+    heartbeat(&mut ctx, &fixture).await;
+    advance(&mut ctx, 60 * 60).await;
+
+    assert_dvpn_error(
+        claim_downtime(&mut ctx, fixture.node, &user, session).await,
+        DvpnError::NodeStillAlive,
+    );
+}
+
+#[tokio::test]
+async fn spl_downtime_refund_returns_tokens_and_closes_empty_escrow() {
+    let mut ctx = start().await;
+    let s = open_spl_session(&mut ctx).await;
+
+    // Node never heartbeats after registration
+    advance(&mut ctx, 6 * 60).await;
+    let claim = ix(
+        dvpn::accounts::ClaimDowntimeRefundSpl {
+            user: s.user.pubkey(),
+            session: s.session,
+            node: s.provider.node,
+            session_token_account: s.escrow,
+            user_token_account: s.user_token,
+            config: config_pda(),
+            token_program: spl_token::ID,
+        },
+        dvpn::instruction::ClaimDowntimeRefundSpl {},
+    );
+    send(&mut ctx, &[claim], &[&s.user]).await.unwrap();
+
+    assert_eq!(token_balance(&mut ctx, &s.user_token).await, SPL_ESCROW);
+    assert!(!exists(&mut ctx, &s.escrow).await);
+    let state: Session = fetch(&mut ctx, &s.session).await;
+    assert!(state.state == SessionState::Closed);
+    assert_eq!(state.remaining_balance, 0);
+}
+
+#[tokio::test]
+async fn heartbeat_timeout_is_configurable_within_range() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+
+    let admin = ctx.payer.pubkey();
+    let update = |seconds| update_config_ix(&admin, dvpn::ConfigParam::HeartbeatTimeoutSeconds(seconds));
+
+    assert_dvpn_error(send(&mut ctx, &[update(10)], &[]).await, DvpnError::ConfigOutOfRange);
+    send(&mut ctx, &[update(15 * 60)], &[]).await.unwrap();
+    let config: dvpn::ProgramConfig = fetch(&mut ctx, &config_pda()).await;
+    assert_eq!(config.heartbeat_timeout_seconds, 15 * 60);
+}
+
+#[tokio::test]
+async fn migrate_node_fills_fields_missing_from_the_legacy_layout() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+
+    // Rewrite the node in the pre-heartbeat layout with two open sessions
+    let mut node: Node = fetch(&mut ctx, &fixture.node).await;
+    node.active_sessions = 2;
+    let mut data = Vec::new();
+    node.try_serialize(&mut data).unwrap();
+    let appended = (8 + 8) + 4 + (32 + 8);
+    data.truncate(data.len() - appended);
+    data.resize(8 + Node::LEGACY_LAYOUT_SIZE, 0);
+    let rent = ctx.banks_client.get_rent().await.unwrap();
+    ctx.set_account(
+        &fixture.node,
+        &Account {
+            lamports: rent.minimum_balance(data.len()),
+            data,
+            owner: dvpn::ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+        .into(),
+    );
+
+    advance(&mut ctx, 60).await;
+    let migrate = ix(
+        dvpn::accounts::MigrateNode {
+            authority: fixture.authority.pubkey(),
+            provider: fixture.provider,
+            node: fixture.node,
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::MigrateNode { _node_id: NODE_ID },
+    );
+    send(&mut ctx, std::slice::from_ref(&migrate), &[&fixture.authority]).await.unwrap();
+
+    let account = ctx.banks_client.get_account(fixture.node).await.unwrap().unwrap();
+    assert_eq!(account.data.len(), 8 + Node::MAX_SIZE);
+    let migrated: Node = fetch(&mut ctx, &fixture.node).await;
+    assert_eq!(migrated.last_heartbeat_ts, now(&mut ctx).await);
+    assert_eq!(migrated.unsettled_sessions, 2);
+    assert_eq!(migrated.endpoint, node.endpoint);
+
+    assert_dvpn_error(
+        send(&mut ctx, &[migrate], &[&fixture.authority]).await,
+        DvpnError::AlreadyMigrated,
+    );
+}
@@ -0,0 +1,227 @@
+mod common;
+
+use common::*;
+use dvpn::{DvpnError, MultisigAction, EARNINGS_POOL_SEED, USAGE_RECORD_SEED};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Signer,
+    system_instruction, system_program,
+};
+
+const EPOCH: u64 = 1;
+const POOL_REVENUE: u64 = LAMPORTS_PER_SOL;
+const USAGE_SECONDS: u64 = 60 * 60;
+
+fn pool_pda() -> Pubkey {
+    Pubkey::find_program_address(&[EARNINGS_POOL_SEED, &EPOCH.to_le_bytes()], &dvpn::ID).0
+}
+
+fn usage_pda(node: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[USAGE_RECORD_SEED, node.as_ref(), &EPOCH.to_le_bytes()], &dvpn::ID).0
+}
+
+// `multisig` once it holds the treasury and `authority` is its earnings reporter
+fn init_pool_ix(authority: &Pubkey, multisig: Option<Pubkey>) -> Instruction {
+    ix(
+        dvpn::accounts::InitializeEarningsPool {
+            authority: *authority,
+            earnings_pool: pool_pda(),
+            config: config_pda(),
+            treasury: treasury_pda(),
+            multisig,
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::InitializeEarningsPool { epoch: EPOCH },
+    )
+}
+
+fn record_usage_ix(authority: &Pubkey, multisig: Option<Pubkey>, node: Pubkey) -> Instruction {
+    ix(
+        dvpn::accounts::RecordNodeUsage {
+            authority: *authority,
+            node,
+            earnings_pool: pool_pda(),
+            usage_record: usage_pda(&node),
+            treasury: treasury_pda(),
+            multisig,
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::RecordNodeUsage {
+            epoch: EPOCH,
+            duration_seconds: USAGE_SECONDS,
+            bytes_transferred: 1 << 30,
+            user_rating: 5,
+        },
+    )
+}
+
+fn add_revenue_ix(authority: &Pubkey, multisig: Option<Pubkey>) -> Instruction {
+    ix(
+        dvpn::accounts::AddToEarningsPool {
+            authority: *authority,
+            earnings_pool: pool_pda(),
+            treasury: treasury_pda(),
+            multisig,
+        },
+        dvpn::instruction::AddToEarningsPool { amount: POOL_REVENUE },
+    )
+}
+
+fn claim_ix(fixture: &ProviderFixture) -> Instruction {
+    ix(
+        dvpn::accounts::ClaimProportionalEarnings {
+            authority: fixture.authority.pubkey(),
+            provider: fixture.provider,
+            operator: None,
+            payout: fixture.authority.pubkey(),
+            node: fixture.node,
+            earnings_pool: pool_pda(),
+            usage_record: usage_pda(&fixture.node),
+            treasury: treasury_pda(),
+            config: config_pda(),
+        },
+        dvpn::instruction::ClaimProportionalEarnings { epoch: EPOCH },
+    )
+}
+
+fn execute_finalize_ix() -> Instruction {
+    ix(
+        dvpn::accounts::ExecuteMultisigProposal {
+            multisig: multisig_pda(),
+            proposal: multisig_proposal_pda(0),
+            treasury: Some(treasury_pda()),
+            config: None,
+            earnings_pool: Some(pool_pda()),
+            governance: None,
+            recipient: None,
+        },
+        dvpn::instruction::ExecuteMultisigProposal {},
+    )
+}
+
+#[tokio::test]
+async fn only_the_treasury_authority_writes_the_earnings_pool() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let outsider = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let admin = ctx.payer.pubkey();
+
+    assert_dvpn_error(
+        send(&mut ctx, &[init_pool_ix(&outsider.pubkey(), None)], &[&outsider]).await,
+        DvpnError::Unauthorized,
+    );
+    send(&mut ctx, &[init_pool_ix(&admin, None)], &[]).await.unwrap();
+
+    // A provider can't inflate its own usage or the pool's revenue
+    let provider_key = fixture.authority.pubkey();
+    assert_dvpn_error(
+        send(&mut ctx, &[record_usage_ix(&provider_key, None, fixture.node)], &[&fixture.authority]).await,
+        DvpnError::Unauthorized,
+    );
+    assert_dvpn_error(
+        send(&mut ctx, &[add_revenue_ix(&provider_key, None)], &[&fixture.authority]).await,
+        DvpnError::Unauthorized,
+    );
+
+    send(&mut ctx, &[record_usage_ix(&admin, None, fixture.node)], &[]).await.unwrap();
+    send(&mut ctx, &[add_revenue_ix(&admin, None)], &[]).await.unwrap();
+}
+
+#[tokio::test]
+async fn usage_record_pays_out_once() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let admin = ctx.payer.pubkey();
+
+    let fund = system_instruction::transfer(&admin, &pool_pda(), POOL_REVENUE);
+    send(
+        &mut ctx,
+        &[init_pool_ix(&admin, None), record_usage_ix(&admin, None, fixture.node), add_revenue_ix(&admin, None), fund],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let config: dvpn::ProgramConfig = fetch(&mut ctx, &config_pda()).await;
+    advance(&mut ctx, config.earnings_epoch_seconds).await;
+    // Only node in the epoch: its weighted score is the pool total
+    let finalize = ix(
+        dvpn::accounts::FinalizeEarningsPool {
+            authority: admin,
+            earnings_pool: pool_pda(),
+            treasury: treasury_pda(),
+        },
+        dvpn::instruction::FinalizeEarningsPool { total_weighted_score: USAGE_SECONDS as u128 },
+    );
+    send(&mut ctx, &[finalize], &[]).await.unwrap();
+
+    let before = lamports(&mut ctx, &treasury_pda()).await;
+    send(&mut ctx, &[claim_ix(&fixture)], &[&fixture.authority]).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &treasury_pda()).await - before, POOL_REVENUE / 5);
+
+    assert_dvpn_error(
+        send(&mut ctx, &[claim_ix(&fixture)], &[&fixture.authority]).await,
+        DvpnError::AlreadyClaimed,
+    );
+}
+
+#[tokio::test]
+async fn once_a_multisig_holds_the_treasury_only_it_finalizes_pools() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let reporter = ctx.payer.pubkey();
+    let a = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let b = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    init_multisig(&mut ctx, &[a.pubkey(), b.pubkey()], 2).await;
+    let treasury: dvpn::Treasury = fetch(&mut ctx, &treasury_pda()).await;
+    assert_eq!(treasury.authority, multisig_pda());
+
+    // The former authority still reports usage, through the multisig
+    let multisig = Some(multisig_pda());
+    let outsider = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    assert_dvpn_error(
+        send(&mut ctx, &[init_pool_ix(&outsider.pubkey(), multisig)], &[&outsider]).await,
+        DvpnError::Unauthorized,
+    );
+    let fund = system_instruction::transfer(&reporter, &pool_pda(), POOL_REVENUE);
+    send(
+        &mut ctx,
+        &[
+            init_pool_ix(&reporter, multisig),
+            record_usage_ix(&reporter, multisig, fixture.node),
+            add_revenue_ix(&reporter, multisig),
+            fund,
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let config: dvpn::ProgramConfig = fetch(&mut ctx, &config_pda()).await;
+    advance(&mut ctx, config.earnings_epoch_seconds).await;
+    let finalize = ix(
+        dvpn::accounts::FinalizeEarningsPool {
+            authority: reporter,
+            earnings_pool: pool_pda(),
+            treasury: treasury_pda(),
+        },
+        dvpn::instruction::FinalizeEarningsPool { total_weighted_score: USAGE_SECONDS as u128 },
+    );
+    assert_dvpn_error(send(&mut ctx, &[finalize], &[]).await, DvpnError::Unauthorized);
+
+    // Finalizing fixes everyone's share, so it waits out the timelock
+    let action = MultisigAction::FinalizeEarningsPool { pool: pool_pda(), total_weighted_score: USAGE_SECONDS as u128 };
+    let propose = create_multisig_proposal_ix(&a.pubkey(), 0, action);
+    send(&mut ctx, &[propose, approve_multisig_proposal_ix(&b.pubkey(), 0)], &[&a, &b]).await.unwrap();
+    assert_dvpn_error(send(&mut ctx, &[execute_finalize_ix()], &[]).await, DvpnError::TimelockNotElapsed);
+    advance(&mut ctx, MULTISIG_TIMELOCK_SECONDS).await;
+    send(&mut ctx, &[execute_finalize_ix()], &[]).await.unwrap();
+
+    let before = lamports(&mut ctx, &treasury_pda()).await;
+    send(&mut ctx, &[claim_ix(&fixture)], &[&fixture.authority]).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &treasury_pda()).await - before, POOL_REVENUE / 5);
+}
@@ -0,0 +1,194 @@
+mod common;
+
+use common::*;
+use dvpn::{
+    ConfigParam, DvpnError, Governance, GovernanceParams, GovernanceProposal, GovernanceVote, MultisigAction, ProgramConfig,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signer};
+
+fn set_config_governance_ix(admin: &Pubkey, governance: Pubkey) -> solana_sdk::instruction::Instruction {
+    ix(
+        dvpn::accounts::UpdateProgramConfig { admin: *admin, config: config_pda() },
+        dvpn::instruction::SetConfigGovernance { governance },
+    )
+}
+
+fn set_config_admin_ix(admin: &Pubkey, new_admin: Pubkey) -> solana_sdk::instruction::Instruction {
+    ix(
+        dvpn::accounts::UpdateProgramConfig { admin: *admin, config: config_pda() },
+        dvpn::instruction::SetConfigAdmin { new_admin },
+    )
+}
+
+#[tokio::test]
+async fn governance_and_multisig_both_write_config_under_a_multisig_admin() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    init_governance(&mut ctx, GOVERNANCE_PARAMS).await;
+    let a = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let b = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    init_multisig(&mut ctx, &[a.pubkey(), b.pubkey()], 2).await;
+
+    // Enable governance, then hand the admin role to the multisig
+    let admin = ctx.payer.pubkey();
+    let enable = set_config_governance_ix(&admin, governance_pda());
+    let handover = set_config_admin_ix(&admin, multisig_pda());
+    send(&mut ctx, &[enable, handover], &[]).await.unwrap();
+    assert_dvpn_error(
+        send(&mut ctx, &[update_config_ix(&admin, ConfigParam::TreasuryShareBps(0))], &[]).await,
+        DvpnError::Unauthorized,
+    );
+
+    // A passed provider proposal applies
+    advance(&mut ctx, 1).await;
+    let propose = create_governance_proposal_ix(&fixture, 0, ConfigParam::TreasuryShareBps(1_500));
+    let vote = governance_vote_ix(&fixture, 0, true);
+    send(&mut ctx, &[propose, vote], &[&fixture.authority]).await.unwrap();
+    advance(&mut ctx, GOVERNANCE_PARAMS.voting_period_seconds).await;
+    send(&mut ctx, &[execute_governance_proposal_ix(0)], &[]).await.unwrap();
+    let config: ProgramConfig = fetch(&mut ctx, &config_pda()).await;
+    assert_eq!(config.treasury_share_bps, 1_500);
+
+    // So does a multisig change, once approved and past its timelock
+    let update = create_multisig_proposal_ix(&a.pubkey(), 0, MultisigAction::UpdateConfig(ConfigParam::TreasuryShareBps(1_000)));
+    send(&mut ctx, &[update, approve_multisig_proposal_ix(&b.pubkey(), 0)], &[&a, &b]).await.unwrap();
+    advance(&mut ctx, MULTISIG_TIMELOCK_SECONDS).await;
+    send(&mut ctx, &[execute_multisig_proposal_ix(0, false, None)], &[]).await.unwrap();
+    let config: ProgramConfig = fetch(&mut ctx, &config_pda()).await;
+    assert_eq!(config.treasury_share_bps, 1_000);
+
+    // Governance parameters are also the multisig's to tune
+    let params = GovernanceParams { approval_bps: 7_000, ..GOVERNANCE_PARAMS };
+    let tune = create_multisig_proposal_ix(&a.pubkey(), 1, MultisigAction::SetGovernanceParams(params));
+    send(&mut ctx, &[tune, approve_multisig_proposal_ix(&b.pubkey(), 1)], &[&a, &b]).await.unwrap();
+    advance(&mut ctx, MULTISIG_TIMELOCK_SECONDS).await;
+    send(&mut ctx, &[execute_multisig_proposal_ix(1, true, None)], &[]).await.unwrap();
+    let governance: Governance = fetch(&mut ctx, &governance_pda()).await;
+    assert!(governance.params == params);
+
+    // The multisig can revoke governance; passed proposals then no longer apply
+    let revoke = create_multisig_proposal_ix(&a.pubkey(), 2, MultisigAction::SetConfigGovernance(Pubkey::default()));
+    send(&mut ctx, &[revoke, approve_multisig_proposal_ix(&b.pubkey(), 2)], &[&a, &b]).await.unwrap();
+    advance(&mut ctx, MULTISIG_TIMELOCK_SECONDS).await;
+    send(&mut ctx, &[execute_multisig_proposal_ix(2, false, None)], &[]).await.unwrap();
+
+    let propose = create_governance_proposal_ix(&fixture, 1, ConfigParam::TreasuryShareBps(500));
+    let vote = governance_vote_ix(&fixture, 1, true);
+    send(&mut ctx, &[propose, vote], &[&fixture.authority]).await.unwrap();
+    advance(&mut ctx, GOVERNANCE_PARAMS.voting_period_seconds).await;
+    assert_dvpn_error(
+        send(&mut ctx, &[execute_governance_proposal_ix(1)], &[]).await,
+        DvpnError::Unauthorized,
+    );
+    let config: ProgramConfig = fetch(&mut ctx, &config_pda()).await;
+    assert_eq!(config.treasury_share_bps, 1_000);
+}
+
+fn stake_ix(fixture: &ProviderFixture, amount_lamports: u64) -> solana_sdk::instruction::Instruction {
+    ix(
+        dvpn::accounts::StakeProvider {
+            authority: fixture.authority.pubkey(),
+            provider: fixture.provider,
+            system_program: solana_sdk::system_program::ID,
+        },
+        dvpn::instruction::StakeProvider { amount_lamports },
+    )
+}
+
+#[tokio::test]
+async fn votes_weigh_stake_held_at_the_snapshot() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let topped_up = setup_provider(&mut ctx).await;
+    let unstaking = setup_provider(&mut ctx).await;
+    init_governance(&mut ctx, GOVERNANCE_PARAMS).await;
+
+    advance(&mut ctx, 1).await;
+    let propose = create_governance_proposal_ix(&topped_up, 0, ConfigParam::TreasuryShareBps(1_500));
+    send(&mut ctx, &[propose], &[&topped_up.authority]).await.unwrap();
+    let proposal = governance_proposal_pda(0);
+
+    // A top-up after the snapshot still votes, with the pre-snapshot stake only
+    advance(&mut ctx, 1).await;
+    let vote = governance_vote_ix(&topped_up, 0, true);
+    send(&mut ctx, &[stake_ix(&topped_up, 2 * PROVIDER_STAKE), vote], &[&topped_up.authority]).await.unwrap();
+    let record: GovernanceVote = fetch(&mut ctx, &governance_vote_pda(&proposal, &topped_up.provider)).await;
+    assert_eq!(record.weight_lamports, PROVIDER_STAKE);
+
+    // Stake withdrawn since the snapshot doesn't count either
+    let unstake = ix(
+        dvpn::accounts::RequestUnstake {
+            authority: unstaking.authority.pubkey(),
+            provider: unstaking.provider,
+            config: config_pda(),
+        },
+        dvpn::instruction::RequestUnstake { amount_lamports: PROVIDER_STAKE / 2 },
+    );
+    let vote = governance_vote_ix(&unstaking, 0, false);
+    send(&mut ctx, &[unstake, vote], &[&unstaking.authority]).await.unwrap();
+    let record: GovernanceVote = fetch(&mut ctx, &governance_vote_pda(&proposal, &unstaking.provider)).await;
+    assert_eq!(record.weight_lamports, PROVIDER_STAKE / 2);
+
+    let state: GovernanceProposal = fetch(&mut ctx, &proposal).await;
+    assert_eq!(state.yes_lamports, PROVIDER_STAKE);
+    assert_eq!(state.no_lamports, PROVIDER_STAKE / 2);
+
+    // A provider with no stake at the snapshot has no vote
+    let latecomer = setup_provider(&mut ctx).await;
+    assert_dvpn_error(
+        send(&mut ctx, &[governance_vote_ix(&latecomer, 0, true)], &[&latecomer.authority]).await,
+        DvpnError::InsufficientStake,
+    );
+
+    advance(&mut ctx, GOVERNANCE_PARAMS.voting_period_seconds).await;
+    assert_dvpn_error(
+        send(&mut ctx, &[governance_vote_ix(&latecomer, 0, true)], &[&latecomer.authority]).await,
+        DvpnError::VotingClosed,
+    );
+}
+
+#[tokio::test]
+async fn proposals_need_quorum_and_approval_to_execute() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let yes = setup_provider(&mut ctx).await;
+    let no = setup_provider(&mut ctx).await;
+    let admin = ctx.payer.pubkey();
+    // Quorum is both providers' stake
+    let params = GovernanceParams { quorum_lamports: 2 * PROVIDER_STAKE, ..GOVERNANCE_PARAMS };
+    init_governance(&mut ctx, params).await;
+    send(&mut ctx, &[set_config_governance_ix(&admin, governance_pda())], &[]).await.unwrap();
+    advance(&mut ctx, 1).await;
+
+    // 0: one voter, below quorum
+    let propose = create_governance_proposal_ix(&yes, 0, ConfigParam::TreasuryShareBps(1_500));
+    send(&mut ctx, &[propose, governance_vote_ix(&yes, 0, true)], &[&yes.authority]).await.unwrap();
+    // 1: quorum reached, but 50% yes is short of the 60% approval
+    let propose = create_governance_proposal_ix(&yes, 1, ConfigParam::TreasuryShareBps(1_000));
+    send(&mut ctx, &[propose, governance_vote_ix(&yes, 1, true)], &[&yes.authority]).await.unwrap();
+    send(&mut ctx, &[governance_vote_ix(&no, 1, false)], &[&no.authority]).await.unwrap();
+    // 2: quorum and approval
+    let propose = create_governance_proposal_ix(&yes, 2, ConfigParam::TreasuryShareBps(500));
+    send(&mut ctx, &[propose, governance_vote_ix(&yes, 2, true)], &[&yes.authority]).await.unwrap();
+    send(&mut ctx, &[governance_vote_ix(&no, 2, true)], &[&no.authority]).await.unwrap();
+
+    assert_dvpn_error(
+        send(&mut ctx, &[execute_governance_proposal_ix(2)], &[]).await,
+        DvpnError::VotingStillOpen,
+    );
+    advance(&mut ctx, params.voting_period_seconds).await;
+    for proposal_id in [0, 1] {
+        assert_dvpn_error(
+            send(&mut ctx, &[execute_governance_proposal_ix(proposal_id)], &[]).await,
+            DvpnError::ProposalNotPassed,
+        );
+    }
+    send(&mut ctx, &[execute_governance_proposal_ix(2)], &[]).await.unwrap();
+    let config: ProgramConfig = fetch(&mut ctx, &config_pda()).await;
+    assert_eq!(config.treasury_share_bps, 500);
+    assert_dvpn_error(
+        send(&mut ctx, &[execute_governance_proposal_ix(2)], &[]).await,
+        DvpnError::ProposalAlreadyExecuted,
+    );
+}
@@ -0,0 +1,330 @@
+mod common;
+
+use anchor_lang::AccountSerialize;
+use common::*;
+use dvpn::{Dispute, DvpnError, Node, Session, SessionState};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{account::Account, hash::hash, pubkey::Pubkey, signature::{Keypair, Signer}};
+
+const MINUTES: u32 = 10;
+const CHAIN_LENGTH: u32 = 5;
+const PRICE_PER_LINK: u64 = 100_000;
+const CRANK_FEE: u64 = 20_000;
+
+// H0 = seed, Hi = sha256(Hi-1); the user commits to the tip H5
+fn build_chain() -> Vec<[u8; 32]> {
+    let mut chain = vec![[42u8; 32]];
+    for i in 0..CHAIN_LENGTH as usize {
+        chain.push(hash(&chain[i]).to_bytes());
+    }
+    chain
+}
+
+async fn open_hashchain(
+    ctx: &mut ProgramTestContext,
+    fixture: &ProviderFixture,
+    user: &Keypair,
+    chain: &[[u8; 32]],
+) -> Pubkey {
+    open_hashchain_at(ctx, fixture, user, chain, PRICE_PER_LINK).await
+}
+
+async fn open_hashchain_at(
+    ctx: &mut ProgramTestContext,
+    fixture: &ProviderFixture,
+    user: &Keypair,
+    chain: &[[u8; 32]],
+    price_per_link_lamports: u64,
+) -> Pubkey {
+    let session = session_pda(&user.pubkey(), &fixture.node, 1);
+    let open = ix(
+        dvpn::accounts::OpenHashchainSession {
+            user: user.pubkey(),
+            provider: fixture.provider,
+            node: fixture.node,
+            session,
+            config: config_pda(),
+            system_program: solana_sdk::system_program::ID,
+        },
+        dvpn::instruction::OpenHashchainSession {
+            session_id: 1,
+            minutes: MINUTES,
+            chain_tip: chain[CHAIN_LENGTH as usize],
+            chain_length: CHAIN_LENGTH,
+            price_per_link_lamports,
+        },
+    );
+    send(ctx, &[open], &[user]).await.unwrap();
+    session
+}
+
+async fn redeem(
+    ctx: &mut ProgramTestContext,
+    fixture: &ProviderFixture,
+    session: Pubkey,
+    preimage: [u8; 32],
+    links: u32,
+) -> Result<(), solana_program_test::BanksClientError> {
+    let claim = ix(
+        dvpn::accounts::ClaimHashchain {
+            authority: fixture.authority.pubkey(),
+            provider: fixture.provider,
+            operator: None,
+            payout: fixture.authority.pubkey(),
+            session,
+            node: fixture.node,
+            treasury: treasury_pda(),
+            config: config_pda(),
+        },
+        dvpn::instruction::ClaimHashchain { preimage, links },
+    );
+    send(ctx, &[claim], &[&fixture.authority]).await
+}
+
+fn refund_ix(user: &Pubkey, session: Pubkey) -> solana_sdk::instruction::Instruction {
+    ix(
+        dvpn::accounts::RefundHashchain { session, refund_to: *user, config: config_pda() },
+        dvpn::instruction::RefundHashchain {},
+    )
+}
+
+#[tokio::test]
+async fn links_stay_redeemable_after_close_until_the_grace_window_ends() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let chain = build_chain();
+    let session = open_hashchain(&mut ctx, &fixture, &user, &chain).await;
+    let escrow = MINUTES as u64 * NODE_PRICE_PER_MINUTE;
+
+    // Wrong preimage is rejected
+    assert_dvpn_error(
+        redeem(&mut ctx, &fixture, session, chain[2], 1).await,
+        DvpnError::InvalidHashchainProof,
+    );
+
+    let treasury_before = lamports(&mut ctx, &treasury_pda()).await;
+    redeem(&mut ctx, &fixture, session, chain[4], 1).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &treasury_pda()).await - treasury_before, PRICE_PER_LINK / 5);
+
+    // Closing refunds nothing up front: the provider may still redeem links it served
+    let user_before = lamports(&mut ctx, &user.pubkey()).await;
+    send(&mut ctx, &[close_session_ix(&user.pubkey(), fixture.node, session)], &[&user])
+        .await
+        .unwrap();
+    assert!(lamports(&mut ctx, &user.pubkey()).await <= user_before);
+    let closed_at = now(&mut ctx).await;
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.state == SessionState::Closed);
+    // The window runs from the close, not the originally booked end
+    assert!(state.end_ts <= closed_at);
+
+    redeem(&mut ctx, &fixture, session, chain[3], 1).await.unwrap();
+    let paid = 2 * PRICE_PER_LINK;
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert_eq!(state.hashchain_redeemed, 2);
+    assert_eq!(state.remaining_balance, escrow - paid);
+
+    // The rest stays escrowed, and unclaimable, until the window passes
+    assert_dvpn_error(
+        send(&mut ctx, &[refund_ix(&user.pubkey(), session)], &[]).await,
+        DvpnError::RedemptionWindowOpen,
+    );
+    assert_dvpn_error(
+        send(&mut ctx, &[claim_payout_ix(&fixture, session)], &[&fixture.authority]).await,
+        DvpnError::HashchainSession,
+    );
+
+    advance(&mut ctx, dvpn::DEFAULT_HASHCHAIN_GRACE_SECONDS).await;
+    assert_dvpn_error(
+        redeem(&mut ctx, &fixture, session, chain[2], 1).await,
+        DvpnError::SessionNotActive,
+    );
+
+    let user_before = lamports(&mut ctx, &user.pubkey()).await;
+    send(&mut ctx, &[refund_ix(&user.pubkey(), session)], &[]).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &user.pubkey()).await - user_before, escrow - paid);
+
+    let payout_before = lamports(&mut ctx, &fixture.authority.pubkey()).await;
+    send(&mut ctx, &[claim_payout_ix(&fixture, session)], &[&fixture.authority]).await.unwrap();
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.state == SessionState::Claimed);
+    assert_eq!(state.remaining_balance, 0);
+    let node: Node = fetch(&mut ctx, &fixture.node).await;
+    assert_eq!(node.unsettled_sessions, 0);
+    // Only the transaction fee left the payout wallet
+    assert!(lamports(&mut ctx, &fixture.authority.pubkey()).await <= payout_before);
+}
+
+#[tokio::test]
+async fn expiry_refunds_unredeemed_links_after_the_grace_window() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let chain = build_chain();
+    let session = open_hashchain(&mut ctx, &fixture, &user, &chain).await;
+    let escrow = MINUTES as u64 * NODE_PRICE_PER_MINUTE;
+
+    redeem(&mut ctx, &fixture, session, chain[4], 1).await.unwrap();
+    advance(&mut ctx, (MINUTES as i64) * 60).await;
+
+    // The crank fee is config, capped so it can't eat the escrow
+    let admin = ctx.payer.pubkey();
+    let crank_fee = |lamports| update_config_ix(&admin, dvpn::ConfigParam::ExpireCrankFeeLamports(lamports));
+    assert_dvpn_error(
+        send(&mut ctx, &[crank_fee(dvpn::MAX_EXPIRE_CRANK_FEE_LAMPORTS + 1)], &[]).await,
+        DvpnError::ConfigOutOfRange,
+    );
+    send(&mut ctx, &[crank_fee(CRANK_FEE)], &[]).await.unwrap();
+
+    let cranker = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let expire = ix(
+        dvpn::accounts::ExpireSession {
+            caller: cranker.pubkey(),
+            session,
+            node: fixture.node,
+            config: config_pda(),
+        },
+        dvpn::instruction::ExpireSession {},
+    );
+    send(&mut ctx, &[expire], &[&cranker]).await.unwrap();
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.state == SessionState::Expired);
+    assert_eq!(state.remaining_balance, escrow - PRICE_PER_LINK - CRANK_FEE);
+
+    // Still inside the window after expiry
+    redeem(&mut ctx, &fixture, session, chain[3], 1).await.unwrap();
+
+    advance(&mut ctx, dvpn::DEFAULT_HASHCHAIN_GRACE_SECONDS).await;
+    let user_before = lamports(&mut ctx, &user.pubkey()).await;
+    send(&mut ctx, &[refund_ix(&user.pubkey(), session)], &[]).await.unwrap();
+    let refunded = lamports(&mut ctx, &user.pubkey()).await - user_before;
+    assert_eq!(refunded, escrow - 2 * PRICE_PER_LINK - CRANK_FEE);
+
+    send(&mut ctx, &[claim_payout_ix(&fixture, session)], &[&fixture.authority]).await.unwrap();
+    let node: Node = fetch(&mut ctx, &fixture.node).await;
+    assert_eq!(node.unsettled_sessions, 0);
+}
+
+#[tokio::test]
+async fn resolved_dispute_returns_unredeemed_links_and_settles() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    init_arbiter_registry(&mut ctx, 1).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let chain = build_chain();
+    let session = open_hashchain(&mut ctx, &fixture, &user, &chain).await;
+    let escrow = MINUTES as u64 * NODE_PRICE_PER_MINUTE;
+
+    redeem(&mut ctx, &fixture, session, chain[4], 1).await.unwrap();
+    let raise = raise_dispute_ix(&fixture, &user.pubkey(), session);
+    send(&mut ctx, &[raise], &[&user]).await.unwrap();
+
+    advance(&mut ctx, RULING_TIMEOUT_SECONDS).await;
+    let dispute: Dispute = fetch(&mut ctx, &dispute_pda(&session)).await;
+    let user_before = lamports(&mut ctx, &user.pubkey()).await;
+    send(&mut ctx, &[auto_resolve_dispute_ix(session, user.pubkey(), user.pubkey())], &[]).await.unwrap();
+    assert_eq!(
+        lamports(&mut ctx, &user.pubkey()).await - user_before,
+        escrow - PRICE_PER_LINK + dispute.bond_lamports,
+    );
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.state == SessionState::Resolved);
+    assert_eq!(state.remaining_balance, 0);
+
+    send(&mut ctx, &[claim_payout_ix(&fixture, session)], &[&fixture.authority]).await.unwrap();
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.state == SessionState::Claimed);
+    let node: Node = fetch(&mut ctx, &fixture.node).await;
+    assert_eq!(node.unsettled_sessions, 0);
+}
+
+#[tokio::test]
+async fn expiry_crank_fee_never_touches_escrow_promised_to_links() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let chain = build_chain();
+    // Every lamport of escrow backs a link
+    let price = MINUTES as u64 * NODE_PRICE_PER_MINUTE / CHAIN_LENGTH as u64;
+    let session = open_hashchain_at(&mut ctx, &fixture, &user, &chain, price).await;
+
+    redeem(&mut ctx, &fixture, session, chain[4], 1).await.unwrap();
+    advance(&mut ctx, (MINUTES as i64) * 60).await;
+
+    let cranker = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let expire = ix(
+        dvpn::accounts::ExpireSession {
+            caller: cranker.pubkey(),
+            session,
+            node: fixture.node,
+            config: config_pda(),
+        },
+        dvpn::instruction::ExpireSession {},
+    );
+    send(&mut ctx, &[expire], &[&cranker]).await.unwrap();
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.state == SessionState::Expired);
+    assert_eq!(state.remaining_balance, (CHAIN_LENGTH as u64 - 1) * price);
+
+    // The last links still redeem in full inside the window
+    redeem(&mut ctx, &fixture, session, chain[0], CHAIN_LENGTH - 1).await.unwrap();
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert_eq!(state.hashchain_redeemed, CHAIN_LENGTH);
+}
+
+#[tokio::test]
+async fn legacy_sessions_migrate_to_the_current_layout() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &fixture, &user, 1, MINUTES).await;
+    let escrow = MINUTES as u64 * NODE_PRICE_PER_MINUTE;
+
+    // Rewrite it in the pre-upgrade layout
+    let state: Session = fetch(&mut ctx, &session).await;
+    let mut data = Vec::new();
+    state.try_serialize(&mut data).unwrap();
+    data.truncate(8 + Session::LEGACY_LAYOUT_SIZE);
+    let rent = ctx.banks_client.get_rent().await.unwrap();
+    ctx.set_account(
+        &session,
+        &Account {
+            lamports: rent.minimum_balance(data.len()) + escrow,
+            data,
+            owner: dvpn::ID,
+            executable: false,
+            rent_epoch: 0,
+        }
+        .into(),
+    );
+    let close = close_session_ix(&user.pubkey(), fixture.node, session);
+    assert!(send(&mut ctx, std::slice::from_ref(&close), &[&user]).await.is_err());
+
+    let payer = ctx.payer.pubkey();
+    let migrate = ix(
+        dvpn::accounts::MigrateSessionLayout {
+            payer,
+            session,
+            system_program: solana_sdk::system_program::ID,
+        },
+        dvpn::instruction::MigrateSessionLayout {},
+    );
+    send(&mut ctx, std::slice::from_ref(&migrate), &[]).await.unwrap();
+    let migrated: Session = fetch(&mut ctx, &session).await;
+    assert_eq!(migrated.refund_to, user.pubkey());
+    assert!(!migrated.capacity_released);
+    assert_eq!(migrated.price_per_link_lamports, 0);
+    assert_eq!(migrated.remaining_balance, escrow);
+    assert_dvpn_error(send(&mut ctx, &[migrate], &[]).await, DvpnError::AlreadyMigrated);
+
+    // The escrow is reachable again
+    let user_before = lamports(&mut ctx, &user.pubkey()).await;
+    send(&mut ctx, &[close], &[&user]).await.unwrap();
+    assert!(lamports(&mut ctx, &user.pubkey()).await > user_before);
+}
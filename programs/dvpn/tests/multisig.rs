@@ -0,0 +1,146 @@
+mod common;
+
+use common::*;
+use dvpn::{DvpnError, Multisig, MultisigAction};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::signature::{Keypair, Signer};
+
+async fn owners(ctx: &mut ProgramTestContext) -> [Keypair; 3] {
+    [
+        funded_keypair(ctx, LAMPORTS_PER_SOL).await,
+        funded_keypair(ctx, LAMPORTS_PER_SOL).await,
+        funded_keypair(ctx, LAMPORTS_PER_SOL).await,
+    ]
+}
+
+#[tokio::test]
+async fn only_the_treasury_authority_sets_up_config_and_multisig() {
+    let mut ctx = start().await;
+    let admin = ctx.payer.pubkey();
+    let init_treasury = ix(
+        dvpn::accounts::InitializeTreasury {
+            authority: admin,
+            treasury: treasury_pda(),
+            system_program: solana_sdk::system_program::ID,
+        },
+        dvpn::instruction::InitializeTreasury {},
+    );
+    send(&mut ctx, &[init_treasury], &[]).await.unwrap();
+
+    let squatter = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let squat_config = ix(
+        dvpn::accounts::InitializeProgramConfig {
+            admin: squatter.pubkey(),
+            treasury: treasury_pda(),
+            config: config_pda(),
+            system_program: solana_sdk::system_program::ID,
+        },
+        dvpn::instruction::InitializeProgramConfig {},
+    );
+    assert_dvpn_error(send(&mut ctx, &[squat_config], &[&squatter]).await, DvpnError::Unauthorized);
+
+    let squat_multisig = ix(
+        dvpn::accounts::InitializeMultisig {
+            authority: squatter.pubkey(),
+            treasury: treasury_pda(),
+            multisig: multisig_pda(),
+            system_program: solana_sdk::system_program::ID,
+        },
+        dvpn::instruction::InitializeMultisig {
+            owners: vec![squatter.pubkey()],
+            threshold: 1,
+            timelock_seconds: MULTISIG_TIMELOCK_SECONDS,
+            withdraw_timelock_threshold_lamports: 0,
+        },
+    );
+    assert_dvpn_error(send(&mut ctx, &[squat_multisig], &[&squatter]).await, DvpnError::Unauthorized);
+
+    init_multisig(&mut ctx, &[admin], 1).await;
+    assert!(exists(&mut ctx, &multisig_pda()).await);
+}
+
+#[tokio::test]
+async fn proposals_execute_at_threshold_and_after_the_timelock() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let [a, b, c] = owners(&mut ctx).await;
+    init_multisig(&mut ctx, &[a.pubkey(), b.pubkey(), c.pubkey()], 2).await;
+
+    // The treasury now answers only to the multisig
+    let admin = ctx.payer.pubkey();
+    let fund = solana_sdk::system_instruction::transfer(&admin, &treasury_pda(), LAMPORTS_PER_SOL);
+    let direct = ix(
+        dvpn::accounts::WithdrawTreasury { authority: admin, treasury: treasury_pda() },
+        dvpn::instruction::WithdrawTreasury { amount: 1 },
+    );
+    send(&mut ctx, &[fund], &[]).await.unwrap();
+    assert_dvpn_error(send(&mut ctx, &[direct], &[]).await, DvpnError::Unauthorized);
+
+    // Withdrawals under the timelock threshold run as soon as they have 2 of 3
+    let recipient = Keypair::new().pubkey();
+    let amount = LAMPORTS_PER_SOL / 2;
+    let withdraw = MultisigAction::WithdrawTreasury { recipient, amount };
+    send(&mut ctx, &[create_multisig_proposal_ix(&a.pubkey(), 0, withdraw)], &[&a]).await.unwrap();
+    assert_dvpn_error(
+        send(&mut ctx, &[execute_multisig_proposal_ix(0, false, Some(recipient))], &[]).await,
+        DvpnError::NotEnoughApprovals,
+    );
+    assert_dvpn_error(
+        send(&mut ctx, &[approve_multisig_proposal_ix(&a.pubkey(), 0)], &[&a]).await,
+        DvpnError::AlreadyApproved,
+    );
+    let outsider = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    assert_dvpn_error(
+        send(&mut ctx, &[approve_multisig_proposal_ix(&outsider.pubkey(), 0)], &[&outsider]).await,
+        DvpnError::NotMultisigOwner,
+    );
+    send(&mut ctx, &[approve_multisig_proposal_ix(&b.pubkey(), 0)], &[&b]).await.unwrap();
+    send(&mut ctx, &[execute_multisig_proposal_ix(0, false, Some(recipient))], &[]).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &recipient).await, amount);
+    assert_dvpn_error(
+        send(&mut ctx, &[execute_multisig_proposal_ix(0, false, Some(recipient))], &[]).await,
+        DvpnError::ProposalAlreadyExecuted,
+    );
+
+    // Owner-set changes wait out the timelock after reaching threshold
+    let raise = create_multisig_proposal_ix(&a.pubkey(), 1, MultisigAction::SetThreshold(3));
+    send(&mut ctx, &[raise, approve_multisig_proposal_ix(&c.pubkey(), 1)], &[&a, &c]).await.unwrap();
+    assert_dvpn_error(
+        send(&mut ctx, &[execute_multisig_proposal_ix(1, false, None)], &[]).await,
+        DvpnError::TimelockNotElapsed,
+    );
+    advance(&mut ctx, MULTISIG_TIMELOCK_SECONDS).await;
+    send(&mut ctx, &[execute_multisig_proposal_ix(1, false, None)], &[]).await.unwrap();
+    let multisig: Multisig = fetch(&mut ctx, &multisig_pda()).await;
+    assert_eq!(multisig.threshold, 3);
+}
+
+#[tokio::test]
+async fn approvals_from_removed_owners_stop_counting() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let [a, b, c] = owners(&mut ctx).await;
+    init_multisig(&mut ctx, &[a.pubkey(), b.pubkey(), c.pubkey()], 2).await;
+
+    // 0: approved by c and a; 1: removes c
+    let timelock = 2 * MULTISIG_TIMELOCK_SECONDS;
+    let retime = create_multisig_proposal_ix(&c.pubkey(), 0, MultisigAction::SetTimelock(timelock));
+    send(&mut ctx, &[retime, approve_multisig_proposal_ix(&a.pubkey(), 0)], &[&c, &a]).await.unwrap();
+    let remove = create_multisig_proposal_ix(&a.pubkey(), 1, MultisigAction::RemoveOwner(c.pubkey()));
+    send(&mut ctx, &[remove, approve_multisig_proposal_ix(&b.pubkey(), 1)], &[&a, &b]).await.unwrap();
+
+    advance(&mut ctx, MULTISIG_TIMELOCK_SECONDS).await;
+    send(&mut ctx, &[execute_multisig_proposal_ix(1, false, None)], &[]).await.unwrap();
+    let multisig: Multisig = fetch(&mut ctx, &multisig_pda()).await;
+    assert_eq!(multisig.owners, vec![a.pubkey(), b.pubkey()]);
+
+    // Only a's approval still counts on proposal 0
+    assert_dvpn_error(
+        send(&mut ctx, &[execute_multisig_proposal_ix(0, false, None)], &[]).await,
+        DvpnError::NotEnoughApprovals,
+    );
+    send(&mut ctx, &[approve_multisig_proposal_ix(&b.pubkey(), 0)], &[&b]).await.unwrap();
+    send(&mut ctx, &[execute_multisig_proposal_ix(0, false, None)], &[]).await.unwrap();
+    let multisig: Multisig = fetch(&mut ctx, &multisig_pda()).await;
+    assert_eq!(multisig.timelock_seconds, timelock);
+}
@@ -0,0 +1,161 @@
+mod common;
+
+use common::*;
+use dvpn::{DvpnError, Node, Session};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Signer,
+    system_program,
+};
+
+const MINUTES: u32 = 10;
+
+#[derive(Default)]
+struct NodeUpdate {
+    endpoint: Option<String>,
+    price_per_minute_lamports: Option<u64>,
+    is_active: Option<bool>,
+}
+
+fn update_node_ix(authority: &Pubkey, fixture: &ProviderFixture, update: NodeUpdate) -> Instruction {
+    ix(
+        dvpn::accounts::UpdateNode {
+            authority: *authority,
+            provider: fixture.provider,
+            operator: None,
+            node: fixture.node,
+            config: config_pda(),
+        },
+        dvpn::instruction::UpdateNode {
+            endpoint: update.endpoint,
+            region: None,
+            price_per_minute_lamports: update.price_per_minute_lamports,
+            max_capacity: None,
+            bandwidth_mbps: None,
+            is_active: update.is_active,
+        },
+    )
+}
+
+fn rotate_wg_key_ix(fixture: &ProviderFixture, next_wg_server_pubkey: [u8; 32], activation_ts: i64) -> Instruction {
+    ix(
+        dvpn::accounts::UpdateNode {
+            authority: fixture.authority.pubkey(),
+            provider: fixture.provider,
+            operator: None,
+            node: fixture.node,
+            config: config_pda(),
+        },
+        dvpn::instruction::RotateWgKey { next_wg_server_pubkey, activation_ts },
+    )
+}
+
+fn open_session_ix(user: &Pubkey, fixture: &ProviderFixture, session_id: u64) -> Instruction {
+    ix(
+        dvpn::accounts::OpenSession {
+            user: *user,
+            provider: fixture.provider,
+            node: fixture.node,
+            session: session_pda(user, &fixture.node, session_id),
+            config: config_pda(),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::OpenSession { session_id, minutes: MINUTES },
+    )
+}
+
+#[tokio::test]
+async fn node_updates_apply_to_new_sessions_only() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let authority = fixture.authority.pubkey();
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let open = open_session(&mut ctx, &fixture, &user, 1, MINUTES).await;
+
+    let outsider = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let reprice = NodeUpdate { price_per_minute_lamports: Some(2 * NODE_PRICE_PER_MINUTE), ..Default::default() };
+    assert_dvpn_error(
+        send(&mut ctx, &[update_node_ix(&outsider.pubkey(), &fixture, reprice)], &[&outsider]).await,
+        DvpnError::Unauthorized,
+    );
+    let too_long = NodeUpdate { endpoint: Some("x".repeat(1024)), ..Default::default() };
+    assert_dvpn_error(
+        send(&mut ctx, &[update_node_ix(&authority, &fixture, too_long)], &[&fixture.authority]).await,
+        DvpnError::StringTooLong,
+    );
+
+    let moved = NodeUpdate {
+        endpoint: Some("198.51.100.4:51820".to_string()),
+        price_per_minute_lamports: Some(2 * NODE_PRICE_PER_MINUTE),
+        ..Default::default()
+    };
+    send(&mut ctx, &[update_node_ix(&authority, &fixture, moved)], &[&fixture.authority]).await.unwrap();
+    let node: Node = fetch(&mut ctx, &fixture.node).await;
+    assert_eq!(node.endpoint, "198.51.100.4:51820");
+    assert_eq!(node.price_per_minute_lamports, 2 * NODE_PRICE_PER_MINUTE);
+
+    // The open session keeps its escrow; a new one pays the new price
+    let state: Session = fetch(&mut ctx, &open).await;
+    assert_eq!(state.escrow_lamports, MINUTES as u64 * NODE_PRICE_PER_MINUTE);
+    let repriced = open_session(&mut ctx, &fixture, &user, 2, MINUTES).await;
+    let state: Session = fetch(&mut ctx, &repriced).await;
+    assert_eq!(state.escrow_lamports, 2 * MINUTES as u64 * NODE_PRICE_PER_MINUTE);
+}
+
+#[tokio::test]
+async fn a_paused_node_refuses_new_sessions_until_resumed() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let authority = fixture.authority.pubkey();
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let open = open_session(&mut ctx, &fixture, &user, 1, MINUTES).await;
+
+    let pause = NodeUpdate { is_active: Some(false), ..Default::default() };
+    send(&mut ctx, &[update_node_ix(&authority, &fixture, pause)], &[&fixture.authority]).await.unwrap();
+    assert_dvpn_error(
+        send(&mut ctx, &[open_session_ix(&user.pubkey(), &fixture, 2)], &[&user]).await,
+        DvpnError::NodeInactive,
+    );
+    // Sessions already open still close normally
+    send(&mut ctx, &[close_session_ix(&user.pubkey(), fixture.node, open)], &[&user]).await.unwrap();
+
+    let resume = NodeUpdate { is_active: Some(true), ..Default::default() };
+    send(&mut ctx, &[update_node_ix(&authority, &fixture, resume)], &[&fixture.authority]).await.unwrap();
+    send(&mut ctx, &[open_session_ix(&user.pubkey(), &fixture, 2)], &[&user]).await.unwrap();
+}
+
+#[tokio::test]
+async fn rotated_wg_key_takes_over_after_the_overlap_window() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let (old_key, new_key) = ([7u8; 32], [9u8; 32]);
+    let activation_ts = now(&mut ctx).await + dvpn::MIN_WG_KEY_OVERLAP_SECONDS;
+
+    assert_dvpn_error(
+        send(&mut ctx, &[rotate_wg_key_ix(&fixture, new_key, activation_ts - 1)], &[&fixture.authority]).await,
+        DvpnError::KeyOverlapTooShort,
+    );
+    send(&mut ctx, &[rotate_wg_key_ix(&fixture, new_key, activation_ts)], &[&fixture.authority]).await.unwrap();
+
+    // During the overlap clients see both keys; sessions still use the old one
+    let node: Node = fetch(&mut ctx, &fixture.node).await;
+    assert_eq!(node.wg_server_pubkey, old_key);
+    assert_eq!(node.next_wg_server_pubkey, new_key);
+    assert_eq!(node.next_wg_key_activation_ts, activation_ts);
+    let before = open_session(&mut ctx, &fixture, &user, 1, MINUTES).await;
+    let state: Session = fetch(&mut ctx, &before).await;
+    assert_eq!(state.wg_server_pubkey, old_key);
+
+    set_time(&mut ctx, activation_ts).await;
+    let after = open_session(&mut ctx, &fixture, &user, 2, MINUTES).await;
+    let state: Session = fetch(&mut ctx, &after).await;
+    assert_eq!(state.wg_server_pubkey, new_key);
+    let node: Node = fetch(&mut ctx, &fixture.node).await;
+    assert_eq!(node.wg_server_pubkey, new_key);
+    assert_eq!(node.next_wg_key_activation_ts, 0);
+}
@@ -0,0 +1,225 @@
+mod common;
+
+use common::*;
+use dvpn::{
+    DvpnError, Session, SessionState, OPERATOR_PERMISSION_CLAIM, OPERATOR_PERMISSION_HEARTBEAT, OPERATOR_SEED,
+};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Signer,
+    system_program,
+};
+
+const MINUTES: u32 = 10;
+
+fn operator_pda(provider: &Pubkey, key: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[OPERATOR_SEED, provider.as_ref(), key.as_ref()], &dvpn::ID).0
+}
+
+async fn add_operator(ctx: &mut ProgramTestContext, fixture: &ProviderFixture, key: &Pubkey, permissions: u8) {
+    let add = ix(
+        dvpn::accounts::AddOperator {
+            authority: fixture.authority.pubkey(),
+            provider: fixture.provider,
+            operator: operator_pda(&fixture.provider, key),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::AddOperator { operator_key: *key, permissions },
+    );
+    send(ctx, &[add], &[&fixture.authority]).await.unwrap();
+}
+
+fn claim_as(
+    signer: &Pubkey,
+    provider: Pubkey,
+    operator: Option<Pubkey>,
+    payout: Pubkey,
+    node: Pubkey,
+    session: Pubkey,
+) -> Instruction {
+    ix(
+        dvpn::accounts::ClaimPayout {
+            authority: *signer,
+            provider,
+            operator,
+            payout,
+            node,
+            session,
+            treasury: treasury_pda(),
+            config: config_pda(),
+        },
+        dvpn::instruction::ClaimPayout {},
+    )
+}
+
+#[tokio::test]
+async fn operator_claims_pay_the_payout_address() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &fixture, &user, 1, MINUTES).await;
+    let escrow = MINUTES as u64 * NODE_PRICE_PER_MINUTE;
+
+    let payout = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await.pubkey();
+    let set_payout = ix(
+        dvpn::accounts::SetPayoutAddress {
+            authority: fixture.authority.pubkey(),
+            provider: fixture.provider,
+        },
+        dvpn::instruction::SetPayoutAddress { payout_address: payout },
+    );
+    send(&mut ctx, &[set_payout], &[&fixture.authority]).await.unwrap();
+
+    let heartbeat_only = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let claimer = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    add_operator(&mut ctx, &fixture, &heartbeat_only.pubkey(), OPERATOR_PERMISSION_HEARTBEAT).await;
+    add_operator(&mut ctx, &fixture, &claimer.pubkey(), OPERATOR_PERMISSION_CLAIM).await;
+    advance(&mut ctx, (MINUTES as i64) * 60).await;
+
+    // Payouts only go to the configured address
+    let to_operator = claim_as(
+        &claimer.pubkey(),
+        fixture.provider,
+        Some(operator_pda(&fixture.provider, &claimer.pubkey())),
+        claimer.pubkey(),
+        fixture.node,
+        session,
+    );
+    assert_dvpn_error(send(&mut ctx, &[to_operator], &[&claimer]).await, DvpnError::Unauthorized);
+
+    // An operator key without the claim permission cannot claim
+    let unscoped = claim_as(
+        &heartbeat_only.pubkey(),
+        fixture.provider,
+        Some(operator_pda(&fixture.provider, &heartbeat_only.pubkey())),
+        payout,
+        fixture.node,
+        session,
+    );
+    assert_dvpn_error(send(&mut ctx, &[unscoped], &[&heartbeat_only]).await, DvpnError::Unauthorized);
+
+    let claim = claim_as(
+        &claimer.pubkey(),
+        fixture.provider,
+        Some(operator_pda(&fixture.provider, &claimer.pubkey())),
+        payout,
+        fixture.node,
+        session,
+    );
+    send(&mut ctx, &[claim], &[&claimer]).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &payout).await, LAMPORTS_PER_SOL + escrow * 4 / 5);
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.state == SessionState::Claimed);
+}
+
+#[tokio::test]
+async fn another_provider_cannot_claim_a_session() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let adversary = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &fixture, &user, 1, MINUTES).await;
+    advance(&mut ctx, (MINUTES as i64) * 60).await;
+
+    let attacker = adversary.authority.pubkey();
+    // Victim's node and session, paid to the adversary
+    let victim_node = claim_as(&attacker, adversary.provider, None, attacker, fixture.node, session);
+    assert_dvpn_error(
+        send(&mut ctx, &[victim_node], &[&adversary.authority]).await,
+        DvpnError::Unauthorized,
+    );
+    // Adversary's own node: not the session's node
+    let own_node = claim_as(&attacker, adversary.provider, None, attacker, adversary.node, session);
+    assert_dvpn_error(
+        send(&mut ctx, &[own_node], &[&adversary.authority]).await,
+        DvpnError::Unauthorized,
+    );
+
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.state == SessionState::Active);
+    assert_eq!(state.remaining_balance, MINUTES as u64 * NODE_PRICE_PER_MINUTE);
+}
+
+#[tokio::test]
+async fn closing_reclaims_pricing_and_waits_for_operators_to_be_removed() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let authority = fixture.authority.pubkey();
+    let node_pricing = node_pricing_pda(&fixture.node);
+    let mint = create_mint(&mut ctx).await;
+    let set_price = ix(
+        dvpn::accounts::SetAcceptedMint {
+            authority,
+            provider: fixture.provider,
+            operator: None,
+            node: fixture.node,
+            mint,
+            node_pricing,
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::SetAcceptedMint { price_per_minute_tokens: 1 },
+    );
+    send(&mut ctx, &[set_price], &[&fixture.authority]).await.unwrap();
+
+    // The pricing PDA can't be left out; it closes along with the node
+    let close_node = |node_pricing| {
+        ix(
+            dvpn::accounts::CloseNode { authority, provider: fixture.provider, node: fixture.node, node_pricing },
+            dvpn::instruction::CloseNode {},
+        )
+    };
+    assert!(send(&mut ctx, &[close_node(Pubkey::new_unique())], &[&fixture.authority]).await.is_err());
+    let rent = lamports(&mut ctx, &fixture.node).await + lamports(&mut ctx, &node_pricing).await;
+    let before = lamports(&mut ctx, &authority).await;
+    send(&mut ctx, &[close_node(node_pricing)], &[&fixture.authority]).await.unwrap();
+    assert!(!exists(&mut ctx, &fixture.node).await);
+    assert!(!exists(&mut ctx, &node_pricing).await);
+    assert_eq!(lamports(&mut ctx, &authority).await, before + rent);
+
+    let unstake = ix(
+        dvpn::accounts::RequestUnstake { authority, provider: fixture.provider, config: config_pda() },
+        dvpn::instruction::RequestUnstake { amount_lamports: PROVIDER_STAKE },
+    );
+    send(&mut ctx, &[unstake], &[&fixture.authority]).await.unwrap();
+    advance(&mut ctx, dvpn::DEFAULT_UNBONDING_PERIOD_SECONDS).await;
+    let withdraw = ix(
+        dvpn::accounts::WithdrawUnstaked { authority, provider: fixture.provider },
+        dvpn::instruction::WithdrawUnstaked {},
+    );
+    send(&mut ctx, &[withdraw], &[&fixture.authority]).await.unwrap();
+
+    // An open operator key would be orphaned, so the provider stays open
+    let operator = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await.pubkey();
+    add_operator(&mut ctx, &fixture, &operator, OPERATOR_PERMISSION_HEARTBEAT).await;
+    let close_provider = || {
+        ix(
+            dvpn::accounts::CloseProvider {
+                authority,
+                provider: fixture.provider,
+                profile: provider_profile_pda(&fixture.provider),
+            },
+            dvpn::instruction::CloseProvider {},
+        )
+    };
+    assert_dvpn_error(
+        send(&mut ctx, &[close_provider()], &[&fixture.authority]).await,
+        DvpnError::ProviderHasOperators,
+    );
+
+    let remove = ix(
+        dvpn::accounts::RemoveOperator {
+            authority,
+            provider: fixture.provider,
+            operator: operator_pda(&fixture.provider, &operator),
+        },
+        dvpn::instruction::RemoveOperator {},
+    );
+    send(&mut ctx, &[remove, close_provider()], &[&fixture.authority]).await.unwrap();
+    assert!(!exists(&mut ctx, &fixture.provider).await);
+    assert!(!exists(&mut ctx, &operator_pda(&fixture.provider, &operator)).await);
+}
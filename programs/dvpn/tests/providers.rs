@@ -0,0 +1,151 @@
+mod common;
+
+use common::*;
+use dvpn::{DvpnError, Provider, ProviderProfile, Session, SessionState};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Signer,
+    system_program,
+};
+
+const MINUTES: u32 = 10;
+
+fn propose_transfer_ix(authority: &Pubkey, provider: Pubkey, new_authority: Pubkey) -> Instruction {
+    ix(
+        dvpn::accounts::ProposeAuthorityTransfer { authority: *authority, provider },
+        dvpn::instruction::ProposeAuthorityTransfer { new_authority },
+    )
+}
+
+fn accept_transfer_ix(new_authority: &Pubkey, provider: Pubkey) -> Instruction {
+    ix(
+        dvpn::accounts::AcceptAuthorityTransfer { new_authority: *new_authority, provider },
+        dvpn::instruction::AcceptAuthorityTransfer {},
+    )
+}
+
+fn create_profile_ix(fixture: &ProviderFixture, display_name: &str) -> Instruction {
+    ix(
+        dvpn::accounts::CreateProviderProfile {
+            authority: fixture.authority.pubkey(),
+            provider: fixture.provider,
+            profile: provider_profile_pda(&fixture.provider),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::CreateProviderProfile {
+            display_name: display_name.to_string(),
+            website: "https://vpn.example".to_string(),
+            jurisdiction: "IS".to_string(),
+            policy_hash: [1u8; 32],
+            policy_uri: "https://vpn.example/no-logs-v1.pdf".to_string(),
+        },
+    )
+}
+
+fn update_profile_accounts(authority: &Pubkey, fixture: &ProviderFixture) -> dvpn::accounts::UpdateProviderProfile {
+    dvpn::accounts::UpdateProviderProfile {
+        authority: *authority,
+        provider: fixture.provider,
+        profile: provider_profile_pda(&fixture.provider),
+    }
+}
+
+#[tokio::test]
+async fn authority_transfer_moves_the_provider_to_the_nominated_key() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &fixture, &user, 1, MINUTES).await;
+    let new_owner = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let outsider = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+
+    assert_dvpn_error(
+        send(&mut ctx, &[accept_transfer_ix(&new_owner.pubkey(), fixture.provider)], &[&new_owner]).await,
+        DvpnError::NoPendingTransfer,
+    );
+    assert_dvpn_error(
+        send(&mut ctx, &[propose_transfer_ix(&outsider.pubkey(), fixture.provider, outsider.pubkey())], &[&outsider]).await,
+        DvpnError::Unauthorized,
+    );
+    let propose = propose_transfer_ix(&fixture.authority.pubkey(), fixture.provider, new_owner.pubkey());
+    send(&mut ctx, &[propose], &[&fixture.authority]).await.unwrap();
+    assert_dvpn_error(
+        send(&mut ctx, &[accept_transfer_ix(&outsider.pubkey(), fixture.provider)], &[&outsider]).await,
+        DvpnError::Unauthorized,
+    );
+
+    // Same PDA, stake and node; new owner and payout address
+    send(&mut ctx, &[accept_transfer_ix(&new_owner.pubkey(), fixture.provider)], &[&new_owner]).await.unwrap();
+    let provider: Provider = fetch(&mut ctx, &fixture.provider).await;
+    assert_eq!(provider.authority, new_owner.pubkey());
+    assert_eq!(provider.payout_address, new_owner.pubkey());
+    assert_eq!(provider.pending_authority, Pubkey::default());
+    assert_eq!(provider.stake_lamports, PROVIDER_STAKE);
+    assert_eq!(provider.node_count, 1);
+
+    // Only the new key can claim the node's sessions
+    advance(&mut ctx, (MINUTES as i64) * 60).await;
+    assert_dvpn_error(
+        send(&mut ctx, &[claim_payout_ix(&fixture, session)], &[&fixture.authority]).await,
+        DvpnError::Unauthorized,
+    );
+    let new_fixture = ProviderFixture { authority: new_owner, provider: fixture.provider, node: fixture.node };
+    send(&mut ctx, &[claim_payout_ix(&new_fixture, session)], &[&new_fixture.authority]).await.unwrap();
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.state == SessionState::Claimed);
+}
+
+#[tokio::test]
+async fn only_policy_changes_bump_the_policy_version() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let authority = fixture.authority.pubkey();
+
+    assert_dvpn_error(
+        send(&mut ctx, &[create_profile_ix(&fixture, &"x".repeat(dvpn::MAX_DISPLAY_NAME_LEN + 1))], &[&fixture.authority]).await,
+        DvpnError::StringTooLong,
+    );
+    send(&mut ctx, &[create_profile_ix(&fixture, "Example VPN")], &[&fixture.authority]).await.unwrap();
+    let profile: ProviderProfile = fetch(&mut ctx, &provider_profile_pda(&fixture.provider)).await;
+    assert_eq!(profile.provider, fixture.provider);
+    assert_eq!(profile.policy_version, 1);
+
+    let rename = |authority: &Pubkey| {
+        ix(
+            update_profile_accounts(authority, &fixture),
+            dvpn::instruction::UpdateProviderProfile {
+                display_name: Some("Example VPN Ltd".to_string()),
+                website: None,
+                jurisdiction: None,
+            },
+        )
+    };
+    let outsider = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    assert_dvpn_error(
+        send(&mut ctx, &[rename(&outsider.pubkey())], &[&outsider]).await,
+        DvpnError::Unauthorized,
+    );
+    send(&mut ctx, &[rename(&authority)], &[&fixture.authority]).await.unwrap();
+    let profile: ProviderProfile = fetch(&mut ctx, &provider_profile_pda(&fixture.provider)).await;
+    assert_eq!(profile.display_name, "Example VPN Ltd");
+    assert_eq!(profile.jurisdiction, "IS");
+    assert_eq!(profile.policy_version, 1);
+
+    // A new policy document is what clients warn subscribers about
+    advance(&mut ctx, 60).await;
+    let new_policy = ix(
+        update_profile_accounts(&authority, &fixture),
+        dvpn::instruction::UpdateNoLogsPolicy {
+            policy_hash: [2u8; 32],
+            policy_uri: "https://vpn.example/no-logs-v2.pdf".to_string(),
+        },
+    );
+    send(&mut ctx, &[new_policy], &[&fixture.authority]).await.unwrap();
+    let updated: ProviderProfile = fetch(&mut ctx, &provider_profile_pda(&fixture.provider)).await;
+    assert_eq!(updated.policy_version, 2);
+    assert_eq!(updated.policy_hash, [2u8; 32]);
+    assert!(updated.policy_updated_at > profile.policy_updated_at);
+}
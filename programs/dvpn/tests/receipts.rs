@@ -0,0 +1,98 @@
+mod common;
+
+use common::*;
+use dvpn::{receipt_message, DvpnError, Session};
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    sysvar,
+};
+
+#[allow(clippy::too_many_arguments)]
+async fn claim_chunk(
+    ctx: &mut ProgramTestContext,
+    claimant: &ProviderFixture,
+    node: Pubkey,
+    session: Pubkey,
+    signer: &Keypair,
+    cumulative_bytes: u64,
+    cumulative_amount: u64,
+    nonce: u64,
+) -> Result<(), BanksClientError> {
+    let message = receipt_message(&session, cumulative_bytes, cumulative_amount, nonce);
+    let verify = ed25519_receipt_ix(signer, &message);
+    let claim = ix(
+        dvpn::accounts::ClaimChunk {
+            authority: claimant.authority.pubkey(),
+            provider: claimant.provider,
+            operator: None,
+            payout: claimant.authority.pubkey(),
+            node,
+            session,
+            treasury: treasury_pda(),
+            config: config_pda(),
+            instructions_sysvar: sysvar::instructions::ID,
+        },
+        dvpn::instruction::ClaimChunk { cumulative_bytes, cumulative_amount, nonce },
+    );
+    send(ctx, &[verify, claim], &[&claimant.authority]).await
+}
+
+#[tokio::test]
+async fn user_signed_receipts_pay_the_session_provider() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &fixture, &user, 1, 10).await;
+    let escrow = 10 * NODE_PRICE_PER_MINUTE;
+
+    let payout_before = lamports(&mut ctx, &fixture.authority.pubkey()).await;
+    claim_chunk(&mut ctx, &fixture, fixture.node, session, &user, 1_000, 200_000, 1).await.unwrap();
+    claim_chunk(&mut ctx, &fixture, fixture.node, session, &user, 3_000, 500_000, 2).await.unwrap();
+
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert_eq!(state.receipt_nonce, 2);
+    assert_eq!(state.bytes_used, 3_000);
+    assert_eq!(state.remaining_balance, escrow - 500_000);
+    // 80% of the receipts; the test payer covers transaction fees
+    let gained = lamports(&mut ctx, &fixture.authority.pubkey()).await - payout_before;
+    assert_eq!(gained, 400_000);
+
+    // Replayed and non-advancing receipts are rejected
+    assert_dvpn_error(
+        claim_chunk(&mut ctx, &fixture, fixture.node, session, &user, 3_000, 500_000, 2).await,
+        DvpnError::StaleReceipt,
+    );
+    // Receipts must be signed by the session's user
+    let stranger = Keypair::new();
+    assert_dvpn_error(
+        claim_chunk(&mut ctx, &fixture, fixture.node, session, &stranger, 4_000, 600_000, 3).await,
+        DvpnError::InvalidReceiptSignature,
+    );
+}
+
+#[tokio::test]
+async fn another_provider_cannot_redeem_receipts() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let adversary = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &fixture, &user, 1, 10).await;
+
+    // Adversary's own node: not the session's node
+    assert_dvpn_error(
+        claim_chunk(&mut ctx, &adversary, adversary.node, session, &user, 1_000, 200_000, 1).await,
+        DvpnError::Unauthorized,
+    );
+    // Victim's node: not the adversary's node
+    assert_dvpn_error(
+        claim_chunk(&mut ctx, &adversary, fixture.node, session, &user, 1_000, 200_000, 1).await,
+        DvpnError::Unauthorized,
+    );
+
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert_eq!(state.remaining_balance, 10 * NODE_PRICE_PER_MINUTE);
+}
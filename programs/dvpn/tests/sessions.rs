@@ -0,0 +1,136 @@
+mod common;
+
+use common::*;
+use dvpn::{DvpnError, Node, Session, SessionState};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::Signer,
+    system_program,
+};
+
+const MINUTES: u32 = 10;
+
+fn extend_session_ix(user: &Pubkey, node: Pubkey, session: Pubkey, minutes: u32) -> Instruction {
+    ix(
+        dvpn::accounts::ExtendSession {
+            user: *user,
+            session,
+            node,
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::ExtendSession { minutes },
+    )
+}
+
+fn migrate_session_ix(
+    user: &Pubkey,
+    old_session: Pubkey,
+    old_node: Pubkey,
+    to: &ProviderFixture,
+    new_session_id: u64,
+) -> Instruction {
+    ix(
+        dvpn::accounts::MigrateSession {
+            user: *user,
+            old_session,
+            old_node,
+            new_provider: to.provider,
+            new_node: to.node,
+            new_session: session_pda(user, &to.node, new_session_id),
+            config: config_pda(),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::MigrateSession { new_session_id },
+    )
+}
+
+#[tokio::test]
+async fn extending_a_session_tops_up_escrow_and_refunds_pro_rata() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &fixture, &user, 1, MINUTES).await;
+    let escrow = MINUTES as u64 * NODE_PRICE_PER_MINUTE;
+    let opened: Session = fetch(&mut ctx, &session).await;
+
+    advance(&mut ctx, (MINUTES as i64) * 30).await;
+    let before = lamports(&mut ctx, &user.pubkey()).await;
+    send(&mut ctx, &[extend_session_ix(&user.pubkey(), fixture.node, session, MINUTES)], &[&user]).await.unwrap();
+    assert_eq!(before - lamports(&mut ctx, &user.pubkey()).await, escrow);
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert_eq!(state.end_ts, opened.end_ts + (MINUTES as i64) * 60);
+    assert_eq!(state.escrow_lamports, 2 * escrow);
+    assert_eq!(state.remaining_balance, 2 * escrow);
+
+    // A quarter of the extended session used: three quarters come back
+    let before = lamports(&mut ctx, &user.pubkey()).await;
+    send(&mut ctx, &[close_session_ix(&user.pubkey(), fixture.node, session)], &[&user]).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &user.pubkey()).await - before, escrow * 3 / 2);
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert_eq!(state.remaining_balance, escrow / 2);
+
+    assert_dvpn_error(
+        send(&mut ctx, &[extend_session_ix(&user.pubkey(), fixture.node, session, MINUTES)], &[&user]).await,
+        DvpnError::SessionNotActive,
+    );
+}
+
+#[tokio::test]
+async fn an_ended_session_cannot_be_extended() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &fixture, &user, 1, MINUTES).await;
+
+    let other = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    assert_dvpn_error(
+        send(&mut ctx, &[extend_session_ix(&other.pubkey(), fixture.node, session, MINUTES)], &[&other]).await,
+        DvpnError::Unauthorized,
+    );
+
+    advance(&mut ctx, (MINUTES as i64) * 60).await;
+    assert_dvpn_error(
+        send(&mut ctx, &[extend_session_ix(&user.pubkey(), fixture.node, session, MINUTES)], &[&user]).await,
+        DvpnError::SessionEnded,
+    );
+}
+
+#[tokio::test]
+async fn failover_moves_unused_escrow_to_a_session_on_the_new_node() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let from = setup_provider(&mut ctx).await;
+    let to = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &from, &user, 1, MINUTES).await;
+    let escrow = MINUTES as u64 * NODE_PRICE_PER_MINUTE;
+
+    assert_dvpn_error(
+        send(&mut ctx, &[migrate_session_ix(&user.pubkey(), session, from.node, &from, 2)], &[&user]).await,
+        DvpnError::SameNode,
+    );
+
+    // 4 of 10 minutes used on the old node; the other 6 carry over at the same price
+    advance(&mut ctx, 4 * 60).await;
+    send(&mut ctx, &[migrate_session_ix(&user.pubkey(), session, from.node, &to, 1)], &[&user]).await.unwrap();
+    let old: Session = fetch(&mut ctx, &session).await;
+    assert!(old.state == SessionState::Closed);
+    assert_eq!(old.remaining_balance, escrow * 2 / 5);
+    let moved: Session = fetch(&mut ctx, &session_pda(&user.pubkey(), &to.node, 1)).await;
+    assert!(moved.state == SessionState::Active);
+    assert_eq!(moved.escrow_lamports, escrow * 3 / 5);
+    assert_eq!(moved.end_ts - moved.start_ts, 6 * 60);
+
+    let old_node: Node = fetch(&mut ctx, &from.node).await;
+    let new_node: Node = fetch(&mut ctx, &to.node).await;
+    assert_eq!(old_node.active_sessions, 0);
+    assert_eq!(new_node.active_sessions, 1);
+
+    // The old provider is paid for the time it served
+    send(&mut ctx, &[claim_payout_ix(&from, session)], &[&from.authority]).await.unwrap();
+    let old: Session = fetch(&mut ctx, &session).await;
+    assert!(old.state == SessionState::Claimed);
+}
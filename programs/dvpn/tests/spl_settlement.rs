@@ -0,0 +1,117 @@
+mod common;
+
+use anchor_spl::token::spl_token;
+use common::*;
+use dvpn::{DvpnError, Node, Session, SessionState};
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Signer};
+
+async fn close(ctx: &mut ProgramTestContext, s: &SplSession) -> Result<(), BanksClientError> {
+    let close = ix(
+        dvpn::accounts::CloseSessionSpl {
+            user: s.user.pubkey(),
+            session: s.session,
+            node: s.provider.node,
+            session_token_account: s.escrow,
+            user_token_account: s.user_token,
+            token_program: spl_token::ID,
+        },
+        dvpn::instruction::CloseSessionSpl {},
+    );
+    send(ctx, &[close], &[&s.user]).await
+}
+
+fn claim_ix(s: &SplSession, escrow: Option<Pubkey>, user_token: Option<Pubkey>) -> Instruction {
+    ix(
+        dvpn::accounts::ClaimPayoutSpl {
+            authority: s.provider.authority.pubkey(),
+            provider: s.provider.provider,
+            operator: None,
+            node: s.provider.node,
+            session: s.session,
+            session_token_account: escrow,
+            provider_token_account: s.provider_token,
+            treasury: treasury_pda(),
+            treasury_token_account: s.treasury_token,
+            user_token_account: user_token,
+            user: s.user.pubkey(),
+            config: config_pda(),
+            token_program: spl_token::ID,
+        },
+        dvpn::instruction::ClaimPayoutSpl {},
+    )
+}
+
+async fn claim(
+    ctx: &mut ProgramTestContext,
+    s: &SplSession,
+    escrow: Option<Pubkey>,
+    user_token: Option<Pubkey>,
+) -> Result<(), BanksClientError> {
+    let claim = claim_ix(s, escrow, user_token);
+    send(ctx, &[claim], &[&s.provider.authority]).await
+}
+
+async fn assert_settled(ctx: &mut ProgramTestContext, s: &SplSession) {
+    let state: Session = fetch(ctx, &s.session).await;
+    assert!(state.state == SessionState::Claimed);
+    assert_eq!(state.remaining_balance, 0);
+    let node: Node = fetch(ctx, &s.provider.node).await;
+    assert_eq!(node.unsettled_sessions, 0);
+    assert!(!exists(ctx, &s.escrow).await);
+}
+
+#[tokio::test]
+async fn close_then_claim_splits_the_used_half() {
+    let mut ctx = start().await;
+    let s = open_spl_session(&mut ctx).await;
+
+    advance(&mut ctx, (SPL_MINUTES as i64) * 30).await;
+    close(&mut ctx, &s).await.unwrap();
+    assert_eq!(token_balance(&mut ctx, &s.user_token).await, SPL_ESCROW / 2);
+
+    let user_lamports = lamports(&mut ctx, &s.user.pubkey()).await;
+    let escrow_rent = lamports(&mut ctx, &s.escrow).await;
+    claim(&mut ctx, &s, Some(s.escrow), None).await.unwrap();
+
+    assert_eq!(token_balance(&mut ctx, &s.provider_token).await, SPL_ESCROW / 2 * 4 / 5);
+    assert_eq!(token_balance(&mut ctx, &s.treasury_token).await, SPL_ESCROW / 2 / 5);
+    assert_eq!(lamports(&mut ctx, &s.user.pubkey()).await, user_lamports + escrow_rent);
+    assert_settled(&mut ctx, &s).await;
+}
+
+#[tokio::test]
+async fn full_refund_closes_escrow_and_claim_still_settles() {
+    let mut ctx = start().await;
+    let s = open_spl_session(&mut ctx).await;
+
+    // Dust sent straight to the escrow must not block closing it
+    mint_to(&mut ctx, &s.mint, &s.escrow, 1).await;
+    close(&mut ctx, &s).await.unwrap();
+    assert_eq!(token_balance(&mut ctx, &s.user_token).await, SPL_ESCROW + 1);
+    assert!(!exists(&mut ctx, &s.escrow).await);
+
+    claim(&mut ctx, &s, None, None).await.unwrap();
+    assert_eq!(token_balance(&mut ctx, &s.provider_token).await, 0);
+    assert_settled(&mut ctx, &s).await;
+}
+
+#[tokio::test]
+async fn claim_sweeps_dust_back_to_the_user() {
+    let mut ctx = start().await;
+    let s = open_spl_session(&mut ctx).await;
+
+    advance(&mut ctx, (SPL_MINUTES as i64) * 60).await;
+    mint_to(&mut ctx, &s.mint, &s.escrow, 7).await;
+
+    // Dust needs somewhere to go
+    assert_dvpn_error(claim(&mut ctx, &s, Some(s.escrow), None).await, DvpnError::TokenAccountMissing);
+    // An unpaid balance can't be settled without the escrow
+    assert_dvpn_error(claim(&mut ctx, &s, None, None).await, DvpnError::TokenAccountMissing);
+
+    claim(&mut ctx, &s, Some(s.escrow), Some(s.user_token)).await.unwrap();
+    assert_eq!(token_balance(&mut ctx, &s.provider_token).await, SPL_ESCROW * 4 / 5);
+    assert_eq!(token_balance(&mut ctx, &s.treasury_token).await, SPL_ESCROW / 5);
+    assert_eq!(token_balance(&mut ctx, &s.user_token).await, 7);
+    assert_settled(&mut ctx, &s).await;
+}
@@ -0,0 +1,251 @@
+mod common;
+
+use common::*;
+use dvpn::{ConfigParam, Delegation, DvpnError, Provider, DELEGATION_SEED};
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+};
+
+const DAY: i64 = 24 * 60 * 60;
+
+async fn request_unstake(
+    ctx: &mut ProgramTestContext,
+    fixture: &ProviderFixture,
+    amount_lamports: u64,
+) -> Result<(), BanksClientError> {
+    let request = ix(
+        dvpn::accounts::RequestUnstake {
+            authority: fixture.authority.pubkey(),
+            provider: fixture.provider,
+            config: config_pda(),
+        },
+        dvpn::instruction::RequestUnstake { amount_lamports },
+    );
+    send(ctx, &[request], &[&fixture.authority]).await
+}
+
+async fn withdraw_unstaked(ctx: &mut ProgramTestContext, fixture: &ProviderFixture) -> Result<(), BanksClientError> {
+    let withdraw = ix(
+        dvpn::accounts::WithdrawUnstaked {
+            authority: fixture.authority.pubkey(),
+            provider: fixture.provider,
+        },
+        dvpn::instruction::WithdrawUnstaked {},
+    );
+    send(ctx, &[withdraw], &[&fixture.authority]).await
+}
+
+fn update_delegation(delegator: &Pubkey, provider: Pubkey) -> dvpn::accounts::UpdateDelegation {
+    dvpn::accounts::UpdateDelegation {
+        delegator: *delegator,
+        provider,
+        delegation: delegation_pda(&provider, delegator),
+        config: config_pda(),
+    }
+}
+
+fn delegation_pda(provider: &Pubkey, delegator: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[DELEGATION_SEED, provider.as_ref(), delegator.as_ref()], &dvpn::ID).0
+}
+
+fn delegate_ix(delegator: &Pubkey, provider: Pubkey, amount_lamports: u64) -> Instruction {
+    ix(
+        dvpn::accounts::DelegateStake {
+            delegator: *delegator,
+            provider,
+            delegation: delegation_pda(&provider, delegator),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::DelegateStake { amount_lamports },
+    )
+}
+
+#[tokio::test]
+async fn unstake_waits_for_the_configured_unbonding_period() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let admin = ctx.payer.pubkey();
+
+    assert_dvpn_error(
+        send(&mut ctx, &[update_config_ix(&admin, ConfigParam::UnbondingPeriodSeconds(60))], &[]).await,
+        DvpnError::ConfigOutOfRange,
+    );
+    send(&mut ctx, &[update_config_ix(&admin, ConfigParam::UnbondingPeriodSeconds(2 * DAY))], &[])
+        .await
+        .unwrap();
+
+    // Stake may not drop below base + per-node + per-capacity minimum
+    assert_dvpn_error(
+        request_unstake(&mut ctx, &fixture, PROVIDER_STAKE - 100_000_000).await,
+        DvpnError::BelowMinimumStake,
+    );
+
+    let amount = PROVIDER_STAKE / 2;
+    request_unstake(&mut ctx, &fixture, amount).await.unwrap();
+    let provider: Provider = fetch(&mut ctx, &fixture.provider).await;
+    assert_eq!(provider.stake_lamports, PROVIDER_STAKE - amount);
+    assert_eq!(provider.unbonding_lamports, amount);
+    assert_eq!(provider.unbonding_unlock_ts, now(&mut ctx).await + 2 * DAY);
+
+    advance(&mut ctx, DAY).await;
+    assert_dvpn_error(withdraw_unstaked(&mut ctx, &fixture).await, DvpnError::StillUnbonding);
+
+    advance(&mut ctx, DAY).await;
+    let before = lamports(&mut ctx, &fixture.authority.pubkey()).await;
+    withdraw_unstaked(&mut ctx, &fixture).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &fixture.authority.pubkey()).await - before, amount);
+    assert_dvpn_error(withdraw_unstaked(&mut ctx, &fixture).await, DvpnError::NothingUnbonding);
+}
+
+#[tokio::test]
+async fn undelegation_unbonds_for_the_configured_period() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let delegator: Keypair = funded_keypair(&mut ctx, 5 * LAMPORTS_PER_SOL).await;
+    let delegation = delegation_pda(&fixture.provider, &delegator.pubkey());
+
+    send(&mut ctx, &[delegate_ix(&delegator.pubkey(), fixture.provider, LAMPORTS_PER_SOL)], &[&delegator])
+        .await
+        .unwrap();
+    let shares = fetch::<Delegation>(&mut ctx, &delegation).await.shares;
+
+    let undelegate = ix(
+        update_delegation(&delegator.pubkey(), fixture.provider),
+        dvpn::instruction::RequestUndelegate { shares },
+    );
+    send(&mut ctx, &[undelegate], &[&delegator]).await.unwrap();
+    let state: Delegation = fetch(&mut ctx, &delegation).await;
+    assert_eq!(state.shares, 0);
+    assert_eq!(state.unbonding_unlock_ts, now(&mut ctx).await + dvpn::DEFAULT_UNBONDING_PERIOD_SECONDS);
+
+    let withdraw = ix(
+        update_delegation(&delegator.pubkey(), fixture.provider),
+        dvpn::instruction::WithdrawUndelegated {},
+    );
+    assert_dvpn_error(send(&mut ctx, std::slice::from_ref(&withdraw), &[&delegator]).await, DvpnError::StillUnbonding);
+
+    advance(&mut ctx, dvpn::DEFAULT_UNBONDING_PERIOD_SECONDS).await;
+    let before = lamports(&mut ctx, &delegator.pubkey()).await;
+    send(&mut ctx, &[withdraw], &[&delegator]).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &delegator.pubkey()).await - before, LAMPORTS_PER_SOL);
+    let provider: Provider = fetch(&mut ctx, &fixture.provider).await;
+    assert_eq!(provider.delegated_unbonding_lamports, 0);
+}
+
+#[tokio::test]
+async fn undelegation_is_refused_while_the_unbonding_pool_is_slashed_to_zero() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    init_insurance_fund(&mut ctx).await;
+    init_arbiter_registry(&mut ctx, 1).await;
+    let arbiter = Keypair::new();
+    add_arbiters(&mut ctx, &[arbiter.pubkey()]).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let early = funded_keypair(&mut ctx, 5 * LAMPORTS_PER_SOL).await;
+    let late = funded_keypair(&mut ctx, 5 * LAMPORTS_PER_SOL).await;
+    let undelegate = |delegator: &Keypair, shares| {
+        ix(update_delegation(&delegator.pubkey(), fixture.provider), dvpn::instruction::RequestUndelegate { shares })
+    };
+    let withdraw = |delegator: &Keypair| {
+        ix(update_delegation(&delegator.pubkey(), fixture.provider), dvpn::instruction::WithdrawUndelegated {})
+    };
+
+    // `early` starts unbonding, then a ruling slashes every bucket to zero
+    send(&mut ctx, &[delegate_ix(&early.pubkey(), fixture.provider, LAMPORTS_PER_SOL)], &[&early]).await.unwrap();
+    send(&mut ctx, &[undelegate(&early, LAMPORTS_PER_SOL)], &[&early]).await.unwrap();
+    let user = funded_keypair(&mut ctx, LAMPORTS_PER_SOL).await;
+    let session = open_session(&mut ctx, &fixture, &user, 1, 10).await;
+    send(&mut ctx, &[raise_dispute_ix(&fixture, &user.pubkey(), session)], &[&user]).await.unwrap();
+    let payer = ctx.payer.pubkey();
+    let assign = assign_arbiters_ix(&payer, session, vec![arbiter.pubkey()]);
+    let vote = dispute_vote_ix(&arbiter.pubkey(), session, 0, u64::MAX);
+    send(&mut ctx, &[assign, vote], &[&arbiter]).await.unwrap();
+    let resolve = resolve_dispute_ix(&payer, &fixture, session, user.pubkey(), user.pubkey());
+    send(&mut ctx, &[resolve], &[]).await.unwrap();
+    let provider: Provider = fetch(&mut ctx, &fixture.provider).await;
+    assert_eq!(provider.delegated_unbonding_lamports, 0);
+    assert_eq!(provider.delegated_unbonding_shares, LAMPORTS_PER_SOL);
+
+    // `late` can't join a pool whose shares are worthless
+    send(&mut ctx, &[delegate_ix(&late.pubkey(), fixture.provider, LAMPORTS_PER_SOL)], &[&late]).await.unwrap();
+    assert_dvpn_error(
+        send(&mut ctx, &[undelegate(&late, LAMPORTS_PER_SOL)], &[&late]).await,
+        DvpnError::DelegationPoolDepleted,
+    );
+
+    // Once the dead shares are withdrawn the pool prices fresh shares again
+    advance(&mut ctx, dvpn::DEFAULT_UNBONDING_PERIOD_SECONDS).await;
+    send(&mut ctx, &[withdraw(&early)], &[&early]).await.unwrap();
+    send(&mut ctx, &[undelegate(&late, LAMPORTS_PER_SOL)], &[&late]).await.unwrap();
+    advance(&mut ctx, dvpn::DEFAULT_UNBONDING_PERIOD_SECONDS).await;
+    let before = lamports(&mut ctx, &late.pubkey()).await;
+    send(&mut ctx, &[withdraw(&late)], &[&late]).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &late.pubkey()).await - before, LAMPORTS_PER_SOL);
+}
+
+#[tokio::test]
+async fn delegated_stake_does_not_count_toward_the_minimum() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let delegator = funded_keypair(&mut ctx, 10 * LAMPORTS_PER_SOL).await;
+    send(&mut ctx, &[delegate_ix(&delegator.pubkey(), fixture.provider, 5 * LAMPORTS_PER_SOL)], &[&delegator])
+        .await
+        .unwrap();
+
+    let provider: Provider = fetch(&mut ctx, &fixture.provider).await;
+    let config: dvpn::ProgramConfig = fetch(&mut ctx, &config_pda()).await;
+    let required = provider.required_stake(&config);
+    assert_dvpn_error(
+        request_unstake(&mut ctx, &fixture, PROVIDER_STAKE - required + 1).await,
+        DvpnError::BelowMinimumStake,
+    );
+    request_unstake(&mut ctx, &fixture, PROVIDER_STAKE - required).await.unwrap();
+}
+
+#[tokio::test]
+async fn minimum_stake_follows_program_config() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let admin = ctx.payer.pubkey();
+
+    assert_dvpn_error(
+        send(&mut ctx, &[update_config_ix(&admin, ConfigParam::MinStakeBaseLamports(0))], &[]).await,
+        DvpnError::ConfigOutOfRange,
+    );
+    assert_dvpn_error(
+        send(&mut ctx, &[update_config_ix(&admin, ConfigParam::MinStakePerNodeLamports(100 * LAMPORTS_PER_SOL))], &[])
+            .await,
+        DvpnError::ConfigOutOfRange,
+    );
+
+    // 0.1 base + 0.9 per node + 10 * 0.001 capacity exceeds the 1 SOL staked
+    send(
+        &mut ctx,
+        &[update_config_ix(&admin, ConfigParam::MinStakePerNodeLamports(900_000_000))],
+        &[],
+    )
+    .await
+    .unwrap();
+    let provider: Provider = fetch(&mut ctx, &fixture.provider).await;
+    let config: dvpn::ProgramConfig = fetch(&mut ctx, &config_pda()).await;
+    assert_eq!(provider.required_stake(&config), 1_010_000_000);
+    assert_dvpn_error(request_unstake(&mut ctx, &fixture, 1).await, DvpnError::BelowMinimumStake);
+
+    // Minimum drops to 0.9 SOL once the capacity term is gone and the node term lowered
+    send(&mut ctx, &[update_config_ix(&admin, ConfigParam::MinStakePerCapacityLamports(0))], &[])
+        .await
+        .unwrap();
+    send(&mut ctx, &[update_config_ix(&admin, ConfigParam::MinStakePerNodeLamports(800_000_000))], &[])
+        .await
+        .unwrap();
+    request_unstake(&mut ctx, &fixture, 100_000_000).await.unwrap();
+    assert_dvpn_error(request_unstake(&mut ctx, &fixture, 1).await, DvpnError::BelowMinimumStake);
+}
@@ -0,0 +1,183 @@
+mod common;
+
+use common::*;
+use dvpn::{DvpnError, Session, SubscriptionPlan, SUBSCRIPTION_SEED, USER_VAULT_SEED};
+use solana_program_test::ProgramTestContext;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+};
+
+const MINUTES: u32 = 10;
+
+fn user_vault_pda(user: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[USER_VAULT_SEED, user.as_ref()], &dvpn::ID).0
+}
+
+fn subscription_pda(user: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[SUBSCRIPTION_SEED, user.as_ref()], &dvpn::ID).0
+}
+
+// Vault with the given SOL daily limit, holding `deposit` lamports
+async fn init_vault(ctx: &mut ProgramTestContext, user: &Keypair, daily_limit_lamports: u64, deposit: u64) -> Pubkey {
+    let user_vault = user_vault_pda(&user.pubkey());
+    let init = ix(
+        dvpn::accounts::InitializeUserVault {
+            user: user.pubkey(),
+            user_vault,
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::InitializeUserVault { daily_limit_lamports, daily_limit_tokens: 0 },
+    );
+    let fund = ix(
+        dvpn::accounts::DepositVault {
+            user: user.pubkey(),
+            user_vault,
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::DepositVault { amount_lamports: deposit },
+    );
+    send(ctx, &[init, fund], &[user]).await.unwrap();
+    user_vault
+}
+
+fn open_from_vault_ix(fixture: &ProviderFixture, user: &Pubkey, session_id: u64, minutes: u32) -> Instruction {
+    ix(
+        dvpn::accounts::OpenSessionFromVault {
+            user: *user,
+            user_vault: user_vault_pda(user),
+            provider: fixture.provider,
+            node: fixture.node,
+            session: session_pda(user, &fixture.node, session_id),
+            config: config_pda(),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::OpenSessionFromVault { session_id, minutes },
+    )
+}
+
+fn withdraw_vault_ix(user: &Pubkey, amount_lamports: u64) -> Instruction {
+    ix(
+        dvpn::accounts::UpdateUserVault { user: *user, user_vault: user_vault_pda(user) },
+        dvpn::instruction::WithdrawVault { amount_lamports },
+    )
+}
+
+#[tokio::test]
+async fn vault_funds_sessions_within_its_daily_limit_and_takes_refunds_back() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let fixture = setup_provider(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, 2 * LAMPORTS_PER_SOL).await;
+    let escrow = MINUTES as u64 * NODE_PRICE_PER_MINUTE;
+    let limit = escrow + escrow / 2;
+
+    let wallet_before = lamports(&mut ctx, &user.pubkey()).await;
+    let vault = init_vault(&mut ctx, &user, limit, LAMPORTS_PER_SOL).await;
+    let rent = lamports(&mut ctx, &vault).await - LAMPORTS_PER_SOL;
+    assert!(wallet_before - lamports(&mut ctx, &user.pubkey()).await >= LAMPORTS_PER_SOL + rent);
+
+    send(&mut ctx, &[open_from_vault_ix(&fixture, &user.pubkey(), 1, MINUTES)], &[&user]).await.unwrap();
+    let session = session_pda(&user.pubkey(), &fixture.node, 1);
+    assert_eq!(lamports(&mut ctx, &vault).await, rent + LAMPORTS_PER_SOL - escrow);
+    assert_dvpn_error(
+        send(&mut ctx, &[open_from_vault_ix(&fixture, &user.pubkey(), 2, MINUTES)], &[&user]).await,
+        DvpnError::DailyLimitExceeded,
+    );
+
+    // Vault-funded sessions are topped up from the vault, not the wallet
+    let extend = ix(
+        dvpn::accounts::ExtendSession {
+            user: user.pubkey(),
+            session,
+            node: fixture.node,
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::ExtendSession { minutes: MINUTES / 2 },
+    );
+    assert_dvpn_error(send(&mut ctx, &[extend], &[&user]).await, DvpnError::VaultFundedSession);
+    let extend = ix(
+        dvpn::accounts::ExtendSessionFromVault {
+            user: user.pubkey(),
+            user_vault: vault,
+            session,
+            node: fixture.node,
+        },
+        dvpn::instruction::ExtendSessionFromVault { minutes: MINUTES / 2 },
+    );
+    send(&mut ctx, &[extend], &[&user]).await.unwrap();
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert_eq!(state.escrow_lamports, limit);
+
+    // The unused escrow comes back to the vault, not the wallet
+    let vault_before = lamports(&mut ctx, &vault).await;
+    let close = ix(
+        dvpn::accounts::CloseSession {
+            user: user.pubkey(),
+            session,
+            node: fixture.node,
+            refund_to: vault,
+        },
+        dvpn::instruction::CloseSession {},
+    );
+    send(&mut ctx, &[close], &[&user]).await.unwrap();
+    let state: Session = fetch(&mut ctx, &session).await;
+    assert!(state.refunded_amount > 0);
+    assert_eq!(lamports(&mut ctx, &vault).await - vault_before, state.refunded_amount);
+
+    // Withdrawals can't dip into the vault's rent
+    let available = lamports(&mut ctx, &vault).await - rent;
+    assert_dvpn_error(
+        send(&mut ctx, &[withdraw_vault_ix(&user.pubkey(), available + 1)], &[&user]).await,
+        DvpnError::InsufficientBalance,
+    );
+    let wallet_before = lamports(&mut ctx, &user.pubkey()).await;
+    send(&mut ctx, &[withdraw_vault_ix(&user.pubkey(), available)], &[&user]).await.unwrap();
+    assert_eq!(lamports(&mut ctx, &vault).await, rent);
+    assert!(lamports(&mut ctx, &user.pubkey()).await > wallet_before);
+}
+
+#[tokio::test]
+async fn cancelling_a_vault_subscription_refunds_the_vault() {
+    let mut ctx = start().await;
+    init_program(&mut ctx).await;
+    let user = funded_keypair(&mut ctx, 2 * LAMPORTS_PER_SOL).await;
+    let vault = init_vault(&mut ctx, &user, 0, LAMPORTS_PER_SOL).await;
+    let subscription = subscription_pda(&user.pubkey());
+
+    let create = ix(
+        dvpn::accounts::CreateSubscriptionFromVault {
+            user: user.pubkey(),
+            user_vault: vault,
+            subscription,
+            config: config_pda(),
+            system_program: system_program::ID,
+        },
+        dvpn::instruction::CreateSubscriptionFromVault { plan: SubscriptionPlan::Weekly },
+    );
+    send(&mut ctx, &[create], &[&user]).await.unwrap();
+
+    let cancel = |refund_to| {
+        ix(
+            dvpn::accounts::CancelSubscription {
+                user: user.pubkey(),
+                subscription,
+                treasury: treasury_pda(),
+                refund_to,
+            },
+            dvpn::instruction::CancelSubscription {},
+        )
+    };
+    assert_dvpn_error(send(&mut ctx, &[cancel(user.pubkey())], &[&user]).await, DvpnError::Unauthorized);
+
+    advance(&mut ctx, 24 * 60 * 60).await;
+    let vault_before = lamports(&mut ctx, &vault).await;
+    let treasury_before = lamports(&mut ctx, &treasury_pda()).await;
+    send(&mut ctx, &[cancel(vault)], &[&user]).await.unwrap();
+    let refund = lamports(&mut ctx, &vault).await - vault_before;
+    let used = lamports(&mut ctx, &treasury_pda()).await - treasury_before;
+    assert!(refund > 0 && used > 0);
+    assert_eq!(refund + used, dvpn::DEFAULT_WEEKLY_PRICE_LAMPORTS);
+}